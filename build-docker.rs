@@ -1,3 +1,4 @@
 fn main() {
     tonic_build::compile_protos("proto/newsrecommend.proto").unwrap();
+    println!("cargo:rerun-if-changed=migrations");
 }
@@ -0,0 +1,6 @@
+DROP TABLE news_tag;
+DROP TABLE interest;
+DROP TABLE history;
+DROP TABLE tag;
+DROP TABLE news;
+DROP TABLE users;
@@ -55,13 +55,3 @@ CREATE INDEX idx_interest_news_tag ON interest(news_tag);
 CREATE INDEX idx_news_title ON news(title);
 CREATE INDEX idx_news_likes ON news(likes);
 CREATE INDEX idx_news_tag_news_id ON news_tag(news_id);
-
-
--- fix1
-ALTER TABLE interest ADD CONSTRAINT interest_user_id_news_tag_key UNIQUE (user_id, news_tag);
-
--- fix2
-ALTER TABLE news_tag ADD CONSTRAINT interest_tag_name_news_id_key UNIQUE (tag_name, news_id);
-
--- fix3
-ALTER TABLE history ADD CONSTRAINT interest_user_id_news_id_key UNIQUE (user_id, news_id);
\ No newline at end of file
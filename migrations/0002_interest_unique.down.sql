@@ -0,0 +1 @@
+ALTER TABLE interest DROP CONSTRAINT interest_user_id_news_tag_key;
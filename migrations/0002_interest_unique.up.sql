@@ -0,0 +1 @@
+ALTER TABLE interest ADD CONSTRAINT interest_user_id_news_tag_key UNIQUE (user_id, news_tag);
@@ -0,0 +1 @@
+ALTER TABLE news_tag DROP CONSTRAINT interest_tag_name_news_id_key;
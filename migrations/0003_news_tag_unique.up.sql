@@ -0,0 +1 @@
+ALTER TABLE news_tag ADD CONSTRAINT interest_tag_name_news_id_key UNIQUE (tag_name, news_id);
@@ -0,0 +1 @@
+ALTER TABLE history DROP CONSTRAINT interest_user_id_news_id_key;
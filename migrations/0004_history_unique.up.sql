@@ -0,0 +1 @@
+ALTER TABLE history ADD CONSTRAINT interest_user_id_news_id_key UNIQUE (user_id, news_id);
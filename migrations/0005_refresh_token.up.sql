@@ -0,0 +1,9 @@
+CREATE TABLE refresh_token (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  token_hash VARCHAR(64) UNIQUE NOT NULL,
+  create_time TIMESTAMP NOT NULL DEFAULT now(),
+  expire_time TIMESTAMP NOT NULL,
+  revoked BOOLEAN NOT NULL DEFAULT false
+);
+CREATE INDEX idx_refresh_token_user_id ON refresh_token(user_id);
@@ -0,0 +1,2 @@
+DROP TABLE user_token_revocation;
+DROP TABLE revoked_token;
@@ -0,0 +1,9 @@
+CREATE TABLE revoked_token (
+  jti VARCHAR(64) PRIMARY KEY,
+  expire_time TIMESTAMP NOT NULL
+);
+
+CREATE TABLE user_token_revocation (
+  user_id INTEGER PRIMARY KEY REFERENCES users(id),
+  revoke_before BIGINT NOT NULL
+);
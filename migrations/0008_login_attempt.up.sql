@@ -0,0 +1,6 @@
+CREATE TABLE login_attempt (
+  key VARCHAR(511) PRIMARY KEY,
+  failures INTEGER NOT NULL DEFAULT 0,
+  last_failure TIMESTAMP NOT NULL DEFAULT now(),
+  locked_until TIMESTAMP
+);
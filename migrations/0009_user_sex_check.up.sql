@@ -0,0 +1 @@
+ALTER TABLE users ADD CONSTRAINT users_sex_check CHECK (sex IN ('man', 'woman', 'unknown'));
@@ -0,0 +1,3 @@
+DROP TABLE email_token;
+ALTER TABLE users DROP COLUMN email_verified;
+ALTER TABLE users DROP COLUMN email;
@@ -0,0 +1,13 @@
+ALTER TABLE users ADD COLUMN email VARCHAR(255) UNIQUE;
+ALTER TABLE users ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE email_token (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  purpose VARCHAR(32) NOT NULL CHECK (purpose IN ('verify_email', 'password_reset')),
+  token_hash VARCHAR(64) NOT NULL UNIQUE,
+  create_time TIMESTAMP NOT NULL DEFAULT now(),
+  expire_time TIMESTAMP NOT NULL,
+  used BOOLEAN NOT NULL DEFAULT false
+);
+CREATE INDEX idx_email_token_user_id ON email_token(user_id);
@@ -0,0 +1,13 @@
+CREATE TABLE user_session (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  user_agent VARCHAR(511) NOT NULL DEFAULT '',
+  ip VARCHAR(64) NOT NULL DEFAULT '',
+  create_time TIMESTAMP NOT NULL DEFAULT now(),
+  last_seen TIMESTAMP NOT NULL DEFAULT now(),
+  revoked BOOLEAN NOT NULL DEFAULT false
+);
+CREATE INDEX idx_user_session_user_id ON user_session(user_id);
+
+ALTER TABLE refresh_token ADD COLUMN session_id INTEGER REFERENCES user_session(id);
+CREATE INDEX idx_refresh_token_session_id ON refresh_token(session_id);
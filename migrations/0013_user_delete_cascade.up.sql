@@ -0,0 +1,18 @@
+ALTER TABLE users ADD COLUMN deleted_time TIMESTAMP;
+
+ALTER TABLE history DROP CONSTRAINT history_user_id_fkey,
+  ADD CONSTRAINT history_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE interest DROP CONSTRAINT interest_user_id_fkey,
+  ADD CONSTRAINT interest_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE refresh_token DROP CONSTRAINT refresh_token_user_id_fkey,
+  ADD CONSTRAINT refresh_token_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE refresh_token DROP CONSTRAINT refresh_token_session_id_fkey,
+  ADD CONSTRAINT refresh_token_session_id_fkey FOREIGN KEY (session_id) REFERENCES user_session(id) ON DELETE CASCADE;
+ALTER TABLE user_token_revocation DROP CONSTRAINT user_token_revocation_user_id_fkey,
+  ADD CONSTRAINT user_token_revocation_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE recovery_code DROP CONSTRAINT recovery_code_user_id_fkey,
+  ADD CONSTRAINT recovery_code_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE email_token DROP CONSTRAINT email_token_user_id_fkey,
+  ADD CONSTRAINT email_token_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE user_session DROP CONSTRAINT user_session_user_id_fkey,
+  ADD CONSTRAINT user_session_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
@@ -0,0 +1,12 @@
+CREATE TABLE api_key (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR(255) NOT NULL,
+  key_prefix VARCHAR(16) NOT NULL,
+  key_hash VARCHAR(64) UNIQUE NOT NULL,
+  scopes VARCHAR(32)[] NOT NULL,
+  created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  create_time TIMESTAMP NOT NULL DEFAULT now(),
+  expire_time TIMESTAMP,
+  last_used TIMESTAMP,
+  revoked BOOLEAN NOT NULL DEFAULT false
+);
@@ -0,0 +1,13 @@
+CREATE TABLE audit_log (
+  id BIGSERIAL PRIMARY KEY,
+  create_time TIMESTAMP NOT NULL DEFAULT now(),
+  action VARCHAR(64) NOT NULL,
+  actor_user_id INTEGER,
+  actor_api_key_id INTEGER,
+  target VARCHAR(255),
+  ip VARCHAR(64) NOT NULL DEFAULT '',
+  payload JSONB NOT NULL DEFAULT '{}'
+);
+CREATE INDEX idx_audit_log_create_time ON audit_log(create_time);
+CREATE INDEX idx_audit_log_actor_user_id ON audit_log(actor_user_id);
+CREATE INDEX idx_audit_log_action ON audit_log(action);
@@ -0,0 +1,7 @@
+CREATE TABLE news_like (
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  news_id INTEGER NOT NULL REFERENCES news(id) ON DELETE CASCADE,
+  create_time TIMESTAMP NOT NULL DEFAULT now(),
+  PRIMARY KEY (user_id, news_id)
+);
+CREATE INDEX idx_news_like_news_id ON news_like(news_id);
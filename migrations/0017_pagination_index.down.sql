@@ -0,0 +1,2 @@
+DROP INDEX idx_news_create_time;
+DROP INDEX idx_history_user_id_last_view_time;
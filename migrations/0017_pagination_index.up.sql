@@ -0,0 +1,2 @@
+CREATE INDEX idx_history_user_id_last_view_time ON history(user_id, last_view_time DESC, id DESC);
+CREATE INDEX idx_news_create_time ON news(create_time DESC, id DESC);
@@ -0,0 +1,5 @@
+DROP INDEX idx_news_search_vector;
+ALTER TABLE news DROP COLUMN search_vector;
+DROP FUNCTION nrs_tsquery(TEXT);
+DROP FUNCTION nrs_tsvector(TEXT, "char");
+DROP FUNCTION nrs_search_terms(TEXT);
@@ -0,0 +1,88 @@
+-- 全文搜索分词：英文与数字按单词切分并转为小写，中日韩文字按相邻两字（bigram）切分
+-- 直接拼接 tsvector / tsquery 文本，不依赖数据库 locale 以及中文分词插件
+CREATE FUNCTION nrs_search_terms(doc TEXT) RETURNS TEXT[] AS $$
+DECLARE
+  c TEXT;
+  code INTEGER;
+  kind INTEGER;
+  last_kind INTEGER := 0;
+  term TEXT := '';
+  terms TEXT[] := '{}';
+BEGIN
+  FOREACH c IN ARRAY string_to_array(lower(COALESCE(doc, '')), NULL) LOOP
+    code := ascii(c);
+    IF (c >= 'a' AND c <= 'z') OR (c >= '0' AND c <= '9') THEN
+      kind := 1;
+    ELSIF code BETWEEN 12352 AND 12543 OR code BETWEEN 13312 AND 40959
+      OR code BETWEEN 44032 AND 55215 OR code BETWEEN 63744 AND 64255 THEN
+      kind := 2;
+    ELSE
+      kind := 0;
+    END IF;
+    IF kind <> last_kind AND term <> '' THEN
+      terms := terms || term;
+      term := '';
+    END IF;
+    IF kind <> 0 THEN
+      term := term || c;
+    END IF;
+    last_kind := kind;
+  END LOOP;
+  IF term <> '' THEN
+    terms := terms || term;
+  END IF;
+  RETURN terms;
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+CREATE FUNCTION nrs_tsvector(doc TEXT, weight "char") RETURNS tsvector AS $$
+DECLARE
+  term TEXT;
+  pos INTEGER := 0;
+  i INTEGER;
+  lexemes TEXT[] := '{}';
+BEGIN
+  FOREACH term IN ARRAY nrs_search_terms(doc) LOOP
+    IF ascii(term) < 128 OR char_length(term) = 1 THEN
+      pos := pos + 1;
+      lexemes := lexemes || (quote_literal(term) || ':' || pos);
+    ELSE
+      FOR i IN 1 .. char_length(term) - 1 LOOP
+        pos := pos + 1;
+        lexemes := lexemes || (quote_literal(substr(term, i, 2)) || ':' || pos);
+      END LOOP;
+    END IF;
+  END LOOP;
+  RETURN setweight(array_to_string(lexemes, ' ')::tsvector, weight);
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- 中日韩文字按 bigram 短语匹配，单个字按前缀匹配
+CREATE FUNCTION nrs_tsquery(query TEXT) RETURNS tsquery AS $$
+DECLARE
+  term TEXT;
+  i INTEGER;
+  grams TEXT[];
+  parts TEXT[] := '{}';
+BEGIN
+  FOREACH term IN ARRAY nrs_search_terms(query) LOOP
+    IF ascii(term) < 128 THEN
+      parts := parts || quote_literal(term);
+    ELSIF char_length(term) = 1 THEN
+      parts := parts || (quote_literal(term) || ':*');
+    ELSE
+      grams := '{}';
+      FOR i IN 1 .. char_length(term) - 1 LOOP
+        grams := grams || quote_literal(substr(term, i, 2));
+      END LOOP;
+      parts := parts || ('(' || array_to_string(grams, ' <-> ') || ')');
+    END IF;
+  END LOOP;
+  RETURN array_to_string(parts, ' & ')::tsquery;
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+ALTER TABLE news ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+  nrs_tsvector(title, 'A') || nrs_tsvector(abstracts, 'B') || nrs_tsvector(content, 'C')
+) STORED;
+CREATE INDEX idx_news_search_vector ON news USING GIN (search_vector);
@@ -0,0 +1,2 @@
+DROP INDEX idx_news_status;
+ALTER TABLE news DROP COLUMN status;
@@ -0,0 +1,2 @@
+ALTER TABLE news ADD COLUMN status VARCHAR(16) NOT NULL DEFAULT 'published';
+CREATE INDEX idx_news_status ON news(status);
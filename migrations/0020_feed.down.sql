@@ -0,0 +1,3 @@
+DROP INDEX idx_news_link;
+DROP TABLE feed_item;
+DROP TABLE feed;
@@ -0,0 +1,22 @@
+CREATE TABLE feed (
+  id SERIAL PRIMARY KEY,
+  url TEXT NOT NULL UNIQUE,
+  title TEXT,
+  last_fetch_time TIMESTAMP,
+  last_success_time TIMESTAMP,
+  last_status VARCHAR(16),
+  last_error TEXT,
+  last_inserted INTEGER NOT NULL DEFAULT 0,
+  total_inserted INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE feed_item (
+  feed_id INTEGER NOT NULL REFERENCES feed(id) ON DELETE CASCADE,
+  guid TEXT NOT NULL,
+  link TEXT NOT NULL,
+  news_id INTEGER REFERENCES news(id) ON DELETE SET NULL,
+  create_time TIMESTAMP NOT NULL DEFAULT now(),
+  PRIMARY KEY (feed_id, guid)
+);
+CREATE INDEX idx_feed_item_link ON feed_item(link);
+CREATE INDEX idx_news_link ON news(link);
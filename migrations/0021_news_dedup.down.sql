@@ -0,0 +1,5 @@
+-- 摘要重新设为唯一，存在重复摘要时需要先手动处理
+DROP INDEX idx_news_content_hash;
+ALTER TABLE news DROP COLUMN simhash;
+ALTER TABLE news DROP COLUMN content_hash;
+ALTER TABLE news ADD CONSTRAINT news_abstracts_key UNIQUE (abstracts);
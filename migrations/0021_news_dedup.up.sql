@@ -0,0 +1,5 @@
+-- 按规范化正文的 hash 去重，旧数据的指纹由服务启动时补充
+ALTER TABLE news DROP CONSTRAINT news_abstracts_key;
+ALTER TABLE news ADD COLUMN content_hash CHAR(64);
+ALTER TABLE news ADD COLUMN simhash BIGINT;
+CREATE UNIQUE INDEX idx_news_content_hash ON news(content_hash);
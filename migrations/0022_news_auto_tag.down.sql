@@ -0,0 +1 @@
+DROP TABLE news_auto_tag;
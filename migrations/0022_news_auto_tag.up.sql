@@ -0,0 +1,9 @@
+-- 自动生成的 tag 与编辑指定的 tag（news_tag）分开保存
+CREATE TABLE news_auto_tag (
+  news_id INTEGER NOT NULL REFERENCES news(id) ON DELETE CASCADE,
+  tag_name VARCHAR(255) NOT NULL,
+  confidence DOUBLE PRECISION NOT NULL,
+  create_time TIMESTAMP NOT NULL DEFAULT now(),
+  PRIMARY KEY (news_id, tag_name)
+);
+CREATE INDEX idx_news_auto_tag_tag_name ON news_auto_tag(tag_name);
@@ -0,0 +1,3 @@
+-- 标记自动生成的摘要，旧数据中截取正文前 100 个字符的摘要视为自动生成
+ALTER TABLE news ADD COLUMN abstracts_generated BOOLEAN NOT NULL DEFAULT false;
+UPDATE news SET abstracts_generated = true WHERE abstracts = left(content, 100);
@@ -0,0 +1,4 @@
+DROP TABLE tag_alias;
+DROP INDEX idx_tag_lower_name;
+DROP INDEX idx_tag_parent_id;
+ALTER TABLE tag DROP COLUMN parent_id;
@@ -0,0 +1,10 @@
+-- tag 层级与别名，别名统一保存为小写
+ALTER TABLE tag ADD COLUMN parent_id INTEGER REFERENCES tag(id) ON DELETE SET NULL;
+CREATE INDEX idx_tag_parent_id ON tag(parent_id);
+CREATE INDEX idx_tag_lower_name ON tag(lower(name));
+CREATE TABLE tag_alias (
+  alias VARCHAR(255) PRIMARY KEY,
+  tag_id INTEGER NOT NULL REFERENCES tag(id) ON DELETE CASCADE,
+  create_time TIMESTAMP NOT NULL DEFAULT now()
+);
+CREATE INDEX idx_tag_alias_tag_id ON tag_alias(tag_id);
@@ -0,0 +1,23 @@
+-- 恢复按 tag 名引用，tag 表中的数据保持不变
+ALTER TABLE news_auto_tag ADD COLUMN tag_name VARCHAR(255);
+UPDATE news_auto_tag SET tag_name = tag.name FROM tag WHERE tag.id = news_auto_tag.tag_id;
+ALTER TABLE news_auto_tag DROP CONSTRAINT news_auto_tag_pkey;
+DROP INDEX idx_news_auto_tag_tag_id;
+ALTER TABLE news_auto_tag DROP COLUMN tag_id;
+ALTER TABLE news_auto_tag ALTER COLUMN tag_name SET NOT NULL;
+ALTER TABLE news_auto_tag ADD PRIMARY KEY (news_id, tag_name);
+CREATE INDEX idx_news_auto_tag_tag_name ON news_auto_tag(tag_name);
+
+ALTER TABLE interest ADD COLUMN news_tag VARCHAR(255);
+UPDATE interest SET news_tag = tag.name FROM tag WHERE tag.id = interest.tag_id;
+DROP INDEX idx_interest_tag_id;
+ALTER TABLE interest DROP COLUMN tag_id;
+ALTER TABLE interest ALTER COLUMN news_tag SET NOT NULL;
+ALTER TABLE interest ADD CONSTRAINT interest_user_id_news_tag_key UNIQUE (user_id, news_tag);
+CREATE INDEX idx_interest_news_tag ON interest(news_tag);
+
+ALTER TABLE news_tag ADD COLUMN tag_name VARCHAR(255);
+UPDATE news_tag SET tag_name = tag.name FROM tag WHERE tag.id = news_tag.tag_id;
+ALTER TABLE news_tag DROP COLUMN tag_id;
+ALTER TABLE news_tag ALTER COLUMN tag_name SET NOT NULL;
+ALTER TABLE news_tag ADD CONSTRAINT interest_tag_name_news_id_key UNIQUE (tag_name, news_id);
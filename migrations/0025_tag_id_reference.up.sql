@@ -0,0 +1,35 @@
+-- news_tag、interest 与 news_auto_tag 改为通过 tag_id 引用 tag
+-- 先补充被引用但不在 tag 表中的 tag 名，保证已有数据都能对应到 tag
+INSERT INTO tag (name)
+SELECT tag_name FROM news_tag
+UNION SELECT news_tag FROM interest
+UNION SELECT tag_name FROM news_auto_tag
+ON CONFLICT (name) DO NOTHING;
+
+ALTER TABLE news_tag ADD COLUMN tag_id INTEGER;
+UPDATE news_tag SET tag_id = tag.id FROM tag WHERE tag.name = news_tag.tag_name;
+ALTER TABLE news_tag DROP CONSTRAINT interest_tag_name_news_id_key;
+ALTER TABLE news_tag DROP COLUMN tag_name;
+ALTER TABLE news_tag ALTER COLUMN tag_id SET NOT NULL;
+ALTER TABLE news_tag ADD CONSTRAINT news_tag_tag_id_fkey FOREIGN KEY (tag_id) REFERENCES tag(id) ON DELETE CASCADE;
+ALTER TABLE news_tag ADD CONSTRAINT news_tag_tag_id_news_id_key UNIQUE (tag_id, news_id);
+
+ALTER TABLE interest ADD COLUMN tag_id INTEGER;
+UPDATE interest SET tag_id = tag.id FROM tag WHERE tag.name = interest.news_tag;
+DROP INDEX idx_interest_news_tag;
+ALTER TABLE interest DROP CONSTRAINT interest_user_id_news_tag_key;
+ALTER TABLE interest DROP COLUMN news_tag;
+ALTER TABLE interest ALTER COLUMN tag_id SET NOT NULL;
+ALTER TABLE interest ADD CONSTRAINT interest_tag_id_fkey FOREIGN KEY (tag_id) REFERENCES tag(id) ON DELETE CASCADE;
+ALTER TABLE interest ADD CONSTRAINT interest_user_id_tag_id_key UNIQUE (user_id, tag_id);
+CREATE INDEX idx_interest_tag_id ON interest(tag_id);
+
+ALTER TABLE news_auto_tag ADD COLUMN tag_id INTEGER;
+UPDATE news_auto_tag SET tag_id = tag.id FROM tag WHERE tag.name = news_auto_tag.tag_name;
+ALTER TABLE news_auto_tag DROP CONSTRAINT news_auto_tag_pkey;
+DROP INDEX idx_news_auto_tag_tag_name;
+ALTER TABLE news_auto_tag DROP COLUMN tag_name;
+ALTER TABLE news_auto_tag ALTER COLUMN tag_id SET NOT NULL;
+ALTER TABLE news_auto_tag ADD CONSTRAINT news_auto_tag_tag_id_fkey FOREIGN KEY (tag_id) REFERENCES tag(id) ON DELETE CASCADE;
+ALTER TABLE news_auto_tag ADD PRIMARY KEY (news_id, tag_id);
+CREATE INDEX idx_news_auto_tag_tag_id ON news_auto_tag(tag_id);
@@ -0,0 +1,3 @@
+-- 合并的 tag 不会恢复
+DROP INDEX idx_tag_lower_name;
+CREATE INDEX idx_tag_lower_name ON tag(lower(name));
@@ -0,0 +1,39 @@
+-- tag 名改为不区分大小写唯一，插入 tag 时由唯一索引处理并发
+-- 先把只有大小写不同的 tag 合并到 id 最小的 tag，新闻 tag 与用户兴趣的处理与合并 tag 接口相同
+CREATE TEMP TABLE tag_case_duplicate ON COMMIT DROP AS
+SELECT id AS source_id, min(id) OVER (PARTITION BY lower(name)) AS target_id FROM tag;
+DELETE FROM tag_case_duplicate WHERE source_id = target_id;
+
+UPDATE tag SET parent_id = d.target_id
+FROM tag_case_duplicate AS d
+WHERE tag.parent_id = d.source_id AND tag.id <> d.target_id;
+
+INSERT INTO news_tag (tag_id, news_id)
+SELECT DISTINCT d.target_id, news_tag.news_id FROM news_tag
+JOIN tag_case_duplicate AS d ON d.source_id = news_tag.tag_id
+ON CONFLICT (tag_id, news_id) DO NOTHING;
+
+INSERT INTO news_auto_tag (news_id, tag_id, confidence)
+SELECT news_auto_tag.news_id, d.target_id, max(news_auto_tag.confidence) FROM news_auto_tag
+JOIN tag_case_duplicate AS d ON d.source_id = news_auto_tag.tag_id
+GROUP BY news_auto_tag.news_id, d.target_id
+ON CONFLICT (news_id, tag_id) DO UPDATE
+  SET confidence = GREATEST(news_auto_tag.confidence, EXCLUDED.confidence);
+
+INSERT INTO interest (user_id, tag_id, weight, last_view_time)
+SELECT interest.user_id, d.target_id, sum(interest.weight), max(interest.last_view_time) FROM interest
+JOIN tag_case_duplicate AS d ON d.source_id = interest.tag_id
+GROUP BY interest.user_id, d.target_id
+ON CONFLICT (user_id, tag_id) DO UPDATE SET
+  weight = interest.weight + EXCLUDED.weight,
+  last_view_time = GREATEST(interest.last_view_time, EXCLUDED.last_view_time);
+
+UPDATE tag_alias SET tag_id = d.target_id
+FROM tag_case_duplicate AS d
+WHERE tag_alias.tag_id = d.source_id;
+
+-- 被合并 tag 的新闻 tag、兴趣等随 tag 一起级联删除
+DELETE FROM tag USING tag_case_duplicate AS d WHERE tag.id = d.source_id;
+
+DROP INDEX idx_tag_lower_name;
+CREATE UNIQUE INDEX idx_tag_lower_name ON tag(lower(name));
@@ -0,0 +1,37 @@
+-- 恢复 0018 中的定义
+CREATE OR REPLACE FUNCTION nrs_search_terms(doc TEXT) RETURNS TEXT[] AS $$
+DECLARE
+  c TEXT;
+  code INTEGER;
+  kind INTEGER;
+  last_kind INTEGER := 0;
+  term TEXT := '';
+  terms TEXT[] := '{}';
+BEGIN
+  FOREACH c IN ARRAY string_to_array(lower(COALESCE(doc, '')), NULL) LOOP
+    code := ascii(c);
+    IF (c >= 'a' AND c <= 'z') OR (c >= '0' AND c <= '9') THEN
+      kind := 1;
+    ELSIF code BETWEEN 12352 AND 12543 OR code BETWEEN 13312 AND 40959
+      OR code BETWEEN 44032 AND 55215 OR code BETWEEN 63744 AND 64255 THEN
+      kind := 2;
+    ELSE
+      kind := 0;
+    END IF;
+    IF kind <> last_kind AND term <> '' THEN
+      terms := terms || term;
+      term := '';
+    END IF;
+    IF kind <> 0 THEN
+      term := term || c;
+    END IF;
+    last_kind := kind;
+  END LOOP;
+  IF term <> '' THEN
+    terms := terms || term;
+  END IF;
+  RETURN terms;
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+UPDATE news SET title = title;
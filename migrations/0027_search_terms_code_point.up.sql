@@ -0,0 +1,42 @@
+-- 0018 中 nrs_search_terms 用字符比较判断英文与数字，结果依赖数据库的排序规则，
+-- 如英文排序规则下 'é' 介于 'a' 与 'z' 之间。改为比较码位，并且只把 A-Z 转为小写，
+-- 与 controller::news::search 的 char_kind 一致，不依赖数据库 locale
+CREATE OR REPLACE FUNCTION nrs_search_terms(doc TEXT) RETURNS TEXT[] AS $$
+DECLARE
+  c TEXT;
+  code INTEGER;
+  kind INTEGER;
+  last_kind INTEGER := 0;
+  term TEXT := '';
+  terms TEXT[] := '{}';
+BEGIN
+  FOREACH c IN ARRAY string_to_array(
+    translate(COALESCE(doc, ''), 'ABCDEFGHIJKLMNOPQRSTUVWXYZ', 'abcdefghijklmnopqrstuvwxyz'), NULL
+  ) LOOP
+    code := ascii(c);
+    IF code BETWEEN 97 AND 122 OR code BETWEEN 48 AND 57 THEN
+      kind := 1;
+    ELSIF code BETWEEN 12352 AND 12543 OR code BETWEEN 13312 AND 40959
+      OR code BETWEEN 44032 AND 55215 OR code BETWEEN 63744 AND 64255 THEN
+      kind := 2;
+    ELSE
+      kind := 0;
+    END IF;
+    IF kind <> last_kind AND term <> '' THEN
+      terms := terms || term;
+      term := '';
+    END IF;
+    IF kind <> 0 THEN
+      term := term || c;
+    END IF;
+    last_kind := kind;
+  END LOOP;
+  IF term <> '' THEN
+    terms := terms || term;
+  END IF;
+  RETURN terms;
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- 重新计算已有新闻的 search_vector
+UPDATE news SET title = title;
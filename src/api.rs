@@ -1,22 +1,27 @@
-use poem::web::Data;
+use poem::{web::Data, Request};
 use poem_openapi::{
-    param::{Header, Query},
-    payload::Json,
+    param::{Path, Query},
+    payload::{Json, PlainText},
     OpenApi, Tags,
 };
 
 use crate::{
     common::{
-        data::DbPool,
+        audit::Actor,
+        data::{audit::AuditFilter, news::SearchFilter, revoke::RevokeStore, DbPool},
         object::{
             self,
+            api_key::{self, Scope},
+            audit::{self, AuditAction},
             news::{self, RandomTagResponse},
-            user,
+            user::{self, Role},
         },
         ApiResult, NoData,
     },
-    config::{AppAuthorization, ServerKey, CONFIG},
+    config::{AdminAuthorization, AppAuthorization, ServerKey, ServiceAuthorization},
     controller,
+    mailer::SharedMailer,
+    util::{client_ip, ClientInfo},
 };
 
 pub struct CommonApi;
@@ -56,11 +61,109 @@ impl UserApi {
     #[oai(path = "/login", method = "post", tag = "ApiTags::User")]
     async fn login(
         &self,
+        req: &Request,
         Json(user): Json<user::LoginRequest>,
         Data(pool): Data<&DbPool>,
         Data(server_key): Data<&ServerKey>,
     ) -> ApiResult<user::LoginSuccess> {
-        controller::user::login(pool, server_key, user).await
+        controller::user::login(pool, server_key, user, &ClientInfo::from_request(req)).await
+    }
+
+    /// 两步验证登录路由，提交登录时返回的 challenge token 与验证码
+    #[oai(path = "/login/2fa", method = "post", tag = "ApiTags::User")]
+    async fn login_two_factor(
+        &self,
+        req: &Request,
+        Json(request): Json<user::TwoFactorLoginRequest>,
+        Data(pool): Data<&DbPool>,
+        Data(server_key): Data<&ServerKey>,
+        Data(revoke_store): Data<&RevokeStore>,
+    ) -> ApiResult<user::LoginSuccess> {
+        controller::user::two_factor::login(
+            pool,
+            server_key,
+            revoke_store,
+            request,
+            &ClientInfo::from_request(req),
+        )
+        .await
+    }
+
+    /// 刷新 token 路由，使用 refresh token 换取新的 token
+    #[oai(path = "/refresh", method = "post", tag = "ApiTags::User")]
+    async fn refresh(
+        &self,
+        req: &Request,
+        Json(request): Json<user::RefreshRequest>,
+        Data(pool): Data<&DbPool>,
+        Data(server_key): Data<&ServerKey>,
+    ) -> ApiResult<user::LoginSuccess> {
+        controller::user::refresh(pool, server_key, request, &ClientInfo::from_request(req)).await
+    }
+
+    /// 用户登出路由，注销当前 token，需要 user 认证
+    #[oai(path = "/logout", method = "post", tag = "ApiTags::User")]
+    async fn logout(
+        &self,
+        Json(request): Json<user::LogoutRequest>,
+        Data(pool): Data<&DbPool>,
+        Data(revoke_store): Data<&RevokeStore>,
+        auth: AppAuthorization,
+    ) -> ApiResult<NoData> {
+        controller::user::logout(pool, revoke_store, &auth.0, request).await
+    }
+
+    /// 所有设备登出路由，注销全部已签发的 token，需要 user 认证
+    #[oai(path = "/logout_all", method = "post", tag = "ApiTags::User")]
+    async fn logout_all(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Data(revoke_store): Data<&RevokeStore>,
+        auth: AppAuthorization,
+    ) -> ApiResult<NoData> {
+        controller::user::logout_all(pool, revoke_store, &auth.0).await
+    }
+
+    /// 导出个人数据路由，需要 user 认证
+    #[oai(path = "/export", method = "get", tag = "ApiTags::User")]
+    async fn export(
+        &self,
+        Data(pool): Data<&DbPool>,
+        auth: AppAuthorization,
+    ) -> ApiResult<user::ExportResponse> {
+        controller::user::export(pool, auth.0.id).await
+    }
+
+    /// 注销账号路由，清除全部个人数据，需要 user 认证
+    #[oai(path = "/delete", method = "post", tag = "ApiTags::User")]
+    async fn delete(
+        &self,
+        Json(request): Json<user::DeleteAccountRequest>,
+        Data(pool): Data<&DbPool>,
+        auth: AppAuthorization,
+    ) -> ApiResult<NoData> {
+        controller::user::delete(pool, auth.0.id, request).await
+    }
+
+    /// 登录会话列表路由，需要 user 认证
+    #[oai(path = "/sessions", method = "get", tag = "ApiTags::User")]
+    async fn sessions(
+        &self,
+        Data(pool): Data<&DbPool>,
+        auth: AppAuthorization,
+    ) -> ApiResult<Vec<user::SessionResponse>> {
+        controller::user::session::list(pool, &auth.0).await
+    }
+
+    /// 注销登录会话路由，需要 user 认证
+    #[oai(path = "/sessions/revoke", method = "post", tag = "ApiTags::User")]
+    async fn revoke_session(
+        &self,
+        Json(request): Json<user::RevokeSessionRequest>,
+        Data(pool): Data<&DbPool>,
+        auth: AppAuthorization,
+    ) -> ApiResult<NoData> {
+        controller::user::session::revoke(pool, auth.0.id, request).await
     }
 
     /// 用户注册路由
@@ -69,8 +172,100 @@ impl UserApi {
         &self,
         Json(user): Json<user::RegisterRequest>,
         Data(pool): Data<&DbPool>,
+        Data(mailer): Data<&SharedMailer>,
+    ) -> ApiResult<NoData> {
+        controller::user::register(pool, mailer, user).await
+    }
+
+    /// 重新发送邮箱验证邮件路由，需要 user 认证
+    #[oai(path = "/verify_email/resend", method = "post", tag = "ApiTags::User")]
+    async fn resend_verify_email(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Data(mailer): Data<&SharedMailer>,
+        auth: AppAuthorization,
+    ) -> ApiResult<NoData> {
+        controller::user::email::resend_verification(pool, mailer, auth.0.id).await
+    }
+
+    /// 验证邮箱路由
+    #[oai(path = "/verify_email", method = "post", tag = "ApiTags::User")]
+    async fn verify_email(
+        &self,
+        Json(request): Json<user::VerifyEmailRequest>,
+        Data(pool): Data<&DbPool>,
+    ) -> ApiResult<NoData> {
+        controller::user::email::verify_email(pool, request).await
+    }
+
+    /// 申请重置密码路由，向已验证的邮箱发送重置链接
+    #[oai(
+        path = "/password_reset/request",
+        method = "post",
+        tag = "ApiTags::User"
+    )]
+    async fn password_reset_request(
+        &self,
+        Json(request): Json<user::PasswordResetRequest>,
+        Data(pool): Data<&DbPool>,
+        Data(mailer): Data<&SharedMailer>,
+    ) -> ApiResult<NoData> {
+        controller::user::email::request_password_reset(pool, mailer, request).await
+    }
+
+    /// 重置密码路由
+    #[oai(
+        path = "/password_reset/confirm",
+        method = "post",
+        tag = "ApiTags::User"
+    )]
+    async fn password_reset_confirm(
+        &self,
+        req: &Request,
+        Json(request): Json<user::PasswordResetConfirm>,
+        Data(pool): Data<&DbPool>,
+        Data(revoke_store): Data<&RevokeStore>,
+    ) -> ApiResult<NoData> {
+        controller::user::email::confirm_password_reset(
+            pool,
+            revoke_store,
+            request,
+            &client_ip(req),
+        )
+        .await
+    }
+
+    /// 生成两步验证密钥路由，需要 user 认证
+    #[oai(path = "/2fa/setup", method = "post", tag = "ApiTags::User")]
+    async fn two_factor_setup(
+        &self,
+        Data(pool): Data<&DbPool>,
+        auth: AppAuthorization,
+    ) -> ApiResult<user::TwoFactorSetupResponse> {
+        controller::user::two_factor::setup(pool, auth.0.id).await
+    }
+
+    /// 确认并启用两步验证路由，返回恢复码，需要 user 认证
+    #[oai(path = "/2fa/verify", method = "post", tag = "ApiTags::User")]
+    async fn two_factor_verify(
+        &self,
+        Json(request): Json<user::TwoFactorCodeRequest>,
+        Data(pool): Data<&DbPool>,
+        auth: AppAuthorization,
+    ) -> ApiResult<user::RecoveryCodesResponse> {
+        controller::user::two_factor::verify(pool, auth.0.id, request).await
+    }
+
+    /// 关闭两步验证路由，需要 user 认证以及当前密码，与登录一样限制失败次数
+    #[oai(path = "/2fa/disable", method = "post", tag = "ApiTags::User")]
+    async fn two_factor_disable(
+        &self,
+        req: &Request,
+        Json(request): Json<user::TwoFactorDisableRequest>,
+        Data(pool): Data<&DbPool>,
+        auth: AppAuthorization,
     ) -> ApiResult<NoData> {
-        controller::user::register(pool, user).await
+        controller::user::two_factor::disable(pool, auth.0.id, request, &client_ip(req)).await
     }
 
     /// 个人认证测试路由，需要 user 认证
@@ -83,11 +278,13 @@ impl UserApi {
     #[oai(path = "/update", method = "post", tag = "ApiTags::User")]
     async fn update(
         &self,
+        req: &Request,
         Json(update_info): Json<user::UpdateRequest>,
         Data(pool): Data<&DbPool>,
+        Data(mailer): Data<&SharedMailer>,
         auth: AppAuthorization,
     ) -> ApiResult<NoData> {
-        controller::user::update(pool, auth.0.id, update_info).await
+        controller::user::update(pool, mailer, auth.0.id, update_info, &client_ip(req)).await
     }
 
     /// 获取个人信息路由，需要 user 认证
@@ -100,14 +297,18 @@ impl UserApi {
         controller::user::get_info(pool, auth.0.id).await
     }
 
-    /// 获取个人历史浏览记录路由，需要 user 认证
+    /// 获取个人历史浏览记录路由，按浏览时间倒序，需要 user 认证
+    /// - cursor: 上一页返回的 next_cursor，第一页不填
+    /// - limit: 每页数量，默认为 20，最大为 100
     #[oai(path = "/history", method = "get", tag = "ApiTags::User")]
     async fn history(
         &self,
         Data(pool): Data<&DbPool>,
+        Query(cursor): Query<Option<String>>,
+        Query(limit): Query<Option<i64>>,
         auth: AppAuthorization,
     ) -> ApiResult<user::HistoryResponse> {
-        controller::user::get_history(pool, auth.0.id).await
+        controller::user::get_history(pool, auth.0.id, cursor, limit).await
     }
 
     /// 通过 user 自己的 tag 去发现相似的人，需要 user 认证
@@ -125,33 +326,327 @@ impl UserApi {
 /// Admin 路由
 #[OpenApi(prefix_path = "/admin")]
 impl AdminApi {
-    /// 指定用户信息路由，需要 admin 认证
+    /// 指定用户信息路由，需要 users:read 权限
     /// - user_id: 用户 id
     #[oai(path = "/userinfo", method = "get", tag = "ApiTags::Admin")]
     async fn user_info(
         &self,
+        req: &Request,
         Data(pool): Data<&DbPool>,
         Query(user_id): Query<i32>,
-        #[oai(name = "ADMIN-TOKEN")] token: Header<String>,
+        auth: ServiceAuthorization,
     ) -> ApiResult<user::InfoResponse> {
-        if token.0 != CONFIG.server.api_key {
-            return Err(crate::common::ApiError::AdminAuthFailed);
-        }
-        controller::admin::get_user_by_id(pool, user_id).await
+        let principal = auth.require(Scope::UsersRead)?;
+        controller::admin::get_user_by_id(pool, user_id, principal.into(), &client_ip(req)).await
     }
 
-    /// 创建新闻路由，需要 admin 认证
+    /// 系统统计数据路由，需要 stats:read 权限
+    #[oai(path = "/stats", method = "get", tag = "ApiTags::Admin")]
+    async fn stats(
+        &self,
+        Data(pool): Data<&DbPool>,
+        auth: ServiceAuthorization,
+    ) -> ApiResult<object::stats::StatsResponse> {
+        auth.require(Scope::StatsRead)?;
+        controller::admin::get_stats(pool).await
+    }
+
+    /// 用户列表路由，按 id 倒序，需要 users:read 权限
+    /// - cursor: 上一页返回的 next_cursor，第一页不填
+    /// - limit: 每页数量，默认为 20，最大为 100
+    #[oai(path = "/user/list", method = "get", tag = "ApiTags::Admin")]
+    async fn list_users(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Query(cursor): Query<Option<String>>,
+        Query(limit): Query<Option<i64>>,
+        auth: ServiceAuthorization,
+    ) -> ApiResult<user::UserListResponse> {
+        auth.require(Scope::UsersRead)?;
+        controller::admin::list_users(pool, cursor, limit).await
+    }
+
+    /// 新闻列表路由，按创建时间倒序，需要 editor 角色
+    /// - status: 只返回该状态的新闻，不填时返回所有状态
+    /// - cursor: 上一页返回的 next_cursor，第一页不填
+    /// - limit: 每页数量，默认为 20，最大为 100
+    #[oai(path = "/news/list", method = "get", tag = "ApiTags::Admin")]
+    async fn list_news(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Query(status): Query<Option<news::NewsStatus>>,
+        Query(cursor): Query<Option<String>>,
+        Query(limit): Query<Option<i64>>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<news::NewsListResponse> {
+        auth.require(Role::Editor)?;
+        controller::admin::list_news(pool, status, cursor, limit).await
+    }
+
+    /// 重复新闻报告路由，按正文相似度分组，需要 editor 角色
+    #[oai(path = "/news/duplicates", method = "get", tag = "ApiTags::Admin")]
+    async fn news_duplicates(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Query(max_distance): Query<Option<i32>>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<news::DuplicateReportResponse> {
+        auth.require(Role::Editor)?;
+        controller::admin::news::duplicates(pool, max_distance).await
+    }
+
+    /// 订阅抓取状态路由，按 id 排序，需要 editor 角色
+    /// - cursor: 上一页返回的 next_cursor，第一页不填
+    /// - limit: 每页数量，默认为 20，最大为 100
+    #[oai(path = "/feed/list", method = "get", tag = "ApiTags::Admin")]
+    async fn list_feeds(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Query(cursor): Query<Option<String>>,
+        Query(limit): Query<Option<i64>>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<object::feed::FeedListResponse> {
+        auth.require(Role::Editor)?;
+        controller::admin::list_feeds(pool, cursor, limit).await
+    }
+
+    /// tag 详情路由，包括父 tag、子 tag 与别名，需要 editor 角色
+    #[oai(path = "/tag/:id", method = "get", tag = "ApiTags::Admin")]
+    async fn get_tag(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Path(id): Path<i32>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<object::tag::TagDetail> {
+        auth.require(Role::Editor)?;
+        controller::admin::tag::get(pool, id).await
+    }
+
+    /// 设置父 tag 路由，需要 editor 角色
+    #[oai(path = "/tag/:id/parent", method = "put", tag = "ApiTags::Admin")]
+    async fn update_tag_parent(
+        &self,
+        req: &Request,
+        Data(pool): Data<&DbPool>,
+        Path(id): Path<i32>,
+        Json(request): Json<object::tag::UpdateTagParentRequest>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<object::tag::TagDetail> {
+        let claims = auth.require(Role::Editor)?;
+        controller::admin::tag::update_parent(
+            pool,
+            id,
+            request,
+            Actor::User(claims.id),
+            &client_ip(req),
+        )
+        .await
+    }
+
+    /// 替换 tag 别名路由，新增 tag 时别名会解析为该 tag，需要 editor 角色
+    #[oai(path = "/tag/:id/aliases", method = "put", tag = "ApiTags::Admin")]
+    async fn update_tag_aliases(
+        &self,
+        req: &Request,
+        Data(pool): Data<&DbPool>,
+        Path(id): Path<i32>,
+        Json(request): Json<object::tag::UpdateTagAliasesRequest>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<object::tag::TagDetail> {
+        let claims = auth.require(Role::Editor)?;
+        controller::admin::tag::update_aliases(
+            pool,
+            id,
+            request,
+            Actor::User(claims.id),
+            &client_ip(req),
+        )
+        .await
+    }
+
+    /// 合并 tag 路由，被合并 tag 的新闻与用户兴趣改为目标 tag，需要 admin 角色
+    #[oai(path = "/tag/merge", method = "post", tag = "ApiTags::Admin")]
+    async fn merge_tag(
+        &self,
+        req: &Request,
+        Data(pool): Data<&DbPool>,
+        Json(request): Json<object::tag::MergeTagRequest>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<object::tag::MergeTagResponse> {
+        let claims = auth.require(Role::Admin)?;
+        controller::admin::tag::merge(pool, request, Actor::User(claims.id), &client_ip(req)).await
+    }
+
+    /// 新闻详情路由，包含未发布与已删除的新闻，需要 news:write 权限
+    #[oai(path = "/news/:id", method = "get", tag = "ApiTags::Admin")]
+    async fn get_news(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Path(id): Path<i32>,
+        auth: ServiceAuthorization,
+    ) -> ApiResult<news::AdminNewsResponse> {
+        auth.require(Scope::NewsWrite)?;
+        controller::admin::news::get(pool, id).await
+    }
+
+    /// 修改新闻路由，未填写的字段保持不变，需要 news:write 权限
+    /// - status 改为 unpublished 即下架，改回 published 即重新发布
+    #[oai(path = "/news/:id", method = "put", tag = "ApiTags::Admin")]
+    async fn update_news(
+        &self,
+        req: &Request,
+        Data(pool): Data<&DbPool>,
+        Path(id): Path<i32>,
+        Json(request): Json<news::UpdateNewsRequest>,
+        auth: ServiceAuthorization,
+    ) -> ApiResult<news::AdminNewsResponse> {
+        let principal = auth.require(Scope::NewsWrite)?;
+        controller::admin::news::update(pool, id, request, principal.into(), &client_ip(req)).await
+    }
+
+    /// 删除新闻路由，新闻不再对用户可见，可以通过修改状态恢复，需要 news:write 权限
+    #[oai(path = "/news/:id", method = "delete", tag = "ApiTags::Admin")]
+    async fn delete_news(
+        &self,
+        req: &Request,
+        Data(pool): Data<&DbPool>,
+        Path(id): Path<i32>,
+        auth: ServiceAuthorization,
+    ) -> ApiResult<NoData> {
+        let principal = auth.require(Scope::NewsWrite)?;
+        controller::admin::news::delete(pool, id, principal.into(), &client_ip(req)).await
+    }
+
+    /// 替换新闻 tag 路由，需要 news:write 权限
+    #[oai(path = "/news/:id/tags", method = "put", tag = "ApiTags::Admin")]
+    async fn update_news_tags(
+        &self,
+        req: &Request,
+        Data(pool): Data<&DbPool>,
+        Path(id): Path<i32>,
+        Json(request): Json<news::UpdateNewsTagsRequest>,
+        auth: ServiceAuthorization,
+    ) -> ApiResult<news::AdminNewsResponse> {
+        let principal = auth.require(Scope::NewsWrite)?;
+        controller::admin::news::update_tags(pool, id, request, principal.into(), &client_ip(req))
+            .await
+    }
+
+    /// 修改用户角色路由，需要 admin 角色
+    #[oai(path = "/role", method = "post", tag = "ApiTags::Admin")]
+    async fn update_role(
+        &self,
+        req: &Request,
+        Data(pool): Data<&DbPool>,
+        Json(request): Json<user::UpdateRoleRequest>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<NoData> {
+        let claims = auth.require(Role::Admin)?;
+        controller::admin::update_role(pool, request, Actor::User(claims.id), &client_ip(req)).await
+    }
+
+    /// 创建新闻路由，需要 news:write 权限
     #[oai(path = "/createnews", method = "post", tag = "ApiTags::Admin")]
     async fn create_news(
         &self,
+        req: &Request,
         Data(pool): Data<&DbPool>,
         Json(news): Json<object::news::CreateNewsRequest>,
-        #[oai(name = "ADMIN-TOKEN")] token: Header<String>,
+        auth: ServiceAuthorization,
     ) -> ApiResult<NoData> {
-        if token.0 != CONFIG.server.api_key {
-            return Err(crate::common::ApiError::AdminAuthFailed);
-        }
-        controller::admin::create_news(pool, news).await
+        let principal = auth.require(Scope::NewsWrite)?;
+        controller::admin::create_news(pool, news, principal.into(), &client_ip(req)).await
+    }
+
+    /// 批量导入新闻路由，请求体每行一条 JSON 格式的新闻，需要 news:write 权限
+    #[oai(path = "/news/bulk", method = "post", tag = "ApiTags::Admin")]
+    async fn bulk_news(
+        &self,
+        req: &Request,
+        Data(pool): Data<&DbPool>,
+        body: news::BulkNewsRequest,
+        auth: ServiceAuthorization,
+    ) -> ApiResult<news::BulkImportResponse> {
+        let principal = auth.require(Scope::NewsWrite)?;
+        let body = match body {
+            news::BulkNewsRequest::Ndjson(PlainText(body)) => body,
+            news::BulkNewsRequest::PlainText(PlainText(body)) => body,
+        };
+        controller::admin::news::bulk(pool, body, principal.into(), &client_ip(req)).await
+    }
+
+    /// 创建 API key 路由，需要 admin 角色
+    #[oai(path = "/apikey/create", method = "post", tag = "ApiTags::Admin")]
+    async fn create_api_key(
+        &self,
+        req: &Request,
+        Data(pool): Data<&DbPool>,
+        Json(request): Json<api_key::CreateApiKeyRequest>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<api_key::CreateApiKeyResponse> {
+        let claims = auth.require(Role::Admin)?;
+        controller::admin::api_key::create(pool, claims.id, request, &client_ip(req)).await
+    }
+
+    /// API key 列表路由，按 id 倒序，需要 admin 角色
+    /// - cursor: 上一页返回的 next_cursor，第一页不填
+    /// - limit: 每页数量，默认为 20，最大为 100
+    #[oai(path = "/apikey/list", method = "get", tag = "ApiTags::Admin")]
+    async fn list_api_keys(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Query(cursor): Query<Option<String>>,
+        Query(limit): Query<Option<i64>>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<api_key::ApiKeyListResponse> {
+        auth.require(Role::Admin)?;
+        controller::admin::api_key::list(pool, cursor, limit).await
+    }
+
+    /// 注销 API key 路由，需要 admin 角色
+    #[oai(path = "/apikey/revoke", method = "post", tag = "ApiTags::Admin")]
+    async fn revoke_api_key(
+        &self,
+        req: &Request,
+        Data(pool): Data<&DbPool>,
+        Json(request): Json<api_key::RevokeApiKeyRequest>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<NoData> {
+        let claims = auth.require(Role::Admin)?;
+        controller::admin::api_key::revoke(pool, request, Actor::User(claims.id), &client_ip(req))
+            .await
+    }
+
+    /// 审计日志路由，需要 admin 角色
+    /// - action: 操作类型
+    /// - actor_user_id: 操作者的用户 id
+    /// - target: 操作对象，如 user:1、news:2
+    /// - since / until: 时间范围，包含 since 不包含 until
+    /// - cursor: 上一页返回的 next_cursor，第一页不填
+    /// - limit: 每页数量，默认为 20，最大为 100
+    #[allow(clippy::too_many_arguments)]
+    #[oai(path = "/audit", method = "get", tag = "ApiTags::Admin")]
+    async fn audit_log(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Query(action): Query<Option<AuditAction>>,
+        Query(actor_user_id): Query<Option<i32>>,
+        Query(target): Query<Option<String>>,
+        Query(since): Query<Option<chrono::NaiveDateTime>>,
+        Query(until): Query<Option<chrono::NaiveDateTime>>,
+        Query(cursor): Query<Option<String>>,
+        Query(limit): Query<Option<i64>>,
+        auth: AdminAuthorization,
+    ) -> ApiResult<audit::AuditLogResponse> {
+        auth.require(Role::Admin)?;
+        let filter = AuditFilter {
+            action,
+            actor_user_id,
+            target,
+            since,
+            until,
+        };
+        controller::admin::get_audit_log(pool, filter, cursor, limit).await
     }
 }
 
@@ -182,7 +677,7 @@ impl NewsApi {
         controller::news::get(pool, auth.0.id, news_id).await
     }
 
-    /// like 指定新闻路由，需要用户认证
+    /// like 指定新闻路由，需要用户认证，重复点赞不会增加点赞数
     /// - news_id: 新闻 id
     #[oai(path = "/like", method = "get", tag = "ApiTags::News")]
     async fn like(
@@ -190,11 +685,50 @@ impl NewsApi {
         Data(pool): Data<&DbPool>,
         Query(news_id): Query<i32>,
         auth: AppAuthorization,
-    ) -> ApiResult<NoData> {
-        // TODO: 非在也返回200
+    ) -> ApiResult<news::LikeResponse> {
         controller::news::like(pool, auth.0.id, news_id).await
     }
 
+    /// 取消 like 指定新闻路由，需要用户认证
+    /// - news_id: 新闻 id
+    #[oai(path = "/unlike", method = "get", tag = "ApiTags::News")]
+    async fn unlike(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Query(news_id): Query<i32>,
+        auth: AppAuthorization,
+    ) -> ApiResult<news::LikeResponse> {
+        controller::news::unlike(pool, auth.0.id, news_id).await
+    }
+
+    /// 全文搜索新闻路由，按相关度倒序，需要用户认证
+    /// - q: 搜索关键词，中文按连续的两个字匹配
+    /// - tags: 新闻需要包含的 tag，可以填写多个
+    /// - since / until: 新闻创建时间范围，包含 since 不包含 until
+    /// - cursor: 上一页返回的 next_cursor，第一页不填
+    /// - limit: 每页数量，默认为 20，最大为 100
+    #[allow(clippy::too_many_arguments)]
+    #[oai(path = "/search", method = "get", tag = "ApiTags::News")]
+    async fn search(
+        &self,
+        Data(pool): Data<&DbPool>,
+        Query(q): Query<String>,
+        Query(tags): Query<Option<Vec<String>>>,
+        Query(since): Query<Option<chrono::NaiveDateTime>>,
+        Query(until): Query<Option<chrono::NaiveDateTime>>,
+        Query(cursor): Query<Option<String>>,
+        Query(limit): Query<Option<i64>>,
+        _auth: AppAuthorization,
+    ) -> ApiResult<news::SearchResponse> {
+        let filter = SearchFilter {
+            query: q,
+            tags: tags.unwrap_or_default(),
+            since,
+            until,
+        };
+        controller::news::search::search(pool, filter, cursor, limit).await
+    }
+
     /// 获取随机 tag，需要用户认证
     /// - limit: 获取 tag 数量，默认为 20
     #[oai(path = "/randomtag", method = "get", tag = "ApiTags::News")]
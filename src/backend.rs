@@ -49,20 +49,16 @@ pub async fn update_weight(pool: &DbPool, mut client: RpcClient) -> anyhow::Resu
     let mut tx = pool.begin().await?;
 
     // 更新数据库
+    let mut user_ids = Vec::with_capacity(response.response.len());
+    let mut tag_ids = Vec::with_capacity(response.response.len());
+    let mut weights = Vec::with_capacity(response.response.len());
     for interest in response.response {
-        let tag = data::tag::find_by_id(&pool, interest.tag_id).await?;
-        // tracing::info!("update insterest: {} - {} - {}", interest.tag_id, tag.name, interest.weight);
-        if let Err(_) = data::user::update_interests_by_id(
-            &mut tx,
-            interest.user_id,
-            vec![tag.name],
-            interest.weight,
-            false,
-        )
-        .await
-        {
-            tracing::error!("update interest error");
-        }
+        user_ids.push(interest.user_id);
+        tag_ids.push(interest.tag_id);
+        weights.push(interest.weight);
+    }
+    if let Err(e) = data::user::set_interest_weights(&mut tx, &user_ids, &tag_ids, &weights).await {
+        tracing::error!("update interest error: {}", e);
     }
 
     // 提交事务
@@ -0,0 +1,65 @@
+use serde_json::Value;
+
+use crate::config::Principal;
+
+use super::{data, data::DbPool, object::audit::AuditAction};
+
+/// 操作者
+#[derive(Clone, Copy, Debug)]
+pub enum Actor {
+    /// 未登录，如登录失败
+    Anonymous,
+    /// 用户
+    User(i32),
+    /// API key
+    ApiKey(i32),
+}
+
+impl From<&Principal> for Actor {
+    fn from(principal: &Principal) -> Self {
+        match principal {
+            Principal::User(claims) => Actor::User(claims.id),
+            Principal::ApiKey(key) => Actor::ApiKey(key.id),
+        }
+    }
+}
+
+/// 一条待写入的审计日志
+pub struct AuditEntry {
+    pub action: AuditAction,
+    pub actor: Actor,
+    pub target: Option<String>,
+    pub ip: String,
+    pub payload: Value,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction, actor: Actor, ip: &str) -> Self {
+        Self {
+            action,
+            actor,
+            target: None,
+            ip: ip.to_string(),
+            payload: Value::Object(Default::default()),
+        }
+    }
+
+    /// 操作对象，如 user:1、news:2
+    pub fn target(mut self, target: impl ToString) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    /// 操作相关的数据
+    pub fn payload(mut self, payload: Value) -> Self {
+        self.payload = payload;
+        self
+    }
+}
+
+/// 写入审计日志，失败时只记录错误，不影响正常请求
+pub async fn record(pool: &DbPool, entry: AuditEntry) {
+    if let Err(e) = data::audit::insert(pool, &entry).await {
+        tracing::error!("write audit log {:?} error: {}", entry.action, e);
+    }
+}
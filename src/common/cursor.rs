@@ -0,0 +1,87 @@
+// 游标分页
+// cursor 为上一页最后一条记录排序键的编码，对调用方不透明
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{validate::invalid_field, ApiError};
+
+/// 默认每页数量
+pub const DEFAULT_LIMIT: i64 = 20;
+
+/// 每页最大数量
+pub const MAX_LIMIT: i64 = 100;
+
+/// 解析后的分页参数
+/// - after 为上一页最后一条记录的排序键，第一页为 None
+pub struct PageQuery<K> {
+    pub after: Option<K>,
+    pub limit: i64,
+}
+
+impl<K: Serialize + DeserializeOwned> PageQuery<K> {
+    /// 解析请求中的 cursor 与 limit，cursor 无效时返回 862
+    pub fn parse(cursor: Option<String>, limit: Option<i64>) -> Result<Self, ApiError> {
+        let after = match cursor.as_deref() {
+            None | Some("") => None,
+            Some(cursor) => Some(
+                decode(cursor).ok_or_else(|| invalid_field("cursor", "invalid", "cursor 无效"))?,
+            ),
+        };
+        Ok(Self {
+            after,
+            limit: limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        })
+    }
+
+    /// 查询时多取一条，用于判断是否还有下一页
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+
+    /// 去掉多取的一条记录，并用本页最后一条记录生成下一页的 cursor
+    pub fn finish<T>(&self, mut items: Vec<T>, key: impl Fn(&T) -> K) -> (Vec<T>, Option<String>) {
+        if items.len() as i64 <= self.limit {
+            return (items, None);
+        }
+        items.truncate(self.limit as usize);
+        let next_cursor = items.last().map(|item| encode(&key(item)));
+        (items, next_cursor)
+    }
+}
+
+fn encode<K: Serialize>(key: &K) -> String {
+    hex::encode(serde_json::to_vec(key).unwrap_or_default())
+}
+
+fn decode<K: DeserializeOwned>(cursor: &str) -> Option<K> {
+    let bytes = hex::decode(cursor).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+#[test]
+fn cursor_pages_through_items() {
+    let page = PageQuery::<(chrono::NaiveDateTime, i32)>::parse(None, Some(2))
+        .ok()
+        .unwrap();
+    assert!(page.after.is_none());
+    assert_eq!(page.fetch_limit(), 3);
+
+    let time = chrono::NaiveDate::from_ymd_opt(2023, 6, 1)
+        .unwrap()
+        .and_hms_micro_opt(8, 0, 0, 123456)
+        .unwrap();
+    let (items, next_cursor) = page.finish(vec![3, 2, 1], |id| (time, *id));
+    assert_eq!(items, vec![3, 2]);
+
+    let page = PageQuery::<(chrono::NaiveDateTime, i32)>::parse(next_cursor, Some(2))
+        .ok()
+        .unwrap();
+    assert_eq!(page.after, Some((time, 2)));
+    let (items, next_cursor) = page.finish(vec![1], |id| (time, *id));
+    assert_eq!(items, vec![1]);
+    assert!(next_cursor.is_none());
+
+    assert!(PageQuery::<i32>::parse(Some("not-a-cursor".to_string()), None).is_err());
+    let page = PageQuery::<i32>::parse(None, Some(1000)).ok().unwrap();
+    assert_eq!(page.limit, MAX_LIMIT);
+}
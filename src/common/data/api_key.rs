@@ -0,0 +1,99 @@
+use crate::common::object::api_key::{ApiKeyResponse, Scope};
+
+use super::DbPool;
+
+/// API key 的固定前缀，用于与用户 token 区分
+pub const KEY_PREFIX: &str = "nrs_";
+
+/// last_used 的最小更新间隔（秒），避免每个请求都写数据库
+const TOUCH_INTERVAL: f64 = 60.0;
+
+/// 通过校验的 API key
+#[derive(Debug, sqlx::FromRow)]
+pub struct ApiKeyData {
+    pub id: i32,
+    pub scopes: Vec<Scope>,
+}
+
+/// 新增 API key，返回其 id
+/// - 只保存 key 的摘要
+pub async fn insert(
+    pool: &DbPool,
+    name: &str,
+    key_prefix: &str,
+    key_hash: String,
+    scopes: &[Scope],
+    created_by: i32,
+    ttl: Option<i64>,
+) -> anyhow::Result<i32> {
+    let (id,) = sqlx::query_as::<_, (i32,)>(
+        "
+        INSERT INTO api_key (name, key_prefix, key_hash, scopes, created_by, expire_time)
+        VALUES ($1, $2, $3, $4, $5, now() + make_interval(secs => $6))
+        RETURNING id",
+    )
+    .bind(name)
+    .bind(key_prefix)
+    .bind(key_hash)
+    .bind(scopes)
+    .bind(created_by)
+    .bind(ttl.map(|ttl| ttl as f64))
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// 校验 API key，有效时按间隔更新 last_used
+pub async fn authenticate(pool: &DbPool, key_hash: &str) -> anyhow::Result<Option<ApiKeyData>> {
+    let key = sqlx::query_as::<_, ApiKeyData>(
+        "
+        SELECT id, scopes FROM api_key
+        WHERE key_hash = $1 AND revoked = false AND (expire_time IS NULL OR expire_time > now())",
+    )
+    .bind(key_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    if let Some(key) = &key {
+        sqlx::query(
+            "
+            UPDATE api_key SET last_used = now()
+            WHERE id = $1 AND (last_used IS NULL OR last_used < now() - make_interval(secs => $2))",
+        )
+        .bind(key.id)
+        .bind(TOUCH_INTERVAL)
+        .execute(pool)
+        .await?;
+    }
+    Ok(key)
+}
+
+/// 按 id 倒序分页获取 API key
+pub async fn list(
+    pool: &DbPool,
+    after: Option<i32>,
+    limit: i64,
+) -> anyhow::Result<Vec<ApiKeyResponse>> {
+    let keys = sqlx::query_as::<_, ApiKeyResponse>(
+        "
+        SELECT id, name, key_prefix, scopes, created_by, create_time, expire_time, last_used, revoked
+        FROM api_key
+        WHERE $1::INTEGER IS NULL OR id < $1
+        ORDER BY id DESC
+        LIMIT $2",
+    )
+    .bind(after)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(keys)
+}
+
+/// 注销 API key，返回 false 表示 key 不存在或已注销
+pub async fn revoke(pool: &DbPool, id: i32) -> anyhow::Result<bool> {
+    let result = sqlx::query("UPDATE api_key SET revoked = true WHERE id = $1 AND revoked = false")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() == 1)
+}
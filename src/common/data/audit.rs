@@ -0,0 +1,88 @@
+use crate::common::{
+    audit::{Actor, AuditEntry},
+    object::audit::{AuditAction, AuditLogEntry},
+};
+
+use super::{DbPool, TransPool};
+
+/// 审计日志查询条件，None 表示不限制
+pub struct AuditFilter {
+    pub action: Option<AuditAction>,
+    pub actor_user_id: Option<i32>,
+    pub target: Option<String>,
+    pub since: Option<chrono::NaiveDateTime>,
+    pub until: Option<chrono::NaiveDateTime>,
+}
+
+/// 写入一条审计日志
+pub async fn insert(pool: &DbPool, entry: &AuditEntry) -> anyhow::Result<()> {
+    let (actor_user_id, actor_api_key_id) = match entry.actor {
+        Actor::Anonymous => (None, None),
+        Actor::User(id) => (Some(id), None),
+        Actor::ApiKey(id) => (None, Some(id)),
+    };
+    let _ = sqlx::query(
+        "
+        INSERT INTO audit_log (action, actor_user_id, actor_api_key_id, target, ip, payload)
+        VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(entry.action)
+    .bind(actor_user_id)
+    .bind(actor_api_key_id)
+    .bind(&entry.target)
+    .bind(&entry.ip)
+    .bind(&entry.payload)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 清除与用户相关的审计日志中的个人信息
+/// - 包括用户自己的操作、以该用户为对象的操作以及使用该用户名的登录失败记录
+/// - 保留操作类型、时间以及用户 id，IP、用户名与 user agent 被清除
+pub async fn anonymize_by_user_id(pool: &mut TransPool<'_>, user_id: i32) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "
+        UPDATE audit_log SET
+            ip = '',
+            payload = payload - 'username' - 'user_agent'
+        WHERE actor_user_id = $1
+            OR target = 'user:' || $1
+            OR payload->>'username' = (SELECT username FROM users WHERE id = $1)",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 按 id 倒序分页查询审计日志
+pub async fn query(
+    pool: &DbPool,
+    filter: &AuditFilter,
+    after: Option<i64>,
+    limit: i64,
+) -> anyhow::Result<Vec<AuditLogEntry>> {
+    let entries = sqlx::query_as::<_, AuditLogEntry>(
+        "
+        SELECT * FROM audit_log
+        WHERE ($1::VARCHAR IS NULL OR action = $1)
+            AND ($2::INTEGER IS NULL OR actor_user_id = $2)
+            AND ($3::VARCHAR IS NULL OR target = $3)
+            AND ($4::TIMESTAMP IS NULL OR create_time >= $4)
+            AND ($5::TIMESTAMP IS NULL OR create_time < $5)
+            AND ($6::BIGINT IS NULL OR id < $6)
+        ORDER BY id DESC
+        LIMIT $7",
+    )
+    .bind(filter.action)
+    .bind(filter.actor_user_id)
+    .bind(&filter.target)
+    .bind(filter.since)
+    .bind(filter.until)
+    .bind(after)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(entries)
+}
@@ -0,0 +1,154 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    common::{
+        keyword::{self, Dictionary},
+        object::news::AutoTag,
+    },
+    config::CONFIG,
+};
+
+use super::{DbPool, TransPool};
+
+/// 自动打 tag 使用的词典、语料规模与文档频率
+/// - 批量导入时每批只加载一次，同一批中新增的 tag 与新闻不影响词典与统计
+pub struct Tagger {
+    dictionary: Dictionary,
+    /// 语料中的新闻数
+    total: i64,
+    /// 已查询过的词 -> 包含该词的新闻数
+    document_frequency: HashMap<String, i64>,
+}
+
+impl Tagger {
+    /// 加载 tag 与别名组成的词典以及语料规模，未启用自动打 tag 时返回 None
+    pub async fn load(pool: &mut TransPool<'_>) -> anyhow::Result<Option<Self>> {
+        if !CONFIG.auto_tag.enabled {
+            return Ok(None);
+        }
+        // 别名排在 tag 名之后，与 tag::insert 一样优先匹配别名
+        let entries = sqlx::query_as::<_, (String, String)>(
+            "
+            SELECT word, name FROM (
+                SELECT name AS word, name, 0 AS priority FROM tag
+                UNION ALL
+                SELECT tag_alias.alias, tag.name, 1 FROM tag_alias JOIN tag ON tag.id = tag_alias.tag_id
+            ) AS entry
+            ORDER BY priority",
+        )
+        .fetch_all(&mut *pool)
+        .await?;
+        let (total,) =
+            sqlx::query_as::<_, (i64,)>("SELECT count(*) FROM news WHERE status <> 'deleted'")
+                .fetch_one(&mut *pool)
+                .await?;
+        Ok(Some(Self {
+            dictionary: Dictionary::new(entries),
+            total,
+            document_frequency: HashMap::new(),
+        }))
+    }
+
+    /// 为新闻自动生成 tag，返回保存的 tag
+    /// - 候选词为标题与正文中出现的已有 tag 及其别名，文档频率按实际匹配到的词使用全文索引统计
+    /// - 一个 tag 匹配到多个词（如 tag 名与别名）时取其中最大的文档频率
+    /// - exclude 为编辑已指定的 tag，不会重复生成
+    #[allow(clippy::too_many_arguments)]
+    pub async fn assign(
+        &mut self,
+        pool: &mut TransPool<'_>,
+        news_id: i32,
+        title: &str,
+        content: &str,
+        exclude: &[String],
+        max_tags: usize,
+        min_confidence: f64,
+    ) -> anyhow::Result<Vec<AutoTag>> {
+        let mut frequency = self.dictionary.term_frequency(title, content);
+        frequency.retain(|name, _| !exclude.iter().any(|tag| tag.eq_ignore_ascii_case(name)));
+        if frequency.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let words = frequency
+            .values()
+            .flat_map(|term| &term.words)
+            .filter(|word| !self.document_frequency.contains_key(*word))
+            .cloned()
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect::<Vec<String>>();
+        if !words.is_empty() {
+            let document_frequency = sqlx::query_as::<_, (String, i64)>(
+                "
+                SELECT word, (
+                    SELECT count(*) FROM news
+                    WHERE status <> 'deleted' AND search_vector @@ nrs_tsquery(word)
+                )
+                FROM unnest($1::TEXT[]) AS word",
+            )
+            .bind(&words)
+            .fetch_all(&mut *pool)
+            .await?;
+            self.document_frequency.extend(document_frequency);
+        }
+
+        let mut tags = frequency
+            .iter()
+            .map(|(name, term)| {
+                let df = term
+                    .words
+                    .iter()
+                    .filter_map(|word| self.document_frequency.get(word))
+                    .copied()
+                    .max()
+                    .unwrap_or(0);
+                AutoTag {
+                    name: name.to_string(),
+                    confidence: keyword::confidence(term.frequency, df, self.total),
+                }
+            })
+            .filter(|tag| tag.confidence >= min_confidence)
+            .collect::<Vec<AutoTag>>();
+        tags.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        tags.truncate(max_tags);
+
+        let (names, confidences): (Vec<&str>, Vec<f64>) = tags
+            .iter()
+            .map(|tag| (tag.name.as_str(), tag.confidence))
+            .unzip();
+        let _ = sqlx::query(
+            "
+            INSERT INTO news_auto_tag (news_id, tag_id, confidence)
+            SELECT $1, tag.id, batch.confidence
+            FROM unnest($2::VARCHAR[], $3::DOUBLE PRECISION[]) AS batch (name, confidence)
+            JOIN tag ON tag.name = batch.name
+            ON CONFLICT (news_id, tag_id) DO UPDATE SET confidence = EXCLUDED.confidence",
+        )
+        .bind(news_id)
+        .bind(names)
+        .bind(confidences)
+        .execute(&mut *pool)
+        .await?;
+        Ok(tags)
+    }
+}
+
+/// 获取新闻自动生成的 tag，按置信度倒序
+pub async fn list_by_news_id(pool: &DbPool, news_id: i32) -> anyhow::Result<Vec<AutoTag>> {
+    let tags = sqlx::query_as::<_, AutoTag>(
+        "
+        SELECT tag.name, news_auto_tag.confidence FROM news_auto_tag
+        JOIN tag ON tag.id = news_auto_tag.tag_id
+        WHERE news_auto_tag.news_id = $1
+        ORDER BY news_auto_tag.confidence DESC, tag.name",
+    )
+    .bind(news_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(tags)
+}
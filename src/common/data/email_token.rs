@@ -0,0 +1,67 @@
+use super::DbPool;
+
+/// 邮件中一次性 token 的用途
+#[derive(sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum Purpose {
+    /// 验证邮箱
+    VerifyEmail,
+    /// 重置密码
+    PasswordReset,
+}
+
+/// 新增一个 token，同一用途下旧的 token 全部作废
+/// - 只保存 token 的摘要
+pub async fn insert(
+    pool: &DbPool,
+    user_id: i32,
+    purpose: Purpose,
+    token_hash: String,
+    ttl: i64,
+) -> anyhow::Result<()> {
+    invalidate_all(pool, user_id, purpose).await?;
+    let _ = sqlx::query(
+        "
+        INSERT INTO email_token (user_id, purpose, token_hash, expire_time)
+        VALUES ($1, $2, $3, now() + make_interval(secs => $4))",
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .bind(token_hash)
+    .bind(ttl as f64)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 消费一个 token，成功时返回所属用户 id
+/// - 只有未过期且未使用过的 token 才能被消费
+pub async fn consume(
+    pool: &DbPool,
+    purpose: Purpose,
+    token_hash: &str,
+) -> anyhow::Result<Option<i32>> {
+    let result = sqlx::query_as::<_, (i32,)>(
+        "
+        UPDATE email_token SET used = true
+        WHERE token_hash = $1 AND purpose = $2 AND used = false AND expire_time > now()
+        RETURNING user_id",
+    )
+    .bind(token_hash)
+    .bind(purpose)
+    .fetch_optional(pool)
+    .await?;
+    Ok(result.map(|(user_id,)| user_id))
+}
+
+/// 作废用户某个用途下所有未使用的 token
+pub async fn invalidate_all(pool: &DbPool, user_id: i32, purpose: Purpose) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "UPDATE email_token SET used = true WHERE user_id = $1 AND purpose = $2 AND used = false",
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use crate::common::object::feed::{FeedFetchStatus, FeedState};
+
+use super::DbPool;
+
+/// 获取订阅 id，不存在时新增
+pub async fn upsert(pool: &DbPool, url: &str) -> anyhow::Result<i32> {
+    let (id,) = sqlx::query_as::<_, (i32,)>(
+        "
+        INSERT INTO feed (url) VALUES ($1)
+        ON CONFLICT (url) DO UPDATE SET url = EXCLUDED.url
+        RETURNING id",
+    )
+    .bind(url)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// 条目是否已经处理过
+/// - 同一订阅中 guid 相同，或任意订阅以及已有新闻中 link 相同
+pub async fn is_known(pool: &DbPool, feed_id: i32, guid: &str, link: &str) -> anyhow::Result<bool> {
+    let (known,) = sqlx::query_as::<_, (bool,)>(
+        "
+        SELECT EXISTS (SELECT 1 FROM feed_item WHERE feed_id = $1 AND guid = $2)
+            OR EXISTS (SELECT 1 FROM feed_item WHERE link = $3)
+            OR EXISTS (SELECT 1 FROM news WHERE link = $3)",
+    )
+    .bind(feed_id)
+    .bind(guid)
+    .bind(link)
+    .fetch_one(pool)
+    .await?;
+    Ok(known)
+}
+
+/// 记录已处理的条目，news_id 为空表示没有生成新闻
+pub async fn insert_item(
+    pool: &DbPool,
+    feed_id: i32,
+    guid: &str,
+    link: &str,
+    news_id: Option<i32>,
+) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "
+        INSERT INTO feed_item (feed_id, guid, link, news_id) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (feed_id, guid) DO NOTHING",
+    )
+    .bind(feed_id)
+    .bind(guid)
+    .bind(link)
+    .bind(news_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 记录一次成功的抓取
+pub async fn record_success(
+    pool: &DbPool,
+    feed_id: i32,
+    title: &str,
+    inserted: i32,
+) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "
+        UPDATE feed SET
+            title = COALESCE(NULLIF($2, ''), title),
+            last_fetch_time = now(),
+            last_success_time = now(),
+            last_status = $3,
+            last_error = NULL,
+            last_inserted = $4,
+            total_inserted = total_inserted + $4
+        WHERE id = $1",
+    )
+    .bind(feed_id)
+    .bind(title)
+    .bind(FeedFetchStatus::Ok)
+    .bind(inserted)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 记录一次失败的抓取
+pub async fn record_failure(pool: &DbPool, feed_id: i32, error: &str) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "
+        UPDATE feed SET
+            last_fetch_time = now(),
+            last_status = $2,
+            last_error = $3,
+            last_inserted = 0
+        WHERE id = $1",
+    )
+    .bind(feed_id)
+    .bind(FeedFetchStatus::Error)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 按 id 分页获取订阅的抓取状态
+pub async fn list(pool: &DbPool, after: Option<i32>, limit: i64) -> anyhow::Result<Vec<FeedState>> {
+    let feeds = sqlx::query_as::<_, FeedState>(
+        "
+        SELECT id, url, title, last_fetch_time, last_success_time, last_status, last_error,
+            last_inserted, total_inserted
+        FROM feed
+        WHERE $1::INTEGER IS NULL OR id > $1
+        ORDER BY id
+        LIMIT $2",
+    )
+    .bind(after)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(feeds)
+}
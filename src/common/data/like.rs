@@ -0,0 +1,73 @@
+use crate::common::object::news::LikeExport;
+
+use super::{DbPool, TransPool};
+
+/// 用户点赞新闻，同时增加新闻的点赞数
+/// - 返回 false 表示用户已经点赞过，点赞数不变
+pub async fn insert(pool: &mut TransPool<'_>, user_id: i32, news_id: i32) -> anyhow::Result<bool> {
+    let result = sqlx::query(
+        "INSERT INTO news_like (user_id, news_id) VALUES ($1, $2) ON CONFLICT (user_id, news_id) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(news_id)
+    .execute(&mut *pool)
+    .await?;
+    if result.rows_affected() != 1 {
+        return Ok(false);
+    }
+    let _ = sqlx::query("UPDATE news SET likes = likes + 1 WHERE id = $1")
+        .bind(news_id)
+        .execute(&mut *pool)
+        .await?;
+    Ok(true)
+}
+
+/// 用户取消点赞，同时减少新闻的点赞数
+/// - 返回 false 表示用户没有点赞过，点赞数不变
+pub async fn delete(pool: &mut TransPool<'_>, user_id: i32, news_id: i32) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM news_like WHERE user_id = $1 AND news_id = $2")
+        .bind(user_id)
+        .bind(news_id)
+        .execute(&mut *pool)
+        .await?;
+    if result.rows_affected() != 1 {
+        return Ok(false);
+    }
+    let _ = sqlx::query("UPDATE news SET likes = GREATEST(likes - 1, 0) WHERE id = $1")
+        .bind(news_id)
+        .execute(&mut *pool)
+        .await?;
+    Ok(true)
+}
+
+/// 删除用户所有的点赞，同时减少对应新闻的点赞数
+pub async fn delete_all_by_user_id(pool: &mut TransPool<'_>, user_id: i32) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "
+        UPDATE news SET likes = GREATEST(likes - 1, 0)
+        WHERE id IN (SELECT news_id FROM news_like WHERE user_id = $1)",
+    )
+    .bind(user_id)
+    .execute(&mut *pool)
+    .await?;
+    let _ = sqlx::query("DELETE FROM news_like WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *pool)
+        .await?;
+    Ok(())
+}
+
+/// 获取用户点赞过的所有新闻
+pub async fn list_by_user_id(pool: &DbPool, user_id: i32) -> anyhow::Result<Vec<LikeExport>> {
+    let result = sqlx::query_as::<_, LikeExport>(
+        "
+        SELECT news.id AS news_id, news.title, news_like.create_time
+        FROM news_like, news
+        WHERE news_like.user_id = $1 AND news_like.news_id = news.id
+        ORDER BY news_like.create_time DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(result)
+}
@@ -0,0 +1,134 @@
+use super::DbPool;
+
+/// 登录失败后的锁定规则
+/// - 连续失败 threshold 次后开始锁定 base 秒，之后每多失败一次锁定时长翻倍，最长 max 秒
+/// - 距上次失败超过 reset_after 秒时重新计数
+pub struct Lockout {
+    pub threshold: i32,
+    pub base: i64,
+    pub max: i64,
+    pub reset_after: i64,
+}
+
+/// 记录一次登录尝试，返回 key 剩余的锁定时间（秒），未被锁定时返回 None
+/// - 尝试先按失败计数，达到阈值时在同一条语句中锁定，并发的尝试不会同时通过检查
+/// - 处于锁定期的尝试不计数
+pub async fn attempt(pool: &DbPool, key: &str, lockout: &Lockout) -> anyhow::Result<Option<i64>> {
+    let (counted, remaining) = sqlx::query_as::<_, (bool, Option<f64>)>(
+        "
+        INSERT INTO login_attempt AS a (key, failures, last_failure, locked_until)
+        VALUES (
+            $1, 1, now(),
+            CASE WHEN 1 >= $2 THEN now() + make_interval(secs => $3) END
+        )
+        ON CONFLICT (key) DO
+            UPDATE SET
+            failures = CASE
+                WHEN a.locked_until > now() THEN a.failures
+                WHEN a.last_failure < now() - make_interval(secs => $5) THEN 1
+                ELSE a.failures + 1
+            END,
+            last_failure = CASE WHEN a.locked_until > now() THEN a.last_failure ELSE now() END,
+            locked_until = CASE
+                WHEN a.locked_until > now() THEN a.locked_until
+                WHEN a.last_failure < now() - make_interval(secs => $5) THEN
+                    CASE WHEN 1 >= $2 THEN now() + make_interval(secs => $3) END
+                WHEN a.failures + 1 >= $2 THEN now() + make_interval(
+                    secs => LEAST($3 * power(2, LEAST(a.failures + 1 - $2, 30)), $4)
+                )
+                ELSE a.locked_until
+            END
+        -- now() 在事务内不变，last_failure 不等于 now() 说明尝试因锁定被拒绝
+        RETURNING
+            a.last_failure = now(),
+            EXTRACT(EPOCH FROM (a.locked_until - now()))::FLOAT8",
+    )
+    .bind(key)
+    .bind(lockout.threshold)
+    .bind(lockout.base as f64)
+    .bind(lockout.max as f64)
+    .bind(lockout.reset_after as f64)
+    .fetch_one(pool)
+    .await?;
+    Ok(match counted {
+        true => None,
+        false => remaining.map(|secs| secs.ceil() as i64),
+    })
+}
+
+/// 撤销一次计入的尝试，失败次数低于阈值时解除锁定
+pub async fn release(pool: &DbPool, key: &str, threshold: i32) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "
+        UPDATE login_attempt SET
+            failures = failures - 1,
+            locked_until = CASE WHEN failures - 1 < $2 THEN NULL ELSE locked_until END
+        WHERE key = $1 AND failures > 0",
+    )
+    .bind(key)
+    .bind(threshold)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 登录成功后清除失败记录
+pub async fn clear(pool: &DbPool, key: &str) -> anyhow::Result<()> {
+    let _ = sqlx::query("DELETE FROM login_attempt WHERE key = $1")
+        .bind(key)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn attempt_locks_with_backoff() {
+    let pool = crate::test::get_test_pool().await;
+    let key = "test:attempt_locks_with_backoff";
+    clear(&pool, key).await.unwrap();
+    let lockout = Lockout {
+        threshold: 3,
+        base: 30,
+        max: 100,
+        reset_after: 3600,
+    };
+    let lock_for = |secs: i64| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query("UPDATE login_attempt SET locked_until = now() + make_interval(secs => $2) WHERE key = $1")
+                .bind(key)
+                .bind(secs as f64)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+    };
+
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), None);
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), None);
+    // 第 3 次尝试计数并锁定，之后的尝试被拒绝且不计数
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), None);
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), Some(30));
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), Some(30));
+
+    // 锁定结束后每次失败锁定时长翻倍，不超过 max
+    lock_for(0).await;
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), None);
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), Some(60));
+    lock_for(0).await;
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), None);
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), Some(100));
+
+    // 撤销计数后低于阈值时解除锁定
+    lock_for(0).await;
+    sqlx::query("UPDATE login_attempt SET failures = 2 WHERE key = $1")
+        .bind(key)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), None);
+    release(&pool, key, lockout.threshold).await.unwrap();
+    assert_eq!(attempt(&pool, key, &lockout).await.unwrap(), None);
+
+    clear(&pool, key).await.unwrap();
+}
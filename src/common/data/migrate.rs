@@ -0,0 +1,149 @@
+// 数据库迁移
+// - 迁移脚本位于 migrations 目录，编译时嵌入二进制，执行记录保存在 _sqlx_migrations 表
+// - 1 为初始表结构，之后的版本依次对应旧版 init.sql 中的 fix1、fix2 ...
+
+use sqlx::migrate::{Migrate, Migration, Migrator};
+
+use super::DbPool;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// 迁移的执行状态
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+/// 嵌入的正向迁移
+fn up_migrations() -> impl Iterator<Item = &'static Migration> {
+    MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+}
+
+/// 程序支持的最新版本
+pub fn latest_version() -> i64 {
+    up_migrations().map(|m| m.version).max().unwrap_or(0)
+}
+
+/// 已执行的迁移版本，没有迁移记录表时为空
+async fn applied_versions(pool: &DbPool) -> anyhow::Result<Vec<i64>> {
+    let (exists,) =
+        sqlx::query_as::<_, (bool,)>("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+    if !exists {
+        return Ok(vec![]);
+    }
+    let versions = sqlx::query_as::<_, (i64,)>(
+        "SELECT version FROM _sqlx_migrations WHERE success ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(versions.into_iter().map(|(v,)| v).collect())
+}
+
+/// 检查数据库是否可以由当前程序管理，返回待执行的迁移数
+/// - 数据库版本高于程序时拒绝，避免旧程序操作新表结构
+/// - 通过旧版 init.sql 手动建表、没有迁移记录的数据库需要先执行 baseline
+pub async fn check(pool: &DbPool) -> anyhow::Result<usize> {
+    let applied = applied_versions(pool).await?;
+    let latest = latest_version();
+    if let Some(&version) = applied.last().filter(|&&v| v > latest) {
+        anyhow::bail!(
+            "数据库版本 {} 高于程序支持的最新版本 {}，请使用新版本的程序",
+            version,
+            latest
+        );
+    }
+    if applied.is_empty() {
+        let (legacy,) = sqlx::query_as::<_, (bool,)>("SELECT to_regclass('users') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+        if legacy {
+            anyhow::bail!(
+                "数据库中已有表但没有迁移记录，请先执行 migrate baseline <VERSION> 标记已有的版本"
+            );
+        }
+    }
+    Ok(up_migrations()
+        .filter(|m| !applied.contains(&m.version))
+        .count())
+}
+
+/// 执行所有未执行的迁移，返回执行的迁移数
+pub async fn run(pool: &DbPool) -> anyhow::Result<usize> {
+    let pending = check(pool).await?;
+    if pending > 0 {
+        MIGRATOR.run(pool).await?;
+    }
+    Ok(pending)
+}
+
+/// 回滚到指定版本，该版本之后的迁移按从新到旧的顺序回滚
+pub async fn revert(pool: &DbPool, target: i64) -> anyhow::Result<()> {
+    check(pool).await?;
+    MIGRATOR.undo(pool, target).await?;
+    Ok(())
+}
+
+/// 当前已执行的最新版本，没有执行过迁移时为 0
+pub async fn current_version(pool: &DbPool) -> anyhow::Result<i64> {
+    Ok(applied_versions(pool).await?.last().copied().unwrap_or(0))
+}
+
+/// 所有嵌入的迁移以及执行状态
+pub async fn status(pool: &DbPool) -> anyhow::Result<Vec<MigrationStatus>> {
+    let applied = applied_versions(pool).await?;
+    Ok(up_migrations()
+        .map(|m| MigrationStatus {
+            version: m.version,
+            description: m.description.to_string(),
+            applied: applied.contains(&m.version),
+        })
+        .collect())
+}
+
+/// 将不超过 version 的迁移标记为已执行，但不执行脚本
+/// - 用于由旧版 init.sql 创建、手动执行过 fix 的旧数据库，fixN 对应版本 N + 1
+pub async fn baseline(pool: &DbPool, version: i64) -> anyhow::Result<usize> {
+    if !up_migrations().any(|m| m.version == version) {
+        anyhow::bail!("不存在版本为 {} 的迁移", version);
+    }
+    pool.acquire().await?.ensure_migrations_table().await?;
+    let mut tx = pool.begin().await?;
+    let mut count = 0;
+    for migration in up_migrations().filter(|m| m.version <= version) {
+        count += sqlx::query(
+            "
+            INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+            VALUES ($1, $2, true, $3, 0)
+            ON CONFLICT (version) DO NOTHING",
+        )
+        .bind(migration.version)
+        .bind(&*migration.description)
+        .bind(&*migration.checksum)
+        .execute(&mut tx)
+        .await?
+        .rows_affected() as usize;
+    }
+    tx.commit().await?;
+    Ok(count)
+}
+
+#[test]
+fn migrations_are_contiguous_and_reversible() {
+    let versions: Vec<i64> = up_migrations().map(|m| m.version).collect();
+    assert_eq!(versions, (1..=latest_version()).collect::<Vec<_>>());
+    for version in versions {
+        assert!(
+            MIGRATOR
+                .iter()
+                .any(|m| m.version == version && m.migration_type.is_down_migration()),
+            "migration {} has no down script",
+            version
+        );
+    }
+}
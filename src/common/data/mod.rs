@@ -1,15 +1,57 @@
 // 定义数据库的表结构
 
-use sqlx::{Postgres, Transaction};
+use sqlx::{postgres::PgPoolOptions, Postgres, Transaction};
 use tonic::transport::Channel;
 
-use crate::rpc::recommend::news_recommend_client::NewsRecommendClient;
+use crate::{config::CONFIG, rpc::recommend::news_recommend_client::NewsRecommendClient};
 
 pub type DbPool = sqlx::PgPool;
 
 pub type TransPool<'c> = Transaction<'c, Postgres>;
 pub type RpcClient = NewsRecommendClient<Channel>;
 
+/// 按配置连接数据库
+pub async fn connect(max_connections: u32) -> anyhow::Result<DbPool> {
+    let database_url = format!(
+        "postgres://{}:{}@{}:{}/{}",
+        CONFIG.database.user_name,
+        CONFIG.database.password,
+        CONFIG.database.host,
+        CONFIG.database.port,
+        CONFIG.database.db
+    );
+    let pool = PgPoolOptions::new()
+        .min_connections(max_connections.min(5))
+        .max_connections(max_connections)
+        .connect(&database_url)
+        .await?;
+    Ok(pool)
+}
+
+/// 是否为违反指定唯一约束（或唯一索引）的错误
+/// - 用于并发写入时，先查询后写入的检查被另一个事务抢先的情况
+pub fn is_unique_violation(e: &anyhow::Error, constraint: &str) -> bool {
+    match e.downcast_ref::<sqlx::Error>() {
+        Some(sqlx::Error::Database(e)) => {
+            e.code().as_deref() == Some("23505") && e.constraint() == Some(constraint)
+        }
+        _ => false,
+    }
+}
+
+pub mod api_key;
+pub mod audit;
+pub mod auto_tag;
+pub mod email_token;
+pub mod feed;
+pub mod like;
+pub mod login_attempt;
+pub mod migrate;
 pub mod news;
+pub mod revoke;
+pub mod session;
+pub mod stats;
 pub mod tag;
+pub mod token;
+pub mod two_factor;
 pub mod user;
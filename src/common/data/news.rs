@@ -1,119 +1,293 @@
-use crate::common::object::news::{AbstractResponse, DetailResponse};
+use sqlx::Acquire;
 
-use super::{DbPool, TransPool};
+use crate::{
+    common::{
+        fingerprint, html,
+        object::news::{
+            AbstractResponse, AdminNews, DetailResponse, NewsStatus, UpdateNewsRequest,
+        },
+        summary,
+    },
+    config::CONFIG,
+};
 
-/// 插入一条新闻
-/// - 新闻的 abstracts 为唯一键，若违反约束便停止插入
+use super::{auto_tag::Tagger, DbPool, TransPool};
+
+/// 每批补充指纹的新闻数
+const BACKFILL_BATCH_SIZE: i64 = 500;
+
+/// 从正文中抽取摘要，正文中没有可用的句子时截取去掉 HTML 标签后的前面的字符
+fn generate_abstracts(content: &str) -> String {
+    match summary::summarize(content, CONFIG.summary.max_length) {
+        abstracts if abstracts.is_empty() => html::html_to_text(content)
+            .chars()
+            .take(CONFIG.summary.max_length)
+            .collect(),
+        abstracts => abstracts,
+    }
+}
+
+/// 插入一条新闻，返回新闻 id
+/// - 规范化后的正文与已有新闻相同时不插入并返回 None
+/// - tagger 不为空时根据标题与正文自动生成 tag，批量导入时同一批共用
+#[allow(clippy::too_many_arguments)]
 pub async fn insert_new_news(
     pool: &mut TransPool<'_>,
     title: String,             // 新闻标题
     content: String,           // 新闻主要内容
-    abstracts: Option<String>, // 新闻摘要，如果没有便选取内容的前100个字
+    abstracts: Option<String>, // 新闻摘要，如果没有便从内容中抽取
     source: String,            // 新闻来源
     tags: Vec<String>,         // 新闻 tag
     link: String,              // 新闻原链接
-) -> anyhow::Result<()> {
-    let abstracts = match abstracts {
-        Some(abstracts) => abstracts,
-        // 如果没有摘要，就取前 100 个字符
-        None => content.chars().take(100).collect::<String>(),
+    tagger: Option<&mut Tagger>,
+) -> anyhow::Result<Option<i32>> {
+    let (abstracts, abstracts_generated) = match abstracts {
+        Some(abstracts) => (abstracts, false),
+        // 如果没有摘要，就从正文中抽取
+        None => (generate_abstracts(&content), true),
     };
 
     tracing::info!("insert news: {}", title);
 
+    let content_hash = fingerprint::content_hash(&content);
+    let simhash = fingerprint::simhash(&content) as i64;
+
     // 插入 news 表
-    let (news_id, ) =
-        sqlx::query_as::<_, (i32, )>("INSERT INTO news (title, content, abstracts, source, link, likes) VALUES ($1, $2, $3, $4, $5, 0) RETURNING id")
-            .bind(title)
-            .bind(content)
+    let news_id =
+        sqlx::query_as::<_, (i32, )>("INSERT INTO news (title, content, abstracts, abstracts_generated, source, link, likes, content_hash, simhash) VALUES ($1, $2, $3, $4, $5, $6, 0, $7, $8) ON CONFLICT (content_hash) DO NOTHING RETURNING id")
+            .bind(&title)
+            .bind(&content)
             .bind(abstracts)
+            .bind(abstracts_generated)
             .bind(source)
             .bind(link)
-            .fetch_one(&mut *pool)
+            .bind(content_hash)
+            .bind(simhash)
+            .fetch_optional(&mut *pool)
             .await
             .map_err(|e| {
                 tracing::error!("{}", e);
                 e
             })?;
+    let (news_id,) = match news_id {
+        Some(news_id) => news_id,
+        None => {
+            tracing::info!("skip duplicate news");
+            return Ok(None);
+        }
+    };
 
     tracing::info!("get news_id: {}", news_id);
 
     // 更新 tag 相关的表
+    let mut resolved = Vec::with_capacity(tags.len());
     for tag in tags {
-        // 首先更新 tag 表，如果没有便插入
-        if let Err(e) = super::tag::insert(&mut *pool, &tag).await {
-            tracing::error!("{}", e);
-        }
+        // 首先把 tag 解析为已有的 tag，如果没有便插入
+        let tag = match super::tag::insert(&mut *pool, &tag).await {
+            Ok(tag) => tag,
+            Err(e) => {
+                tracing::error!("{}", e);
+                continue;
+            }
+        };
 
         // 然后更新 news_tag 表，与 news 相关性大
-        if let Err(e) = update_news_tag(&mut *pool, news_id, tag).await {
+        if let Err(e) = update_news_tag(&mut *pool, news_id, tag.id).await {
             tracing::error!("{}", e);
         }
+        resolved.push(tag.name);
     }
-    Ok(())
+
+    // 自动生成 tag，失败时不影响新闻的插入
+    if let Some(tagger) = tagger {
+        let mut savepoint = pool.begin().await?;
+        match tagger
+            .assign(
+                &mut savepoint,
+                news_id,
+                &title,
+                &content,
+                &resolved,
+                CONFIG.auto_tag.max_tags,
+                CONFIG.auto_tag.min_confidence,
+            )
+            .await
+        {
+            Ok(_) => savepoint.commit().await?,
+            Err(e) => {
+                tracing::error!("auto tag error: {}", e);
+                savepoint.rollback().await?;
+            }
+        }
+    }
+    Ok(Some(news_id))
 }
 
 pub async fn update_news_tag(
     pool: &mut TransPool<'_>,
     news_id: i32,
-    tag: String,
+    tag_id: i32,
 ) -> anyhow::Result<()> {
-    let _ = sqlx::query("INSERT INTO news_tag (tag_name, news_id) VALUES ($1, $2) ON CONFLICT (tag_name, news_id) DO NOTHING")
-        .bind(tag)
+    let _ = sqlx::query("INSERT INTO news_tag (tag_id, news_id) VALUES ($1, $2) ON CONFLICT (tag_id, news_id) DO NOTHING")
+        .bind(tag_id)
         .bind(news_id)
         .execute(pool)
         .await?;
     Ok(())
 }
 
-pub async fn increase_like(pool: &DbPool, news_id: i32) -> anyhow::Result<()> {
-    let _ = sqlx::query("UPDATE news SET likes = likes + 1 WHERE id = $1")
-        .bind(news_id)
-        .execute(pool)
-        .await?;
-    Ok(())
+/// 分页获取新闻，按创建时间倒序
+/// - status 为空时返回所有状态的新闻
+/// - after 为上一页最后一条新闻的 (创建时间, 新闻 id)
+pub async fn list(
+    pool: &DbPool,
+    status: Option<NewsStatus>,
+    after: Option<(chrono::NaiveDateTime, i32)>,
+    limit: i64,
+) -> anyhow::Result<Vec<AbstractResponse>> {
+    let after_time = after.map(|(time, _)| time);
+    let after_id = after.map(|(_, id)| id);
+    let news = sqlx::query_as::<_, AbstractResponse>(
+        "
+        SELECT news.id as news_id, news.title, news.abstracts, news.source, news.create_time, news.likes as like,
+            COALESCE(array_agg(tag.name) FILTER (WHERE tag.name IS NOT NULL), '{}') as tags
+        FROM news
+        LEFT JOIN news_tag ON news.id = news_tag.news_id
+        LEFT JOIN tag ON tag.id = news_tag.tag_id
+        WHERE ($1::TIMESTAMP IS NULL OR (news.create_time, news.id) < ($1, $2))
+            AND ($4::VARCHAR IS NULL OR news.status = $4)
+        GROUP BY news.id
+        ORDER BY news.create_time DESC, news.id DESC
+        LIMIT $3",
+    )
+    .bind(after_time)
+    .bind(after_id)
+    .bind(limit)
+    .bind(status)
+    .fetch_all(pool)
+    .await?;
+    Ok(news)
 }
 
-pub async fn find_by_id(pool: &DbPool, news_id: i32) -> anyhow::Result<DetailResponse> {
-    let news = sqlx::query_as::<_, DetailResponse>(
-        "SELECT news.id as news_id, news.title, news.content, news.source, news.create_time, news.likes as like, array_agg(news_tag.tag_name) as tags
-        FROM news 
-        LEFT JOIN news_tag 
-        ON news.id = news_tag.news_id 
-        WHERE news.id = $1 
-        GROUP BY news.id")
-        .bind(news_id)
-        .fetch_one(pool)
-        .await?;
-    Ok(news)
+/// 新闻搜索条件
+pub struct SearchFilter {
+    /// 搜索关键词，分词规则见 迁移 0018_full_text_search 中的 nrs_search_terms
+    pub query: String,
+    /// 新闻需要包含全部 tag
+    pub tags: Vec<String>,
+    pub since: Option<chrono::NaiveDateTime>,
+    pub until: Option<chrono::NaiveDateTime>,
 }
 
-pub async fn find_by_id_abstract(pool: &DbPool, news_id: i32) -> anyhow::Result<AbstractResponse> {
-    let news = sqlx::query_as::<_, AbstractResponse>(
-        "SELECT news.id as news_id, news.title, news.abstracts, news.source, news.create_time, news.likes as like, array_agg(news_tag.tag_name) as tags
+/// 搜索命中的新闻，以及用于生成片段的正文与相关度
+#[derive(sqlx::FromRow)]
+pub struct SearchRow {
+    #[sqlx(flatten)]
+    pub news: AbstractResponse,
+    pub content: String,
+    pub rank: f32,
+}
+
+/// 全文搜索新闻，按相关度倒序
+/// - after 为上一页最后一条结果的 (相关度, 新闻 id)
+pub async fn search(
+    pool: &DbPool,
+    filter: &SearchFilter,
+    after: Option<(f32, i32)>,
+    limit: i64,
+) -> anyhow::Result<Vec<SearchRow>> {
+    let after_rank = after.map(|(rank, _)| rank);
+    let after_id = after.map(|(_, id)| id);
+    let rows = sqlx::query_as::<_, SearchRow>(
+        "
+        SELECT * FROM (
+            SELECT news.id as news_id, news.title, news.abstracts, news.source, news.create_time, news.likes as like,
+                COALESCE(array_agg(tag.name) FILTER (WHERE tag.name IS NOT NULL), '{}') as tags,
+                news.content, ts_rank(news.search_vector, query) AS rank
+            FROM news
+            CROSS JOIN nrs_tsquery($1) AS query
+            LEFT JOIN news_tag ON news.id = news_tag.news_id
+            LEFT JOIN tag ON tag.id = news_tag.tag_id
+            WHERE news.search_vector @@ query AND news.status = 'published'
+                AND ($2::TIMESTAMP IS NULL OR news.create_time >= $2)
+                AND ($3::TIMESTAMP IS NULL OR news.create_time < $3)
+                AND (cardinality($4::VARCHAR[]) = 0 OR news.id IN (
+                    SELECT news_tag.news_id FROM news_tag
+                    JOIN tag ON tag.id = news_tag.tag_id
+                    WHERE tag.name = ANY($4)
+                    GROUP BY news_tag.news_id
+                    HAVING count(DISTINCT tag.name) = cardinality($4)
+                ))
+            GROUP BY news.id, query
+        ) AS result
+        WHERE $5::REAL IS NULL OR (rank, news_id) < ($5, $6)
+        ORDER BY rank DESC, news_id DESC
+        LIMIT $7",
+    )
+    .bind(&filter.query)
+    .bind(filter.since)
+    .bind(filter.until)
+    .bind(&filter.tags)
+    .bind(after_rank)
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// 获取已发布新闻的点赞数，新闻不存在或未发布时返回 None
+pub async fn find_likes_by_id(pool: &DbPool, news_id: i32) -> anyhow::Result<Option<i32>> {
+    let result = sqlx::query_as::<_, (i32,)>(
+        "SELECT likes FROM news WHERE id = $1 AND status = 'published'",
+    )
+    .bind(news_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(result.map(|(likes,)| likes))
+}
+
+/// 获取已发布的新闻详情，新闻不存在或未发布时返回 None
+pub async fn find_by_id(pool: &DbPool, news_id: i32) -> anyhow::Result<Option<DetailResponse>> {
+    let news = sqlx::query_as::<_, DetailResponse>(
+        "SELECT news.id as news_id, news.title, news.content, news.source, news.create_time, news.likes as like,
+            COALESCE(array_agg(tag.name) FILTER (WHERE tag.name IS NOT NULL), '{}') as tags
         FROM news 
         LEFT JOIN news_tag 
         ON news.id = news_tag.news_id 
-        WHERE news.id = $1 
+        LEFT JOIN tag ON tag.id = news_tag.tag_id
+        WHERE news.id = $1 AND news.status = 'published'
         GROUP BY news.id")
         .bind(news_id)
-        .fetch_one(pool)
+        .fetch_optional(pool)
         .await?;
     Ok(news)
 }
 
+/// 随机获取带有指定 tag 或其子孙 tag 的新闻，包含自动生成了这些 tag 的新闻
 pub async fn find_by_tag_id(pool: &DbPool, tag_id: i32, per_limit: i32) -> anyhow::Result<Vec<AbstractResponse>> {
     let news = sqlx::query_as::<_, AbstractResponse>(
         "
-        SELECT news.id as news_id, news.title, news.abstracts, news.source, news.create_time, news.likes as like, array_agg(news_tag.tag_name) as tags
+        WITH RECURSIVE subtree AS (
+            SELECT id FROM tag WHERE id = $1
+            UNION
+            SELECT tag.id FROM tag JOIN subtree ON tag.parent_id = subtree.id
+        )
+        SELECT news.id as news_id, news.title, news.abstracts, news.source, news.create_time, news.likes as like,
+            COALESCE(array_agg(tag.name) FILTER (WHERE tag.name IS NOT NULL), '{}') as tags
         FROM news 
         LEFT JOIN news_tag 
         ON news.id = news_tag.news_id 
-        WHERE news.id IN 
+        LEFT JOIN tag ON tag.id = news_tag.tag_id
+        WHERE news.status = 'published' AND news.id IN 
         (
             SELECT DISTINCT news_tag.news_id
             FROM news_tag 
-            WHERE news_tag.tag_name in (SELECT T.name FROM tag AS T WHERE T.id = $1)
+            WHERE news_tag.tag_id in (SELECT id FROM subtree)
+            UNION
+            SELECT news_auto_tag.news_id
+            FROM news_auto_tag
+            WHERE news_auto_tag.tag_id in (SELECT id FROM subtree)
         )
         GROUP BY news.id 
         ORDER BY RANDOM()
@@ -127,6 +301,233 @@ pub async fn find_by_tag_id(pool: &DbPool, tag_id: i32, per_limit: i32) -> anyho
     Ok(news)
 }
 
+/// 获取任意状态的新闻详情，用于管理后台
+pub async fn find_by_id_admin(pool: &DbPool, news_id: i32) -> anyhow::Result<Option<AdminNews>> {
+    let news = sqlx::query_as::<_, AdminNews>(
+        "
+        SELECT news.id as news_id, news.title, news.abstracts, news.content, news.source, news.link, news.status,
+            news.create_time, news.likes as like,
+            COALESCE(array_agg(tag.name) FILTER (WHERE tag.name IS NOT NULL), '{}') as tags
+        FROM news
+        LEFT JOIN news_tag ON news.id = news_tag.news_id
+        LEFT JOIN tag ON tag.id = news_tag.tag_id
+        WHERE news.id = $1
+        GROUP BY news.id",
+    )
+    .bind(news_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(news)
+}
+
+/// 修改新闻，请求中未填写的字段保持不变
+/// - 修改正文时同时更新正文指纹，摘要是自动生成的则重新生成
+/// - 返回 false 表示新闻不存在
+pub async fn update(
+    pool: &DbPool,
+    news_id: i32,
+    request: &UpdateNewsRequest,
+) -> anyhow::Result<bool> {
+    let content_hash = request.content.as_deref().map(fingerprint::content_hash);
+    let simhash = request
+        .content
+        .as_deref()
+        .map(|content| fingerprint::simhash(content) as i64);
+    let abstracts = request.content.as_deref().map(generate_abstracts);
+    let result = sqlx::query(
+        "
+        UPDATE news SET
+            title = COALESCE($2, title),
+            content = COALESCE($3, content),
+            abstracts = COALESCE($4, CASE WHEN abstracts_generated THEN $10 END, abstracts),
+            abstracts_generated = abstracts_generated AND $4 IS NULL,
+            source = COALESCE($5, source),
+            link = COALESCE($6, link),
+            status = COALESCE($7, status),
+            content_hash = COALESCE($8, content_hash),
+            simhash = COALESCE($9, simhash)
+        WHERE id = $1",
+    )
+    .bind(news_id)
+    .bind(&request.title)
+    .bind(&request.content)
+    .bind(&request.abstracts)
+    .bind(&request.source)
+    .bind(&request.link)
+    .bind(request.status)
+    .bind(content_hash)
+    .bind(simhash)
+    .bind(abstracts)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// 查找正文与 content 相同的新闻，包含未发布以及已删除的新闻
+pub async fn find_id_by_content(pool: &DbPool, content: &str) -> anyhow::Result<Option<i32>> {
+    let news_id = sqlx::query_as::<_, (i32,)>("SELECT id FROM news WHERE content_hash = $1")
+        .bind(fingerprint::content_hash(content))
+        .fetch_optional(pool)
+        .await?
+        .map(|(news_id,)| news_id);
+    Ok(news_id)
+}
+
+/// 获取未删除新闻的 (新闻 id, simhash)
+pub async fn list_simhash(pool: &DbPool) -> anyhow::Result<Vec<(i32, u64)>> {
+    let rows = sqlx::query_as::<_, (i32, i64)>(
+        "SELECT id, simhash FROM news WHERE simhash IS NOT NULL AND status <> 'deleted'",
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(news_id, simhash)| (news_id, simhash as u64))
+    .collect();
+    Ok(rows)
+}
+
+/// 按 id 获取新闻，包含未发布的新闻，按 id 排序
+pub async fn find_by_ids_abstract(
+    pool: &DbPool,
+    news_ids: &[i32],
+) -> anyhow::Result<Vec<AbstractResponse>> {
+    let news = sqlx::query_as::<_, AbstractResponse>(
+        "
+        SELECT news.id as news_id, news.title, news.abstracts, news.source, news.create_time, news.likes as like,
+            COALESCE(array_agg(tag.name) FILTER (WHERE tag.name IS NOT NULL), '{}') as tags
+        FROM news
+        LEFT JOIN news_tag ON news.id = news_tag.news_id
+        LEFT JOIN tag ON tag.id = news_tag.tag_id
+        WHERE news.id = ANY($1)
+        GROUP BY news.id
+        ORDER BY news.id",
+    )
+    .bind(news_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(news)
+}
+
+/// 为缺少指纹的旧新闻补充 content_hash 与 simhash，返回处理的新闻数
+/// - 与已有新闻正文相同的新闻只补充 simhash，content_hash 留空，之后会出现在重复新闻报告中
+pub async fn backfill_fingerprints(pool: &DbPool) -> anyhow::Result<u64> {
+    let mut count = 0;
+    loop {
+        let rows = sqlx::query_as::<_, (i32, String)>(
+            "SELECT id, content FROM news WHERE simhash IS NULL ORDER BY id LIMIT $1",
+        )
+        .bind(BACKFILL_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+        if rows.is_empty() {
+            return Ok(count);
+        }
+        for (news_id, content) in rows {
+            let _ = sqlx::query(
+                "
+                UPDATE news SET
+                    simhash = $2,
+                    content_hash = CASE
+                        WHEN EXISTS (SELECT 1 FROM news WHERE content_hash = $3) THEN NULL
+                        ELSE $3
+                    END
+                WHERE id = $1",
+            )
+            .bind(news_id)
+            .bind(fingerprint::simhash(&content) as i64)
+            .bind(fingerprint::content_hash(&content))
+            .execute(pool)
+            .await?;
+            count += 1;
+        }
+    }
+}
+
+/// 重新生成摘要，返回处理的新闻数
+/// - all 为 false 时只处理摘要是自动生成的新闻，为 true 时处理所有新闻并标记为自动生成
+/// - 按 id 分批处理，每批用一条语句更新
+pub async fn regenerate_abstracts(
+    pool: &DbPool,
+    all: bool,
+    batch_size: i64,
+) -> anyhow::Result<u64> {
+    let mut count = 0;
+    let mut last_id = 0;
+    loop {
+        let rows = sqlx::query_as::<_, (i32, String)>(
+            "
+            SELECT id, content FROM news
+            WHERE id > $1 AND ($2 OR abstracts_generated)
+            ORDER BY id LIMIT $3",
+        )
+        .bind(last_id)
+        .bind(all)
+        .bind(batch_size)
+        .fetch_all(pool)
+        .await?;
+        last_id = match rows.last() {
+            Some((news_id, _)) => *news_id,
+            None => return Ok(count),
+        };
+
+        let (news_ids, abstracts): (Vec<i32>, Vec<String>) = rows
+            .iter()
+            .map(|(news_id, content)| (*news_id, generate_abstracts(content)))
+            .unzip();
+        let result = sqlx::query(
+            "
+            UPDATE news SET abstracts = batch.abstracts, abstracts_generated = true
+            FROM unnest($1::INTEGER[], $2::TEXT[]) AS batch (id, abstracts)
+            WHERE news.id = batch.id",
+        )
+        .bind(news_ids)
+        .bind(abstracts)
+        .execute(pool)
+        .await?;
+        count += result.rows_affected();
+        tracing::info!("regenerated {} abstracts, last news id {}", count, last_id);
+    }
+}
+
+/// 修改新闻状态
+/// - 返回 false 表示新闻不存在
+pub async fn set_status(pool: &DbPool, news_id: i32, status: NewsStatus) -> anyhow::Result<bool> {
+    let result = sqlx::query("UPDATE news SET status = $2 WHERE id = $1")
+        .bind(news_id)
+        .bind(status)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// 替换新闻的 tag 集合
+/// - 返回 false 表示新闻不存在
+pub async fn replace_tags(
+    pool: &mut TransPool<'_>,
+    news_id: i32,
+    tags: Vec<String>,
+) -> anyhow::Result<bool> {
+    // 锁住新闻记录，避免并发替换时 tag 集合交错
+    let exists = sqlx::query("SELECT id FROM news WHERE id = $1 FOR UPDATE")
+        .bind(news_id)
+        .fetch_optional(&mut *pool)
+        .await?
+        .is_some();
+    if !exists {
+        return Ok(false);
+    }
+
+    let _ = sqlx::query("DELETE FROM news_tag WHERE news_id = $1")
+        .bind(news_id)
+        .execute(&mut *pool)
+        .await?;
+    for tag in tags {
+        let tag = super::tag::insert(&mut *pool, &tag).await?;
+        update_news_tag(&mut *pool, news_id, tag.id).await?;
+    }
+    Ok(true)
+}
+
 // pub async fn find_by_tag_id(pool: &DbPool, tag_id: i32) -> anyhow::Result<Vec<AbstractResponse>> {
 //     let news = sqlx::query_as::<_, (i32,)>(
 //         "SELECT DISTINCT news_tag.news_id
@@ -148,3 +549,10 @@ pub async fn find_by_tag_id(pool: &DbPool, tag_id: i32, per_limit: i32) -> anyho
 
 //     Ok(result)
 // }
+
+#[test]
+fn generated_abstracts_strip_markup() {
+    // 只有标签、抽取不到句子的正文不会把 HTML 作为摘要
+    let abstracts = generate_abstracts("<div><img src=\"a.png\"><br/></div>");
+    assert!(!abstracts.contains('<'), "{}", abstracts);
+}
@@ -0,0 +1,142 @@
+use redis::{aio::ConnectionManager, AsyncCommands};
+
+use crate::{common::object::user::UserClaims, config::CONFIG};
+
+use super::DbPool;
+
+/// 已注销 token 的存储
+/// - 配置了 redis 时使用 redis，key 随 token 过期自动清理
+/// - 否则回退到 postgres 的 revoked_token 与 user_token_revocation 表
+#[derive(Clone)]
+pub enum RevokeStore {
+    Redis(ConnectionManager),
+    Postgres(DbPool),
+}
+
+impl RevokeStore {
+    /// 根据配置初始化存储，redis 不可用时回退到 postgres
+    pub async fn new(pool: DbPool) -> Self {
+        let url = match &CONFIG.redis.url {
+            Some(url) => url,
+            None => return RevokeStore::Postgres(pool),
+        };
+        let manager = match redis::Client::open(url.as_str()) {
+            Ok(client) => ConnectionManager::new(client).await,
+            Err(e) => Err(e),
+        };
+        match manager {
+            Ok(manager) => RevokeStore::Redis(manager),
+            Err(e) => {
+                tracing::warn!("redis unavailable, fallback to postgres: {}", e);
+                RevokeStore::Postgres(pool)
+            }
+        }
+    }
+
+    /// 注销单个 token，记录保留到 token 过期为止
+    pub async fn revoke(&self, claims: &UserClaims) -> anyhow::Result<()> {
+        self.revoke_jti(&claims.jti, claims.exp).await
+    }
+
+    /// 按 token id 注销，exp 为 token 的过期时间
+    pub async fn revoke_jti(&self, jti: &str, exp: i64) -> anyhow::Result<()> {
+        let ttl = (exp - chrono::Utc::now().timestamp()).max(1);
+        match self {
+            RevokeStore::Redis(manager) => {
+                let mut conn = manager.clone();
+                conn.set_ex::<_, _, ()>(jti_key(jti), 1, ttl as usize)
+                    .await?;
+            }
+            RevokeStore::Postgres(pool) => {
+                // 顺便清理已经过期的记录
+                sqlx::query("DELETE FROM revoked_token WHERE expire_time < now()")
+                    .execute(pool)
+                    .await?;
+                sqlx::query(
+                    "
+                    INSERT INTO revoked_token (jti, expire_time)
+                    VALUES ($1, now() + make_interval(secs => $2))
+                    ON CONFLICT (jti) DO NOTHING",
+                )
+                .bind(jti)
+                .bind(ttl as f64)
+                .execute(pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 注销用户在 before 时刻之前签发的所有 token
+    pub async fn revoke_all_before(&self, user_id: i32, before: i64) -> anyhow::Result<()> {
+        match self {
+            RevokeStore::Redis(manager) => {
+                let mut conn = manager.clone();
+                // access token 最长存活 access_token_ttl，之后记录便不再需要
+                let ttl = CONFIG.token.access_token_ttl.max(1);
+                conn.set_ex::<_, _, ()>(user_key(user_id), before, ttl as usize)
+                    .await?;
+            }
+            RevokeStore::Postgres(pool) => {
+                sqlx::query(
+                    "
+                    INSERT INTO user_token_revocation (user_id, revoke_before)
+                    VALUES ($1, $2)
+                    ON CONFLICT (user_id) DO UPDATE SET revoke_before = $2",
+                )
+                .bind(user_id)
+                .bind(before)
+                .execute(pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 判断 token 是否已被注销
+    pub async fn is_revoked(&self, claims: &UserClaims) -> anyhow::Result<bool> {
+        if self.is_jti_revoked(&claims.jti).await? {
+            return Ok(true);
+        }
+        let before = match self {
+            RevokeStore::Redis(manager) => {
+                let mut conn = manager.clone();
+                conn.get::<_, Option<i64>>(user_key(claims.id)).await?
+            }
+            RevokeStore::Postgres(pool) => sqlx::query_as::<_, (i64,)>(
+                "SELECT revoke_before FROM user_token_revocation WHERE user_id = $1",
+            )
+            .bind(claims.id)
+            .fetch_optional(pool)
+            .await?
+            .map(|(before,)| before),
+        };
+        Ok(matches!(before, Some(before) if claims.iat < before))
+    }
+
+    /// 判断 token id 是否已被注销
+    pub async fn is_jti_revoked(&self, jti: &str) -> anyhow::Result<bool> {
+        let revoked = match self {
+            RevokeStore::Redis(manager) => {
+                let mut conn = manager.clone();
+                conn.exists(jti_key(jti)).await?
+            }
+            RevokeStore::Postgres(pool) => {
+                sqlx::query("SELECT jti FROM revoked_token WHERE jti = $1")
+                    .bind(jti)
+                    .fetch_optional(pool)
+                    .await?
+                    .is_some()
+            }
+        };
+        Ok(revoked)
+    }
+}
+
+fn jti_key(jti: &str) -> String {
+    format!("nrs:revoked:{}", jti)
+}
+
+fn user_key(user_id: i32) -> String {
+    format!("nrs:revoke_before:{}", user_id)
+}
@@ -0,0 +1,113 @@
+use super::{DbPool, TransPool};
+
+/// last_seen 的最小更新间隔（秒），避免每个请求都写数据库
+const TOUCH_INTERVAL: f64 = 60.0;
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct SessionData {
+    pub id: i32,
+    pub user_agent: String,
+    pub ip: String,
+    pub create_time: chrono::NaiveDateTime,
+    pub last_seen: chrono::NaiveDateTime,
+}
+
+/// 登录时新建会话，返回会话 id
+pub async fn create(
+    pool: &mut TransPool<'_>,
+    user_id: i32,
+    user_agent: &str,
+    ip: &str,
+) -> anyhow::Result<i32> {
+    let (id,) = sqlx::query_as::<_, (i32,)>(
+        "INSERT INTO user_session (user_id, user_agent, ip) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(user_id)
+    .bind(user_agent)
+    .bind(ip)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// 判断会话是否有效，有效时按间隔更新 last_seen
+pub async fn touch(pool: &DbPool, user_id: i32, session_id: i32) -> anyhow::Result<bool> {
+    let result = sqlx::query_as::<_, (bool, bool)>(
+        "
+        SELECT revoked, last_seen < now() - make_interval(secs => $3)
+        FROM user_session
+        WHERE id = $1 AND user_id = $2",
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .bind(TOUCH_INTERVAL)
+    .fetch_optional(pool)
+    .await?;
+
+    match result {
+        Some((false, stale)) => {
+            if stale {
+                sqlx::query("UPDATE user_session SET last_seen = now() WHERE id = $1")
+                    .bind(session_id)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// 获取用户仍然有效的会话，超过 max_idle 秒未活动的会话视为已失效
+pub async fn list_by_user_id(
+    pool: &DbPool,
+    user_id: i32,
+    max_idle: i64,
+) -> anyhow::Result<Vec<SessionData>> {
+    let sessions = sqlx::query_as::<_, SessionData>(
+        "
+        SELECT id, user_agent, ip, create_time, last_seen FROM user_session
+        WHERE user_id = $1 AND revoked = false AND last_seen > now() - make_interval(secs => $2)
+        ORDER BY last_seen DESC",
+    )
+    .bind(user_id)
+    .bind(max_idle as f64)
+    .fetch_all(pool)
+    .await?;
+    Ok(sessions)
+}
+
+/// 注销用户的某个会话，同时作废该会话的 refresh token
+/// - 返回 false 表示会话不存在或不属于该用户
+pub async fn revoke(pool: &DbPool, user_id: i32, session_id: i32) -> anyhow::Result<bool> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
+        "UPDATE user_session SET revoked = true WHERE id = $1 AND user_id = $2 AND revoked = false",
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .execute(&mut tx)
+    .await?;
+    if result.rows_affected() != 1 {
+        return Ok(false);
+    }
+    let _ = sqlx::query(
+        "UPDATE refresh_token SET revoked = true WHERE session_id = $1 AND revoked = false",
+    )
+    .bind(session_id)
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// 注销用户所有的会话
+pub async fn revoke_all_by_user_id(pool: &DbPool, user_id: i32) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "UPDATE user_session SET revoked = true WHERE user_id = $1 AND revoked = false",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use crate::common::object::stats::StatsResponse;
+
+use super::DbPool;
+
+/// 统计各类数据的总数
+pub async fn summary(pool: &DbPool) -> anyhow::Result<StatsResponse> {
+    let stats = sqlx::query_as::<_, StatsResponse>(
+        "
+        SELECT
+            (SELECT COUNT(*) FROM users WHERE deleted_time IS NULL) AS user_count,
+            (SELECT COUNT(*) FROM news WHERE status = 'published') AS published_news_count,
+            (SELECT COUNT(*) FROM news_like) AS like_count,
+            (SELECT COUNT(*) FROM history) AS view_count,
+            (SELECT COUNT(*) FROM feed) AS feed_count",
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(stats)
+}
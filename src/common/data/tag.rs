@@ -1,3 +1,5 @@
+use crate::common::object::tag::{TagBrief, TagDetail};
+
 use super::{DbPool, TransPool};
 
 #[derive(sqlx::FromRow)]
@@ -22,24 +24,33 @@ pub async fn find_by_name(pool: &DbPool, tag_name: String) -> anyhow::Result<Tag
     Ok(tag)
 }
 
-pub async fn find_by_id(pool: &DbPool, tag_id: i32) -> anyhow::Result<TagData> {
-    let tag = sqlx::query_as::<_, TagData>("SELECT * FROM tag WHERE id = $1")
-        .bind(tag_id)
-        .fetch_one(pool)
-        .await?;
-    Ok(tag)
-}
+/// 把 tag 名解析为已有的 tag，不存在时插入，返回规范的 tag
+/// - 先匹配别名，再匹配不区分大小写的 tag 名
+/// - 并发插入只有大小写不同的 tag 名时由 lower(name) 唯一索引保证只有一个 tag
+pub async fn insert(pool: &mut TransPool<'_>, tag_name: &str) -> anyhow::Result<TagBrief> {
+    let resolved = sqlx::query_as::<_, TagBrief>(
+        "
+        SELECT tag.id, tag.name FROM tag_alias
+        JOIN tag ON tag.id = tag_alias.tag_id
+        WHERE tag_alias.alias = lower($1)",
+    )
+    .bind(tag_name)
+    .fetch_optional(&mut *pool)
+    .await?;
+    if let Some(tag) = resolved {
+        return Ok(tag);
+    }
 
-pub async fn insert(pool: &mut TransPool<'_>, tag_name: &String) -> anyhow::Result<()> {
-    let _ = sqlx::query(
+    let tag = sqlx::query_as::<_, TagBrief>(
         "
         INSERT INTO tag (name) VALUES ($1)
-        ON CONFLICT (name) DO NOTHING;",
+        ON CONFLICT ((lower(name))) DO UPDATE SET name = tag.name
+        RETURNING id, name",
     )
     .bind(tag_name)
-    .execute(pool)
+    .fetch_one(pool)
     .await?;
-    Ok(())
+    Ok(tag)
 }
 
 /// 随机返回一些 tag
@@ -65,17 +76,300 @@ pub async fn find_random_tags_id(pool: &DbPool, limit: i32) -> anyhow::Result<Ve
     Ok(tags)
 }
 
-/// 通过 news_id 查找 tag_name
-pub async fn find_all_tag_name_by_news_id(
-    pool: &DbPool,
-    news_id: i32,
+/// 获取 tag 详情，包括父 tag、子 tag 与别名
+pub async fn find_detail(pool: &DbPool, tag_id: i32) -> anyhow::Result<Option<TagDetail>> {
+    let tag = sqlx::query_as::<_, (i32, String, Option<i32>, Option<String>)>(
+        "
+        SELECT tag.id, tag.name, parent.id, parent.name FROM tag
+        LEFT JOIN tag AS parent ON parent.id = tag.parent_id
+        WHERE tag.id = $1",
+    )
+    .bind(tag_id)
+    .fetch_optional(pool)
+    .await?;
+    let (id, name, parent_id, parent_name) = match tag {
+        Some(tag) => tag,
+        None => return Ok(None),
+    };
+
+    let children = sqlx::query_as::<_, TagBrief>(
+        "SELECT id, name FROM tag WHERE parent_id = $1 ORDER BY name",
+    )
+    .bind(tag_id)
+    .fetch_all(pool)
+    .await?;
+    let aliases = sqlx::query_as::<_, (String,)>(
+        "SELECT alias FROM tag_alias WHERE tag_id = $1 ORDER BY alias",
+    )
+    .bind(tag_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(alias,)| alias)
+    .collect();
+
+    Ok(Some(TagDetail {
+        id,
+        name,
+        parent: parent_id
+            .zip(parent_name)
+            .map(|(id, name)| TagBrief { id, name }),
+        children,
+        aliases,
+    }))
+}
+
+/// ancestor 是否为 tag_id 本身或它的祖先
+pub async fn is_ancestor(
+    pool: &mut TransPool<'_>,
+    ancestor: i32,
+    tag_id: i32,
+) -> anyhow::Result<bool> {
+    let (result,) = sqlx::query_as::<_, (bool,)>(
+        "
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id FROM tag WHERE id = $1
+            UNION
+            SELECT tag.id, tag.parent_id FROM tag JOIN ancestors ON tag.id = ancestors.parent_id
+        )
+        SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = $2)",
+    )
+    .bind(tag_id)
+    .bind(ancestor)
+    .fetch_one(pool)
+    .await?;
+    Ok(result)
+}
+
+/// 锁住 tag 记录，返回存在的 tag
+pub async fn lock(pool: &mut TransPool<'_>, tag_ids: &[i32]) -> anyhow::Result<Vec<TagBrief>> {
+    let tags = sqlx::query_as::<_, TagBrief>(
+        "SELECT id, name FROM tag WHERE id = ANY($1) ORDER BY id FOR UPDATE",
+    )
+    .bind(tag_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(tags)
+}
+
+/// 设置父 tag，parent_id 为空时取消父 tag
+pub async fn set_parent(
+    pool: &mut TransPool<'_>,
+    tag_id: i32,
+    parent_id: Option<i32>,
+) -> anyhow::Result<()> {
+    let _ = sqlx::query("UPDATE tag SET parent_id = $2 WHERE id = $1")
+        .bind(tag_id)
+        .bind(parent_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 查找已被其他 tag 占用的别名，包括与其他 tag 名只有大小写不同的别名
+pub async fn find_alias_conflicts(
+    pool: &mut TransPool<'_>,
+    tag_id: i32,
+    aliases: &[String],
 ) -> anyhow::Result<Vec<String>> {
-    let result = sqlx::query_as::<_, (String,)>("SELECT tag_name FROM news_tag WHERE news_id = $1")
-        .bind(news_id)
-        .fetch_all(pool)
+    let conflicts = sqlx::query_as::<_, (String,)>(
+        "
+        SELECT alias FROM unnest($2::TEXT[]) AS alias
+        WHERE EXISTS (SELECT 1 FROM tag_alias WHERE tag_alias.alias = lower(alias) AND tag_id <> $1)
+            OR EXISTS (SELECT 1 FROM tag WHERE lower(name) = lower(alias) AND id <> $1)
+        ORDER BY alias",
+    )
+    .bind(tag_id)
+    .bind(aliases)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(alias,)| alias)
+    .collect();
+    Ok(conflicts)
+}
+
+/// 替换 tag 的别名集合，别名保存为小写
+pub async fn replace_aliases(
+    pool: &mut TransPool<'_>,
+    tag_id: i32,
+    aliases: &[String],
+) -> anyhow::Result<()> {
+    let _ = sqlx::query("DELETE FROM tag_alias WHERE tag_id = $1")
+        .bind(tag_id)
+        .execute(&mut *pool)
+        .await?;
+    let _ = sqlx::query(
+        "
+        INSERT INTO tag_alias (alias, tag_id)
+        SELECT DISTINCT lower(alias), $1 FROM unnest($2::TEXT[]) AS alias
+        ON CONFLICT (alias) DO NOTHING",
+    )
+    .bind(tag_id)
+    .bind(aliases)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 把 source 合并到 target，返回 (改为 target 的新闻数, 改为 target 的用户兴趣数)
+/// - 新闻 tag 与自动生成的 tag 改为 target，用户兴趣改为 target 并与已有的权重相加
+/// - source 的子 tag 与别名移到 target 下，source 的名称成为 target 的别名
+/// - target 是 source 的子孙时，target 先移到 source 原来的位置，避免出现环
+/// - 调用前需要用 lock 锁住两个 tag
+pub async fn merge(
+    pool: &mut TransPool<'_>,
+    source: &TagBrief,
+    target: &TagBrief,
+) -> anyhow::Result<(u64, u64)> {
+    if is_ancestor(&mut *pool, source.id, target.id).await? {
+        let _ = sqlx::query(
+            "UPDATE tag SET parent_id = (SELECT parent_id FROM tag WHERE id = $1) WHERE id = $2",
+        )
+        .bind(source.id)
+        .bind(target.id)
+        .execute(&mut *pool)
+        .await?;
+    }
+    let _ = sqlx::query("UPDATE tag SET parent_id = $2 WHERE parent_id = $1 AND id <> $2")
+        .bind(source.id)
+        .bind(target.id)
+        .execute(&mut *pool)
+        .await?;
+
+    let _ = sqlx::query(
+        "
+        INSERT INTO news_tag (tag_id, news_id)
+        SELECT $2, news_id FROM news_tag WHERE tag_id = $1
+        ON CONFLICT (tag_id, news_id) DO NOTHING",
+    )
+    .bind(source.id)
+    .bind(target.id)
+    .execute(&mut *pool)
+    .await?;
+    let news = sqlx::query("DELETE FROM news_tag WHERE tag_id = $1")
+        .bind(source.id)
+        .execute(&mut *pool)
         .await?
-        .into_iter()
-        .map(|tag_name| tag_name.0)
-        .collect::<Vec<String>>();
-    Ok(result)
+        .rows_affected();
+
+    let _ = sqlx::query(
+        "
+        INSERT INTO news_auto_tag (news_id, tag_id, confidence)
+        SELECT news_id, $2, confidence FROM news_auto_tag WHERE tag_id = $1
+        ON CONFLICT (news_id, tag_id) DO UPDATE
+            SET confidence = GREATEST(news_auto_tag.confidence, EXCLUDED.confidence)",
+    )
+    .bind(source.id)
+    .bind(target.id)
+    .execute(&mut *pool)
+    .await?;
+    let _ = sqlx::query("DELETE FROM news_auto_tag WHERE tag_id = $1")
+        .bind(source.id)
+        .execute(&mut *pool)
+        .await?;
+
+    let _ = sqlx::query(
+        "
+        INSERT INTO interest (user_id, tag_id, weight, last_view_time)
+        SELECT user_id, $2, weight, last_view_time FROM interest WHERE tag_id = $1
+        ON CONFLICT (user_id, tag_id) DO UPDATE SET
+            weight = interest.weight + EXCLUDED.weight,
+            last_view_time = GREATEST(interest.last_view_time, EXCLUDED.last_view_time)",
+    )
+    .bind(source.id)
+    .bind(target.id)
+    .execute(&mut *pool)
+    .await?;
+    let interests = sqlx::query("DELETE FROM interest WHERE tag_id = $1")
+        .bind(source.id)
+        .execute(&mut *pool)
+        .await?
+        .rows_affected();
+
+    let _ = sqlx::query("UPDATE tag_alias SET tag_id = $2 WHERE tag_id = $1")
+        .bind(source.id)
+        .bind(target.id)
+        .execute(&mut *pool)
+        .await?;
+    let _ = sqlx::query(
+        "
+        INSERT INTO tag_alias (alias, tag_id) VALUES (lower($1), $2)
+        ON CONFLICT (alias) DO UPDATE SET tag_id = EXCLUDED.tag_id",
+    )
+    .bind(&source.name)
+    .bind(target.id)
+    .execute(&mut *pool)
+    .await?;
+    let _ = sqlx::query("DELETE FROM tag WHERE id = $1")
+        .bind(source.id)
+        .execute(pool)
+        .await?;
+    Ok((news, interests))
+}
+
+#[tokio::test]
+async fn merge_tags_in_transaction() {
+    let pool = crate::test::get_test_pool().await;
+    let mut tx = pool.begin().await.unwrap();
+
+    let (user_id,) = sqlx::query_as::<_, (i32,)>(
+        "INSERT INTO users (username, password, sex, age) VALUES ('tag_merge_test', '', 'unknown', 18) RETURNING id",
+    )
+    .fetch_one(&mut tx)
+    .await
+    .unwrap();
+    let (news_id,) = sqlx::query_as::<_, (i32,)>(
+        "INSERT INTO news (title, source, abstracts, content, likes, link) VALUES ('t', 's', 'a', 'tag merge test', 0, '') RETURNING id",
+    )
+    .fetch_one(&mut tx)
+    .await
+    .unwrap();
+
+    // 只有大小写不同的 tag 解析为已有的 tag
+    let target = insert(&mut tx, "TagMergeAI").await.unwrap();
+    assert_eq!(insert(&mut tx, "tagmergeai").await.unwrap().id, target.id);
+    let source = insert(&mut tx, "TagMerge人工智能").await.unwrap();
+    let (source, target) = (&source, &target);
+
+    for (tag, weight) in [(source.id, 1.5), (target.id, 2.0)] {
+        sqlx::query("INSERT INTO news_tag (tag_id, news_id) VALUES ($1, $2)")
+            .bind(tag)
+            .bind(news_id)
+            .execute(&mut tx)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO interest (user_id, tag_id, weight) VALUES ($1, $2, $3)")
+            .bind(user_id)
+            .bind(tag)
+            .bind(weight)
+            .execute(&mut tx)
+            .await
+            .unwrap();
+    }
+    set_parent(&mut tx, target.id, Some(source.id))
+        .await
+        .unwrap();
+    assert!(is_ancestor(&mut tx, source.id, target.id).await.unwrap());
+
+    assert_eq!(merge(&mut tx, source, target).await.unwrap(), (1, 1));
+    let (weight, parent_id) = sqlx::query_as::<_, (f64, Option<i32>)>(
+        "
+        SELECT interest.weight, tag.parent_id FROM interest, tag
+        WHERE interest.user_id = $1 AND interest.tag_id = tag.id AND tag.id = $2",
+    )
+    .bind(user_id)
+    .bind(target.id)
+    .fetch_one(&mut tx)
+    .await
+    .unwrap();
+    assert_eq!(weight, 3.5);
+    assert_eq!(parent_id, None);
+    // 被合并的 tag 名成为别名
+    assert_eq!(
+        insert(&mut tx, "TagMerge人工智能").await.unwrap().id,
+        target.id
+    );
+
+    tx.rollback().await.unwrap();
 }
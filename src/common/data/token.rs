@@ -0,0 +1,85 @@
+use super::{DbPool, TransPool};
+
+/// 新增一条 refresh token 记录
+/// - 只保存 token 的摘要
+pub async fn insert_refresh_token(
+    pool: &mut TransPool<'_>,
+    user_id: i32,
+    session_id: i32,
+    token_hash: String,
+    ttl: i64,
+) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "INSERT INTO refresh_token (user_id, session_id, token_hash, expire_time) VALUES ($1, $2, $3, now() + make_interval(secs => $4))",
+    )
+    .bind(user_id)
+    .bind(session_id)
+    .bind(token_hash)
+    .bind(ttl as f64)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 消费一个 refresh token，成功时返回其所属的用户 id 以及会话 id
+/// - 只有未过期且未被使用过的 token 才能被消费，消费后立即作废
+/// - 会话功能上线前签发的 token 没有会话 id
+pub async fn consume_refresh_token(
+    pool: &mut TransPool<'_>,
+    token_hash: &str,
+) -> anyhow::Result<Option<(i32, Option<i32>)>> {
+    let result = sqlx::query_as::<_, (i32, Option<i32>)>(
+        "
+        UPDATE refresh_token SET revoked = true
+        WHERE token_hash = $1 AND revoked = false AND expire_time > now()
+        RETURNING user_id, session_id",
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?;
+    Ok(result)
+}
+
+/// 查找已作废 refresh token 的所属用户，用于检测 token 被重复使用
+/// - 会话被注销而作废的 token 不算作重复使用
+pub async fn find_revoked_owner(pool: &DbPool, token_hash: &str) -> anyhow::Result<Option<i32>> {
+    let result = sqlx::query_as::<_, (i32,)>(
+        "
+        SELECT refresh_token.user_id
+        FROM refresh_token
+        LEFT JOIN user_session ON user_session.id = refresh_token.session_id
+        WHERE refresh_token.token_hash = $1 AND refresh_token.revoked = true
+            AND user_session.revoked IS NOT TRUE",
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?;
+    Ok(result.map(|(user_id,)| user_id))
+}
+
+/// 作废用户的某个 refresh token
+pub async fn revoke_refresh_token(
+    pool: &DbPool,
+    user_id: i32,
+    token_hash: &str,
+) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "UPDATE refresh_token SET revoked = true WHERE user_id = $1 AND token_hash = $2",
+    )
+    .bind(user_id)
+    .bind(token_hash)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 作废用户所有的 refresh token
+pub async fn revoke_all_by_user_id(pool: &DbPool, user_id: i32) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "UPDATE refresh_token SET revoked = true WHERE user_id = $1 AND revoked = false",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
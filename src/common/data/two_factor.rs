@@ -0,0 +1,92 @@
+use super::{DbPool, TransPool};
+
+/// 保存待确认的 TOTP 密钥，确认前不会启用两步验证
+pub async fn set_pending_secret(pool: &DbPool, user_id: i32, secret: &str) -> anyhow::Result<()> {
+    let result = sqlx::query(
+        "UPDATE users SET totp_secret = $1, totp_last_step = NULL WHERE id = $2 AND totp_enabled = false",
+    )
+    .bind(secret)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(()),
+        _ => Err(anyhow::anyhow!("两步验证已启用")),
+    }
+}
+
+/// 启用两步验证
+pub async fn enable(pool: &mut TransPool<'_>, user_id: i32) -> anyhow::Result<()> {
+    let _ = sqlx::query("UPDATE users SET totp_enabled = true WHERE id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 关闭两步验证，同时清除密钥与恢复码
+pub async fn disable(pool: &mut TransPool<'_>, user_id: i32) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "UPDATE users SET totp_enabled = false, totp_secret = NULL, totp_last_step = NULL WHERE id = $1",
+    )
+    .bind(user_id)
+    .execute(&mut *pool)
+    .await?;
+    let _ = sqlx::query("DELETE FROM recovery_code WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *pool)
+        .await?;
+    Ok(())
+}
+
+/// 记录已使用的时间步，同一时间步或更早的验证码不能再次使用
+/// - 返回 false 表示验证码已被使用过
+pub async fn consume_step(pool: &DbPool, user_id: i32, step: i64) -> anyhow::Result<bool> {
+    let result = sqlx::query(
+        "
+        UPDATE users SET totp_last_step = $2
+        WHERE id = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)",
+    )
+    .bind(user_id)
+    .bind(step)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// 替换用户的全部恢复码
+pub async fn replace_recovery_codes(
+    pool: &mut TransPool<'_>,
+    user_id: i32,
+    code_hashes: Vec<String>,
+) -> anyhow::Result<()> {
+    let _ = sqlx::query("DELETE FROM recovery_code WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *pool)
+        .await?;
+    for code_hash in code_hashes {
+        let _ = sqlx::query("INSERT INTO recovery_code (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(code_hash)
+            .execute(&mut *pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// 使用一个恢复码，每个恢复码只能使用一次
+pub async fn consume_recovery_code(
+    pool: &DbPool,
+    user_id: i32,
+    code_hash: &str,
+) -> anyhow::Result<bool> {
+    let result = sqlx::query(
+        "UPDATE recovery_code SET used = true WHERE user_id = $1 AND code_hash = $2 AND used = false",
+    )
+    .bind(user_id)
+    .bind(code_hash)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
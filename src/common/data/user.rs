@@ -2,7 +2,11 @@ use poem_openapi::payload::Json;
 use tracing::error;
 
 use crate::{
-    common::{object, ApiResult, NoData},
+    common::{
+        object,
+        object::user::{Role, Sex},
+        ApiResult, NoData,
+    },
     rpc::recommend::{
         GetWeightRequest, GetWeightRequestUnit, TrainModelRequest, TrainModelRequestUnit,
     },
@@ -15,8 +19,13 @@ pub struct UserData {
     pub id: i32,
     pub username: String,
     pub password: String,
-    pub sex: String,
+    pub sex: Sex,
     pub age: i32,
+    pub role: Role,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
     pub create_time: chrono::NaiveDateTime,
     pub update_time: chrono::NaiveDateTime,
 }
@@ -35,42 +44,112 @@ pub async fn insert_new_user(
     pool: &DbPool,
     username: String,
     password: String,
-    sex: String,
+    sex: Sex,
     age: i32,
+    email: Option<String>,
+) -> anyhow::Result<i32> {
+    let result = sqlx::query_as::<_, (i32,)>(
+        "INSERT INTO users (username, password, sex, age, email) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+    )
+    .bind(username)
+    .bind(password)
+    .bind(sex)
+    .bind(age)
+    .bind(email)
+    .fetch_one(pool)
+    .await;
+
+    match result {
+        Ok((id,)) => Ok(id),
+        _ => Err(anyhow::anyhow!("新增用户失败")),
+    }
+}
+
+/// 判断邮箱是否已被其他用户使用
+pub async fn is_exist_by_email(pool: &DbPool, email: &str) -> anyhow::Result<bool> {
+    let result = sqlx::query("SELECT id FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_optional(pool)
+        .await?;
+    Ok(result.is_some())
+}
+
+/// 通过已验证的邮箱查找用户
+pub async fn find_by_verified_email(
+    pool: &DbPool,
+    email: &str,
+) -> anyhow::Result<Option<UserData>> {
+    let user = sqlx::query_as::<_, UserData>(
+        "SELECT * FROM users WHERE email = $1 AND email_verified = true",
+    )
+    .bind(email)
+    .fetch_optional(pool)
+    .await?;
+    Ok(user)
+}
+
+/// 更新用户邮箱，新邮箱需要重新验证
+pub async fn update_email_by_id(
+    pool: &mut TransPool<'_>,
+    user_id: i32,
+    email: &str,
 ) -> anyhow::Result<()> {
-    let result =
-        sqlx::query("INSERT INTO users (username, password, sex, age) VALUES ($1, $2, $3, $4)")
-            .bind(username)
-            .bind(password)
-            .bind(sex)
-            .bind(age)
-            .execute(pool)
-            .await;
+    let result = sqlx::query(
+        "UPDATE users SET email = $1, email_verified = false, update_time = now() WHERE id = $2",
+    )
+    .bind(email)
+    .bind(user_id)
+    .execute(pool)
+    .await;
 
     match result {
         Ok(r) if r.rows_affected() == 1 => Ok(()),
-        _ => Err(anyhow::anyhow!("新增用户失败")),
+        _ => Err(anyhow::anyhow!("更新用户邮箱失败")),
     }
 }
 
-/// 通过用户名查找用户
+/// 将用户当前邮箱标记为已验证
+pub async fn set_email_verified(pool: &DbPool, user_id: i32) -> anyhow::Result<()> {
+    let _ =
+        sqlx::query("UPDATE users SET email_verified = true WHERE id = $1 AND email IS NOT NULL")
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+    Ok(())
+}
+
+/// 通过用户名查找用户，不包括已注销的用户
 pub async fn find_by_name(pool: &DbPool, username: String) -> anyhow::Result<UserData> {
-    let user = sqlx::query_as::<_, UserData>("SELECT * FROM users WHERE username = $1")
-        .bind(username)
-        .fetch_one(pool)
-        .await?;
+    let user = sqlx::query_as::<_, UserData>(
+        "SELECT * FROM users WHERE username = $1 AND deleted_time IS NULL",
+    )
+    .bind(username)
+    .fetch_one(pool)
+    .await?;
     Ok(user)
 }
 
-/// 通过用户 id 查找用户
+/// 通过用户 id 查找用户，已注销的用户视为不存在
 pub async fn find_by_id(pool: &DbPool, user_id: i32) -> anyhow::Result<UserData> {
-    let user = sqlx::query_as::<_, UserData>("SELECT * FROM users WHERE id = $1")
-        .bind(user_id)
-        .fetch_one(pool)
-        .await?;
+    let user =
+        sqlx::query_as::<_, UserData>("SELECT * FROM users WHERE id = $1 AND deleted_time IS NULL")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
     Ok(user)
 }
 
+/// 批量查找用户，跳过不存在以及已注销的用户
+pub async fn find_by_ids(pool: &DbPool, user_ids: &[i32]) -> anyhow::Result<Vec<UserData>> {
+    let users = sqlx::query_as::<_, UserData>(
+        "SELECT * FROM users WHERE id = ANY($1) AND deleted_time IS NULL",
+    )
+    .bind(user_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(users)
+}
+
 /// 更新用户密码
 pub async fn update_password_by_id(
     pool: &mut TransPool<'_>,
@@ -89,6 +168,103 @@ pub async fn update_password_by_id(
     }
 }
 
+/// 更新用户角色
+pub async fn update_role_by_id(pool: &DbPool, user_id: i32, role: Role) -> anyhow::Result<()> {
+    let result = sqlx::query("UPDATE users SET role = $1, update_time = now() WHERE id = $2")
+        .bind(role)
+        .bind(user_id)
+        .execute(pool)
+        .await;
+
+    match result {
+        Ok(r) if r.rows_affected() == 1 => Ok(()),
+        _ => Err(anyhow::anyhow!("更新用户角色失败")),
+    }
+}
+
+/// 注销用户
+/// - 删除用户的点赞、兴趣、历史记录、token 与会话等数据
+/// - 保留 users 中的记录以免 id 被复用，个人信息全部清除
+pub async fn anonymize_by_id(pool: &mut TransPool<'_>, user_id: i32) -> anyhow::Result<()> {
+    super::like::delete_all_by_user_id(&mut *pool, user_id).await?;
+    for sql in [
+        "DELETE FROM history WHERE user_id = $1",
+        "DELETE FROM interest WHERE user_id = $1",
+        "DELETE FROM refresh_token WHERE user_id = $1",
+        "DELETE FROM user_session WHERE user_id = $1",
+        "DELETE FROM email_token WHERE user_id = $1",
+        "DELETE FROM recovery_code WHERE user_id = $1",
+        "DELETE FROM login_attempt WHERE key = (SELECT 'user:' || username FROM users WHERE id = $1)",
+    ] {
+        let _ = sqlx::query(sql).bind(user_id).execute(&mut *pool).await?;
+    }
+    // 需要在清除用户名之前执行
+    super::audit::anonymize_by_user_id(&mut *pool, user_id).await?;
+
+    // 用户名中的 # 不能通过注册校验，不会与正常用户冲突
+    let result = sqlx::query(
+        "
+        UPDATE users SET
+            username = '#deleted:' || id,
+            password = '',
+            sex = 'unknown',
+            age = 0,
+            email = NULL,
+            email_verified = false,
+            totp_secret = NULL,
+            totp_enabled = false,
+            totp_last_step = NULL,
+            update_time = now(),
+            deleted_time = now()
+        WHERE id = $1 AND deleted_time IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut *pool)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(()),
+        _ => Err(anyhow::anyhow!("注销用户失败")),
+    }
+}
+
+/// 通过用户 id 获取用户全部兴趣 tag 以及权重
+pub async fn get_interest_weights_by_user_id(
+    pool: &DbPool,
+    user_id: i32,
+) -> anyhow::Result<Vec<object::user::InterestExport>> {
+    let result = sqlx::query_as::<_, object::user::InterestExport>(
+        "
+        SELECT tag.name AS tag, interest.weight, interest.last_view_time
+        FROM interest
+        JOIN tag ON tag.id = interest.tag_id
+        WHERE interest.user_id = $1
+        ORDER BY interest.weight DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(result)
+}
+
+/// 通过用户 id 获取用户全部历史记录
+pub async fn get_all_history_by_user_id(
+    pool: &DbPool,
+    user_id: i32,
+) -> anyhow::Result<Vec<object::user::HistoryExport>> {
+    let result = sqlx::query_as::<_, object::user::HistoryExport>(
+        "
+        SELECT news.id AS news_id, news.title, history.last_view_time
+        FROM history, news
+        WHERE history.user_id = $1 AND history.news_id = news.id
+        ORDER BY history.last_view_time DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(result)
+}
+
 /// 更新用户 tags 信息
 pub async fn update_interests_by_id(
     pool: &mut TransPool<'_>,
@@ -100,17 +276,22 @@ pub async fn update_interests_by_id(
     let mut error_array = Vec::new();
 
     for interest in interests {
-        // 首先更新 tag 表，如果没有便插入
-        if let Err(e) = super::tag::insert(&mut *pool, &interest).await {
-            tracing::error!("{}", e);
-        }
+        // 首先把 tag 解析为已有的 tag，如果没有便插入
+        let tag = match super::tag::insert(&mut *pool, &interest).await {
+            Ok(tag) => tag,
+            Err(e) => {
+                error_array.push(interest);
+                error!("更新用户兴趣标签失败: {}", e);
+                continue;
+            }
+        };
 
         let sql_query = match change_time {
             true => {
                 "
-                INSERT INTO interest (user_id, news_tag, weight) 
+                INSERT INTO interest (user_id, tag_id, weight) 
                 VALUES ($1, $2, $3) 
-                ON CONFLICT (user_id, news_tag) DO 
+                ON CONFLICT (user_id, tag_id) DO 
                     UPDATE SET 
                     weight = $3,
                     last_view_time = now()
@@ -118,9 +299,9 @@ pub async fn update_interests_by_id(
             }
             false => {
                 "
-                INSERT INTO interest (user_id, news_tag, weight) 
+                INSERT INTO interest (user_id, tag_id, weight) 
                 VALUES ($1, $2, $3) 
-                ON CONFLICT (user_id, news_tag) DO 
+                ON CONFLICT (user_id, tag_id) DO 
                     UPDATE SET 
                     weight =$3
                 "
@@ -130,7 +311,7 @@ pub async fn update_interests_by_id(
         // 再更新 interest 表，与 user 相关性大
         let result = sqlx::query(sql_query)
             .bind(user_id)
-            .bind(&interest)
+            .bind(tag.id)
             .bind(weight)
             .execute(&mut *pool)
             .await;
@@ -153,12 +334,64 @@ pub async fn update_interests_by_id(
     }
 }
 
+/// 批量写入模型计算的兴趣权重，不更新浏览时间
+/// - 三个数组按下标一一对应，已注销的用户或已删除的 tag 会被跳过
+pub async fn set_interest_weights(
+    pool: &mut TransPool<'_>,
+    user_ids: &[i32],
+    tag_ids: &[i32],
+    weights: &[f64],
+) -> anyhow::Result<u64> {
+    let result = sqlx::query(
+        "
+        INSERT INTO interest (user_id, tag_id, weight)
+        SELECT DISTINCT ON (batch.user_id, batch.tag_id) batch.user_id, batch.tag_id, batch.weight
+        FROM unnest($1::INTEGER[], $2::INTEGER[], $3::FLOAT8[]) AS batch (user_id, tag_id, weight)
+        JOIN users ON users.id = batch.user_id AND users.deleted_time IS NULL
+        JOIN tag ON tag.id = batch.tag_id
+        ON CONFLICT (user_id, tag_id) DO UPDATE SET weight = EXCLUDED.weight",
+    )
+    .bind(user_ids)
+    .bind(tag_ids)
+    .bind(weights)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// 按新闻的 tag 提高用户的兴趣权重
+/// - 已有权重高于 weight 时保持不变
+pub async fn raise_interests_by_news_id(
+    pool: &mut TransPool<'_>,
+    user_id: i32,
+    news_id: i32,
+    weight: f64,
+) -> anyhow::Result<()> {
+    let _ = sqlx::query(
+        "
+        INSERT INTO interest (user_id, tag_id, weight)
+        SELECT $1, tag_id, $3 FROM news_tag WHERE news_id = $2
+        ON CONFLICT (user_id, tag_id) DO
+            UPDATE SET
+            weight = GREATEST(interest.weight, EXCLUDED.weight),
+            last_view_time = now()",
+    )
+    .bind(user_id)
+    .bind(news_id)
+    .bind(weight)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 /// 通过用户 id 获取用户兴趣 tag name
 pub async fn get_interests_by_user_id(pool: &DbPool, user_id: i32) -> anyhow::Result<Vec<String>> {
-    let result = sqlx::query_as::<_, (String,)>("SELECT news_tag FROM interest WHERE user_id = $1")
-        .bind(user_id)
-        .fetch_all(pool)
-        .await?
+    let result = sqlx::query_as::<_, (String,)>(
+        "SELECT tag.name FROM interest JOIN tag ON tag.id = interest.tag_id WHERE interest.user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?
         .into_iter()
         .map(|(interest,)| interest)
         .collect::<Vec<String>>();
@@ -169,9 +402,9 @@ pub async fn get_interests_by_user_id(pool: &DbPool, user_id: i32) -> anyhow::Re
 pub async fn get_tag_id_by_user_id(pool: &DbPool, user_id: i32, limit: f64) -> anyhow::Result<Vec<i32>> {
     let result = sqlx::query_as::<_, (i32,)>(
         "
-        SELECT tag.id 
-        FROM interest, tag 
-        WHERE interest.user_id = $1 AND interest.news_tag = tag.name AND interest.weight >= $2 
+        SELECT tag_id 
+        FROM interest 
+        WHERE user_id = $1 AND weight >= $2 
         ",
     )
     .bind(user_id)
@@ -184,29 +417,68 @@ pub async fn get_tag_id_by_user_id(pool: &DbPool, user_id: i32, limit: f64) -> a
     Ok(result)
 }
 
-/// 通过用户 id 获取用户历史记录
+/// 历史记录以及其分页排序键
+#[derive(sqlx::FromRow)]
+pub struct HistoryRow {
+    pub history_id: i32,
+    pub last_view_time: chrono::NaiveDateTime,
+    #[sqlx(flatten)]
+    pub news: object::news::AbstractResponse,
+}
+
+/// 通过用户 id 分页获取用户历史记录，按浏览时间倒序
+/// - after 为上一页最后一条记录的 (浏览时间, 历史记录 id)
+/// - 只返回已发布的新闻
 pub async fn get_history_by_user_id(
     pool: &DbPool,
     user_id: i32,
-) -> anyhow::Result<Vec<object::news::AbstractResponse>> {
-    let mut historys = sqlx::query_as::<_, object::news::AbstractResponse>("
-        SELECT news.id as news_id, news.title, news.abstracts, news.source, news.create_time, news.likes as like, array_agg(news_tag.tag_name) as tags
-        FROM history, news
-        JOIN news_tag ON news_tag.news_id = news.id
-        WHERE  history.user_id = $1 AND history.news_id = news.id
-        GROUP BY news.id
-        LIMIT 50")
+    after: Option<(chrono::NaiveDateTime, i32)>,
+    limit: i64,
+) -> anyhow::Result<Vec<HistoryRow>> {
+    let after_time = after.map(|(time, _)| time);
+    let after_id = after.map(|(_, id)| id);
+    let historys = sqlx::query_as::<_, HistoryRow>("
+        SELECT history.id AS history_id, history.last_view_time,
+            news.id as news_id, news.title, news.abstracts, news.source, news.create_time, news.likes as like,
+            COALESCE(array_agg(tag.name) FILTER (WHERE tag.name IS NOT NULL), '{}') as tags
+        FROM history
+        JOIN news ON news.id = history.news_id
+        LEFT JOIN news_tag ON news_tag.news_id = news.id
+        LEFT JOIN tag ON tag.id = news_tag.tag_id
+        WHERE history.user_id = $1 AND news.status = 'published'
+            AND ($2::TIMESTAMP IS NULL OR (history.last_view_time, history.id) < ($2, $3))
+        GROUP BY history.id, news.id
+        ORDER BY history.last_view_time DESC, history.id DESC
+        LIMIT $4")
         .bind(user_id)
+        .bind(after_time)
+        .bind(after_id)
+        .bind(limit)
         .fetch_all(pool)
         .await?;
-    historys.sort_by(|a, b| {
-        b.create_time
-            .partial_cmp(&a.create_time)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
     Ok(historys)
 }
 
+/// 分页获取未注销的用户，按 id 倒序
+pub async fn list(
+    pool: &DbPool,
+    after: Option<i32>,
+    limit: i64,
+) -> anyhow::Result<Vec<object::user::UserSummary>> {
+    let users = sqlx::query_as::<_, object::user::UserSummary>(
+        "
+        SELECT id, username, role, email, email_verified, create_time FROM users
+        WHERE deleted_time IS NULL AND ($1::INTEGER IS NULL OR id < $1)
+        ORDER BY id DESC
+        LIMIT $2",
+    )
+    .bind(after)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(users)
+}
+
 /// 更新用户历史记录
 pub async fn update_history(
     pool: &mut TransPool<'_>,
@@ -225,9 +497,9 @@ pub async fn update_history(
 pub async fn get_train_model_data(pool: &DbPool) -> anyhow::Result<TrainModelRequest> {
     let result = sqlx::query_as::<_, (i32, i32, f64)>(
         "
-        SELECT interest.user_id, tag.id as tag_id, interest.weight
-        FROM interest, tag
-        WHERE tag.name = interest.news_tag",
+        SELECT interest.user_id, interest.tag_id, interest.weight
+        FROM interest, users
+        WHERE users.id = interest.user_id AND users.deleted_time IS NULL",
     )
     .fetch_all(pool)
     .await?
@@ -245,9 +517,10 @@ pub async fn get_train_model_data(pool: &DbPool) -> anyhow::Result<TrainModelReq
 pub async fn update_weight_data(pool: &DbPool) -> anyhow::Result<GetWeightRequest> {
     let result: Vec<GetWeightRequestUnit> = sqlx::query_as::<_, (i32, i32, f64, chrono::NaiveDateTime)>(
         "
-        SELECT interest.user_id, tag.id as tag_id, interest.weight, interest.last_view_time as time
-        FROM interest, tag
-        WHERE tag.name = interest.news_tag AND interest.weight > 0",
+        SELECT interest.user_id, interest.tag_id, interest.weight, interest.last_view_time as time
+        FROM interest, users
+        WHERE interest.weight > 0
+            AND users.id = interest.user_id AND users.deleted_time IS NULL",
     )
     .fetch_all(pool)
     .await?
@@ -275,3 +548,100 @@ async fn test_send_train_model() {
     let res = get_train_model_data(&pool).await.unwrap();
     println!("{:?}", res);
 }
+
+#[tokio::test]
+async fn anonymize_clears_audit_log() {
+    let pool = crate::test::get_test_pool().await;
+    let mut tx = pool.begin().await.unwrap();
+
+    let (user_id,) = sqlx::query_as::<_, (i32,)>(
+        "INSERT INTO users (username, password, sex, age) VALUES ('anonymize_test', '', 'unknown', 18) RETURNING id",
+    )
+    .fetch_one(&mut tx)
+    .await
+    .unwrap();
+    for (actor, target) in [
+        (Some(user_id), None),
+        (None, Some(format!("user:{}", user_id))),
+    ] {
+        sqlx::query(
+            "
+            INSERT INTO audit_log (action, actor_user_id, target, ip, payload)
+            VALUES ('login_failure', $1, $2, '10.0.0.1', '{\"username\": \"anonymize_test\", \"reason\": \"x\"}')",
+        )
+        .bind(actor)
+        .bind(target)
+        .execute(&mut tx)
+        .await
+        .unwrap();
+    }
+    // 用户不存在时的登录失败只能按用户名关联
+    sqlx::query(
+        "INSERT INTO audit_log (action, ip, payload) VALUES ('login_failure', '10.0.0.1', '{\"username\": \"anonymize_test\"}')",
+    )
+    .execute(&mut tx)
+    .await
+    .unwrap();
+
+    anonymize_by_id(&mut tx, user_id).await.unwrap();
+
+    let rows = sqlx::query_as::<_, (String, serde_json::Value)>(
+        "SELECT ip, payload FROM audit_log WHERE ip = '10.0.0.1' OR payload->>'username' = 'anonymize_test' OR actor_user_id = $1 OR target = 'user:' || $1",
+    )
+    .bind(user_id)
+    .fetch_all(&mut tx)
+    .await
+    .unwrap();
+    assert_eq!(rows.len(), 2);
+    for (ip, payload) in rows {
+        assert_eq!(ip, "");
+        assert_eq!(payload, serde_json::json!({ "reason": "x" }));
+    }
+
+    tx.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn view_keeps_like_weight() {
+    let pool = crate::test::get_test_pool().await;
+    let mut tx = pool.begin().await.unwrap();
+
+    let (user_id,) = sqlx::query_as::<_, (i32,)>(
+        "INSERT INTO users (username, password, sex, age) VALUES ('view_weight_test', '', 'unknown', 18) RETURNING id",
+    )
+    .fetch_one(&mut tx)
+    .await
+    .unwrap();
+    let (news_id,) = sqlx::query_as::<_, (i32,)>(
+        "INSERT INTO news (title, source, abstracts, content, likes, link) VALUES ('t', 's', 'a', 'view weight test', 0, '') RETURNING id",
+    )
+    .fetch_one(&mut tx)
+    .await
+    .unwrap();
+    let tag = super::tag::insert(&mut tx, "ViewWeightTest").await.unwrap();
+    sqlx::query("INSERT INTO news_tag (tag_id, news_id) VALUES ($1, $2)")
+        .bind(tag.id)
+        .bind(news_id)
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+    // 先点赞再浏览，浏览的权重更低，不会覆盖点赞的权重
+    raise_interests_by_news_id(&mut tx, user_id, news_id, 5.5)
+        .await
+        .unwrap();
+    raise_interests_by_news_id(&mut tx, user_id, news_id, 4.8)
+        .await
+        .unwrap();
+    let (weight,) = sqlx::query_as::<_, (f64,)>(
+        "SELECT weight FROM interest WHERE user_id = $1 AND tag_id = $2",
+    )
+    .bind(user_id)
+    .bind(tag.id)
+    .fetch_one(&mut tx)
+    .await
+    .unwrap();
+    assert_eq!(weight, 5.5);
+
+    tx.rollback().await.unwrap();
+}
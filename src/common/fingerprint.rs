@@ -0,0 +1,170 @@
+// 新闻正文指纹
+// content_hash 用于拒绝正文相同的新闻，simhash 用于发现正文相似的新闻
+
+use std::collections::HashMap;
+
+use sha2::{Digest, Sha256};
+
+/// 相似新闻的最大汉明距离
+/// - 分组时把 64 位 simhash 分为 MAX_DISTANCE + 1 段，距离不超过 MAX_DISTANCE 的两条新闻至少有一段相同
+pub const MAX_DISTANCE: u32 = 3;
+
+/// 规范化正文，只保留文字与数字，英文转为小写
+pub fn normalize(content: &str) -> String {
+    content
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 规范化正文的 sha256 摘要，空白与标点不同的正文视为相同
+pub fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(normalize(content).as_bytes()))
+}
+
+/// 正文特征：英文与数字按单词切分，其他文字按相邻两字切分
+pub fn features(content: &str) -> Vec<String> {
+    let mut features = Vec::new();
+    let mut word = String::new();
+    let mut last: Option<char> = None;
+    for c in content.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            word.push(c);
+            last = None;
+            continue;
+        }
+        if !word.is_empty() {
+            features.push(std::mem::take(&mut word));
+        }
+        match (last, c.is_alphanumeric()) {
+            (Some(prev), true) => features.push(format!("{}{}", prev, c)),
+            (None, true) => features.push(c.to_string()),
+            _ => (),
+        }
+        last = Some(c).filter(|c| c.is_alphanumeric());
+    }
+    if !word.is_empty() {
+        features.push(word);
+    }
+    features
+}
+
+/// 稳定的 64 位 hash（FNV-1a 加 murmur3 的 fmix64），结果会保存到数据库，不能使用 DefaultHasher
+fn hash64(feature: &str) -> u64 {
+    let mut hash = feature.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
+/// 正文的 64 位 simhash，相似的正文汉明距离较小
+pub fn simhash(content: &str) -> u64 {
+    let mut weights = [0i32; 64];
+    for feature in features(content) {
+        let hash = hash64(&feature);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            match hash >> bit & 1 {
+                1 => *weight += 1,
+                _ => *weight -= 1,
+            }
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |simhash, (bit, _)| simhash | 1 << bit)
+}
+
+/// 两个 simhash 的汉明距离
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    let mut i = i;
+    while parent[i] != root {
+        i = std::mem::replace(&mut parent[i], root);
+    }
+    root
+}
+
+/// 把 simhash 距离不超过 max_distance 的新闻分为一组，相似关系可以传递
+/// - 只返回包含两条以上新闻的组，组内按 id 排序，组之间按新闻数倒序
+pub fn clusters(items: &[(i32, u64)], max_distance: u32) -> Vec<Vec<i32>> {
+    let max_distance = max_distance.min(MAX_DISTANCE);
+    let bands = MAX_DISTANCE as usize + 1;
+    let width = 64 / bands;
+
+    let mut parent = (0..items.len()).collect::<Vec<usize>>();
+    for band in 0..bands {
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, (_, simhash)) in items.iter().enumerate() {
+            let key = simhash >> (band * width) & ((1 << width) - 1);
+            buckets.entry(key).or_default().push(i);
+        }
+        for bucket in buckets.values() {
+            for (n, &i) in bucket.iter().enumerate() {
+                for &j in &bucket[n + 1..] {
+                    if distance(items[i].1, items[j].1) <= max_distance {
+                        let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                        parent[a] = b;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<i32>> = HashMap::new();
+    for (i, (id, _)) in items.iter().enumerate() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(*id);
+    }
+    let mut clusters = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort();
+            group
+        })
+        .collect::<Vec<Vec<i32>>>();
+    clusters.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+    clusters
+}
+
+#[test]
+fn fingerprint_finds_duplicates() {
+    assert_eq!(
+        content_hash("Rust 1.70 发布了！\n稀疏索引"),
+        content_hash("rust1.70发布了 稀疏索引。")
+    );
+    assert_ne!(content_hash("Rust 发布"), content_hash("Rust 发布了"));
+
+    let base = "新闻推荐系统使用 Rust 编写后端服务，通过 gRPC 调用推荐模型，\
+        根据用户的浏览记录与点赞数据计算兴趣权重，并定时训练模型更新推荐结果。";
+    let edited = base.replace("定时训练模型", "每天训练模型");
+    let other = "今日股市三大指数集体收涨，成交额较上一交易日有所放大，\
+        半导体与新能源板块表现活跃，北向资金全天净买入。";
+    let (a, b, c) = (simhash(base), simhash(&edited), simhash(other));
+    assert!(distance(a, b) < distance(a, c));
+    assert!(distance(a, c) > MAX_DISTANCE);
+
+    let items = [
+        (1, a),
+        (2, c),
+        (3, a ^ 0b101),
+        (4, a ^ 0b101 ^ 1 << 40),
+        (5, !a),
+    ];
+    assert_eq!(clusters(&items, 2), vec![vec![1, 3, 4]]);
+    assert_eq!(clusters(&items, 0), Vec::<Vec<i32>>::new());
+}
@@ -0,0 +1,65 @@
+// HTML 转纯文本
+// 用于订阅条目与新闻正文，只处理常见的标签与实体
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// 匹配 HTML 标签
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[a-zA-Z/!?][^>]*>").unwrap());
+
+/// 匹配换行的 HTML 标签
+static BREAK_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<br\s*/?>|</(p|div|li|h[1-6])>").unwrap());
+
+/// 匹配 HTML 实体
+static ENTITY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"&(#x?[0-9a-fA-F]+|[a-zA-Z]+);").unwrap());
+
+/// 把连续的空白合并为一个空格
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn decode_entity(entity: &str) -> Option<String> {
+    let c = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        _ => {
+            let code = match entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => entity.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some(c.to_string())
+}
+
+/// 把 HTML 转为纯文本，段落之间保留一个换行
+pub fn html_to_text(html: &str) -> String {
+    let text = BREAK_RE.replace_all(html, "\n");
+    let text = TAG_RE.replace_all(&text, "");
+    let text = ENTITY_RE.replace_all(&text, |caps: &regex::Captures| {
+        decode_entity(&caps[1]).unwrap_or_else(|| caps[0].to_string())
+    });
+    text.lines()
+        .map(collapse_whitespace)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[test]
+fn html_to_text_keeps_plain_text() {
+    assert_eq!(
+        html_to_text("<p>第一段 &amp; <b>加粗</b></p><p>第二段<br/>换行</p>"),
+        "第一段 & 加粗\n第二段\n换行"
+    );
+    assert_eq!(html_to_text("1 < 2 且 3 > 2"), "1 < 2 且 3 > 2");
+}
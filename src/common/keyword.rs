@@ -0,0 +1,171 @@
+// 关键词提取
+// 以已有 tag 为词典做正向最大匹配分词，再按 TF-IDF 计算每个 tag 的置信度
+
+use std::collections::{BTreeSet, HashMap};
+
+/// 词典中参与匹配的最大词长（字符数），更长的 tag 不会被自动匹配
+const MAX_WORD_LENGTH: usize = 16;
+
+/// 标题中出现一次相当于正文中出现的次数
+const TITLE_WEIGHT: f64 = 3.0;
+
+/// TF-IDF 分数等于该值时置信度为 0.5
+const HALF_SCORE: f64 = 4.0;
+
+/// 分词词典，英文不区分大小写
+pub struct Dictionary {
+    /// 小写的词 -> tag 名
+    words: HashMap<Vec<char>, String>,
+    /// 词典中出现过的词长，从长到短匹配
+    lengths: Vec<usize>,
+}
+
+/// 一个 tag 在标题与正文中的匹配情况
+#[derive(Default)]
+pub struct Term {
+    /// 加权词频
+    pub frequency: f64,
+    /// 实际匹配到的词（小写），可能是 tag 名或别名
+    pub words: BTreeSet<String>,
+}
+
+fn is_word_char(c: Option<&char>) -> bool {
+    matches!(c, Some(c) if c.is_ascii_alphanumeric())
+}
+
+impl Dictionary {
+    /// 由 (词, tag 名) 建立词典，tag 名与别名都作为词，相同的词后出现的优先
+    pub fn new(entries: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut lengths = BTreeSet::new();
+        let words = entries
+            .into_iter()
+            .filter_map(|(word, name)| {
+                let word = word
+                    .trim()
+                    .chars()
+                    .map(|c| c.to_ascii_lowercase())
+                    .collect::<Vec<char>>();
+                match word.is_empty() || word.len() > MAX_WORD_LENGTH {
+                    true => None,
+                    false => {
+                        lengths.insert(word.len());
+                        Some((word, name))
+                    }
+                }
+            })
+            .collect();
+        Self {
+            words,
+            lengths: lengths.into_iter().rev().collect(),
+        }
+    }
+
+    /// 正向最大匹配分词，只返回词典中的词（小写）及其对应的 tag 名
+    /// - 英文与数字组成的词需要完整匹配，不匹配单词的一部分
+    pub fn segment(&self, text: &str) -> Vec<(String, &str)> {
+        let chars = text
+            .chars()
+            .map(|c| c.to_ascii_lowercase())
+            .collect::<Vec<char>>();
+        let mut words = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let matched = self.lengths.iter().find_map(|&len| {
+                let word = chars.get(i..i + len)?;
+                let name = self.words.get(word)?;
+                // 英文词的前后不能紧接着字母或数字
+                let starts_word = word[0].is_ascii_alphanumeric();
+                let ends_word = word[len - 1].is_ascii_alphanumeric();
+                if (starts_word && i > 0 && is_word_char(chars.get(i - 1)))
+                    || (ends_word && is_word_char(chars.get(i + len)))
+                {
+                    return None;
+                }
+                Some((word, name.as_str()))
+            });
+            match matched {
+                Some((word, name)) => {
+                    let len = word.len();
+                    words.push((word.iter().collect(), name));
+                    i += len;
+                }
+                None => i += 1,
+            }
+        }
+        words
+    }
+
+    /// 统计标题与正文中各个 tag 的加权词频以及匹配到的词
+    pub fn term_frequency(&self, title: &str, content: &str) -> HashMap<&str, Term> {
+        let mut frequency = HashMap::<&str, Term>::new();
+        for (text, weight) in [(title, TITLE_WEIGHT), (content, 1.0)] {
+            for (word, name) in self.segment(text) {
+                let term = frequency.entry(name).or_default();
+                term.frequency += weight;
+                term.words.insert(word);
+            }
+        }
+        frequency
+    }
+}
+
+/// 由词频与文档频率计算置信度，取值在 0 到 1 之间
+/// - total 为语料中的新闻数，df 为包含该词的新闻数
+pub fn confidence(tf: f64, df: i64, total: i64) -> f64 {
+    if tf <= 0.0 {
+        return 0.0;
+    }
+    let idf = ((total.max(0) as f64 + 1.0) / (df.max(0) as f64 + 1.0)).ln() + 1.0;
+    let score = (1.0 + tf.ln()) * idf;
+    score / (score + HALF_SCORE)
+}
+
+#[test]
+fn dictionary_segments_text() {
+    let dictionary = Dictionary::new(
+        ["机器学习", "学习", "Rust", "GO", "人工智能", "ai"]
+            .iter()
+            .map(|name| (name.to_string(), name.to_string()))
+            .chain([("ai".to_string(), "人工智能".to_string())]),
+    );
+    let segment = |text| {
+        dictionary
+            .segment(text)
+            .into_iter()
+            .map(|(word, name)| format!("{}:{}", word, name))
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(
+        segment("用 rust 做机器学习，不是 rusty 也不是 Google，Go!"),
+        vec!["rust:Rust", "机器学习:机器学习", "go:GO"]
+    );
+    // 别名匹配到对应的 tag
+    assert_eq!(
+        segment("AI 与人工智能"),
+        vec!["ai:人工智能", "人工智能:人工智能"]
+    );
+
+    let frequency = dictionary.term_frequency("Rust 机器学习", "学习 Rust，学习机器学习");
+    assert_eq!(frequency["Rust"].frequency, 4.0);
+    assert_eq!(frequency["机器学习"].frequency, 4.0);
+    assert_eq!(frequency["学习"].frequency, 2.0);
+    assert!(!frequency.contains_key("人工智能"));
+
+    // 记录实际匹配到的词，用于按该词统计文档频率
+    let frequency = dictionary.term_frequency("AI 新闻", "rust");
+    assert_eq!(frequency["人工智能"].frequency, 3.0);
+    assert_eq!(
+        frequency["人工智能"].words.iter().collect::<Vec<_>>(),
+        vec!["ai"]
+    );
+    assert_eq!(
+        frequency["Rust"].words.iter().collect::<Vec<_>>(),
+        vec!["rust"]
+    );
+
+    // 出现次数越多、越少见的词置信度越高
+    assert!(confidence(4.0, 10, 1000) > confidence(1.0, 10, 1000));
+    assert!(confidence(4.0, 10, 1000) > confidence(4.0, 500, 1000));
+    assert!(confidence(100.0, 0, 1000) < 1.0);
+    assert_eq!(confidence(0.0, 0, 1000), 0.0);
+}
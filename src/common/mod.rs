@@ -2,8 +2,17 @@
 
 use poem_openapi::{payload::Json, ApiResponse, Object};
 
+use self::object::user::TwoFactorChallenge;
+
+pub mod audit;
+pub mod cursor;
 pub mod data;
+pub mod fingerprint;
+pub mod html;
+pub mod keyword;
+pub mod summary;
 pub mod object;
+pub mod validate;
 
 #[derive(Object)]
 pub struct ErrorMessage {
@@ -18,6 +27,17 @@ impl ErrorMessage {
     }
 }
 
+/// 字段校验错误
+#[derive(Object)]
+pub struct FieldError {
+    /// 字段名
+    pub field: String,
+    /// 错误类型
+    pub code: String,
+    /// 错误信息
+    pub message: String,
+}
+
 /// Api 异常处理
 #[derive(ApiResponse)]
 pub enum ApiError {
@@ -72,6 +92,62 @@ pub enum ApiError {
     /// 其他错误
     #[oai(status = 860)]
     Error(Json<ErrorMessage>),
+
+    /// refresh token 无效、过期或已被使用
+    #[oai(status = 861)]
+    RefreshTokenInvalid,
+
+    /// 请求参数校验失败，按字段返回错误
+    #[oai(status = 862)]
+    ValidationError(Json<Vec<FieldError>>),
+
+    /// 需要两步验证，使用返回的 challenge token 提交验证码
+    #[oai(status = 863)]
+    TwoFactorRequired(Json<TwoFactorChallenge>),
+
+    /// 两步验证码错误
+    #[oai(status = 864)]
+    TwoFactorCodeError,
+
+    /// 两步验证状态不允许该操作
+    #[oai(status = 865)]
+    TwoFactorStateError(Json<ErrorMessage>),
+
+    /// 邮箱已被其他用户使用
+    #[oai(status = 866)]
+    EmailAlreadyExists,
+
+    /// 邮件中的 token 无效、已过期或已被使用
+    #[oai(status = 867)]
+    EmailTokenInvalid,
+
+    /// 会话不存在或已被注销
+    #[oai(status = 868)]
+    SessionNotExists,
+
+    /// API key 不存在或已被注销
+    #[oai(status = 869)]
+    ApiKeyNotExists,
+
+    /// 正文与已有新闻相同
+    #[oai(status = 870)]
+    NewsAlreadyExists,
+
+    /// 新闻不存在
+    #[oai(status = 404)]
+    NewsNotExists,
+
+    /// tag 不存在
+    #[oai(status = 404)]
+    TagNotExists,
+
+    /// 登录失败次数过多，暂时锁定
+    #[oai(status = 429)]
+    TooManyAttempts(#[oai(header = "Retry-After")] u64),
+
+    /// 服务繁忙，稍后重试
+    #[oai(status = 503)]
+    ServiceBusy(#[oai(header = "Retry-After")] u64),
 }
 
 /// 无数据返回
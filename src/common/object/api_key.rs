@@ -0,0 +1,106 @@
+use poem_openapi::{Enum, Object};
+use serde::Serialize;
+use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
+use validator::Validate;
+
+use super::user::Role;
+
+/// API key 权限范围
+#[derive(Enum, Serialize, sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "varchar")]
+pub enum Scope {
+    /// 发布新闻
+    #[oai(rename = "news:write")]
+    #[serde(rename = "news:write")]
+    #[sqlx(rename = "news:write")]
+    NewsWrite,
+    /// 查看用户信息
+    #[oai(rename = "users:read")]
+    #[serde(rename = "users:read")]
+    #[sqlx(rename = "users:read")]
+    UsersRead,
+    /// 查看统计数据
+    #[oai(rename = "stats:read")]
+    #[serde(rename = "stats:read")]
+    #[sqlx(rename = "stats:read")]
+    StatsRead,
+}
+
+impl PgHasArrayType for Scope {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_varchar")
+    }
+}
+
+impl Role {
+    /// 使用用户 token 访问时，角色对应的权限范围
+    pub fn scopes(&self) -> &'static [Scope] {
+        match self {
+            Role::User => &[],
+            Role::Editor => &[Scope::NewsWrite],
+            Role::Admin => &[Scope::NewsWrite, Scope::UsersRead, Scope::StatsRead],
+        }
+    }
+}
+
+/// 创建 API key 请求
+#[derive(Object, Validate)]
+pub struct CreateApiKeyRequest {
+    /// 名称，用于区分不同的调用方
+    #[validate(length(min = 1, max = 255, message = "名称长度需在 1 到 255 之间"))]
+    pub name: String,
+    /// 权限范围
+    #[validate(length(min = 1, message = "至少需要一个权限范围"))]
+    pub scopes: Vec<Scope>,
+    /// 有效期（秒），不填写时永久有效
+    #[validate(range(min = 1, message = "有效期需大于 0"))]
+    pub expires_in: Option<i64>,
+}
+
+/// 创建 API key 响应，key 只在创建时返回一次
+#[derive(Object)]
+pub struct CreateApiKeyResponse {
+    /// API key id
+    pub id: i32,
+    /// API key，放在 NRS-TOKEN 请求头中使用
+    pub key: String,
+}
+
+/// API key 信息
+#[derive(Object, sqlx::FromRow)]
+pub struct ApiKeyResponse {
+    /// API key id
+    pub id: i32,
+    /// 名称
+    pub name: String,
+    /// key 的前几位，用于辨认
+    pub key_prefix: String,
+    /// 权限范围
+    pub scopes: Vec<Scope>,
+    /// 创建者的用户 id
+    pub created_by: Option<i32>,
+    /// 创建时间
+    pub create_time: chrono::NaiveDateTime,
+    /// 过期时间
+    pub expire_time: Option<chrono::NaiveDateTime>,
+    /// 最近使用时间
+    pub last_used: Option<chrono::NaiveDateTime>,
+    /// 是否已注销
+    pub revoked: bool,
+}
+
+/// API key 列表响应
+#[derive(Object)]
+pub struct ApiKeyListResponse {
+    /// API key，按 id 倒序
+    pub keys: Vec<ApiKeyResponse>,
+    /// 下一页的 cursor，没有下一页时为空
+    pub next_cursor: Option<String>,
+}
+
+/// 注销 API key 请求
+#[derive(Object)]
+pub struct RevokeApiKeyRequest {
+    /// API key id
+    pub id: i32,
+}
@@ -0,0 +1,72 @@
+use poem_openapi::{Enum, Object};
+
+/// 审计日志记录的操作
+#[derive(Enum, sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[oai(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum AuditAction {
+    /// 登录成功
+    LoginSuccess,
+    /// 登录失败
+    LoginFailure,
+    /// 修改密码
+    PasswordChange,
+    /// 通过邮件重置密码
+    PasswordReset,
+    /// 更新兴趣 tag
+    InterestUpdate,
+    /// 注销账号
+    AccountDelete,
+    /// 发布新闻
+    NewsCreate,
+    /// 批量导入新闻
+    NewsBulkImport,
+    /// 修改新闻内容或状态
+    NewsUpdate,
+    /// 删除新闻
+    NewsDelete,
+    /// 替换新闻 tag
+    NewsTagUpdate,
+    /// 修改 tag 的父 tag 或别名
+    TagUpdate,
+    /// 合并 tag
+    TagMerge,
+    /// 查看用户信息
+    UserLookup,
+    /// 修改用户角色
+    RoleChange,
+    /// 创建 API key
+    ApiKeyCreate,
+    /// 注销 API key
+    ApiKeyRevoke,
+}
+
+/// 审计日志
+#[derive(Object, sqlx::FromRow)]
+pub struct AuditLogEntry {
+    /// 日志 id
+    pub id: i64,
+    /// 记录时间
+    pub create_time: chrono::NaiveDateTime,
+    /// 操作
+    pub action: AuditAction,
+    /// 操作者的用户 id
+    pub actor_user_id: Option<i32>,
+    /// 操作者的 API key id
+    pub actor_api_key_id: Option<i32>,
+    /// 操作对象，如 user:1、news:2
+    pub target: Option<String>,
+    /// 操作者 IP
+    pub ip: String,
+    /// 操作相关的数据
+    pub payload: serde_json::Value,
+}
+
+/// 审计日志分页响应
+#[derive(Object)]
+pub struct AuditLogResponse {
+    /// 日志，按时间倒序
+    pub entries: Vec<AuditLogEntry>,
+    /// 下一页的 cursor，没有下一页时为空
+    pub next_cursor: Option<String>,
+}
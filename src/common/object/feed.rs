@@ -0,0 +1,44 @@
+use poem_openapi::{Enum, Object};
+
+/// 订阅最近一次抓取的结果
+#[derive(Enum, sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[oai(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum FeedFetchStatus {
+    /// 抓取并解析成功
+    Ok,
+    /// 请求或解析失败，原因见 last_error
+    Error,
+}
+
+/// 订阅的抓取状态
+#[derive(Object, sqlx::FromRow)]
+pub struct FeedState {
+    /// 订阅 id
+    pub id: i32,
+    /// 订阅地址
+    pub url: String,
+    /// 订阅标题，同时作为新闻来源
+    pub title: Option<String>,
+    /// 最近一次抓取时间
+    pub last_fetch_time: Option<chrono::NaiveDateTime>,
+    /// 最近一次成功抓取的时间
+    pub last_success_time: Option<chrono::NaiveDateTime>,
+    /// 最近一次抓取的结果，尚未抓取时为空
+    pub last_status: Option<FeedFetchStatus>,
+    /// 最近一次抓取失败的原因
+    pub last_error: Option<String>,
+    /// 最近一次抓取新增的新闻数
+    pub last_inserted: i32,
+    /// 累计新增的新闻数
+    pub total_inserted: i32,
+}
+
+/// 订阅列表响应
+#[derive(Object)]
+pub struct FeedListResponse {
+    /// 订阅，按 id 排序
+    pub feeds: Vec<FeedState>,
+    /// 下一页的 cursor，没有下一页时为空
+    pub next_cursor: Option<String>,
+}
@@ -1,4 +1,9 @@
 // 定义操作结构体
 
+pub mod api_key;
+pub mod audit;
+pub mod feed;
 pub mod news;
+pub mod stats;
+pub mod tag;
 pub mod user;
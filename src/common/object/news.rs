@@ -1,13 +1,85 @@
-use poem_openapi::Object;
+use poem_openapi::{payload::PlainText, ApiRequest, Enum, Object};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
 
-#[derive(Object)]
+use crate::common::validate::validate_tags;
+
+/// 新闻状态，只有已发布的新闻对用户可见
+#[derive(Enum, Serialize, sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[oai(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum NewsStatus {
+    /// 已发布
+    Published,
+    /// 已下架，可以重新发布
+    Unpublished,
+    /// 已删除
+    Deleted,
+}
+
+#[derive(Object, Deserialize, Validate)]
 pub struct CreateNewsRequest {
-    pub title: String,             // 新闻标题
-    pub content: String,           // 新闻内容
-    pub source: String,            // 新闻来源
-    pub abstracts: Option<String>, // 新闻摘要
-    pub link: String,              // 新闻链接
-    pub tags: Vec<String>,         // 新闻 tag
+    /// 新闻标题
+    #[validate(length(min = 1, message = "标题不能为空"))]
+    pub title: String,
+    /// 新闻内容
+    #[validate(length(min = 1, message = "内容不能为空"))]
+    pub content: String,
+    /// 新闻来源
+    pub source: String,
+    /// 新闻摘要，不填时从内容中抽取
+    pub abstracts: Option<String>,
+    /// 新闻链接
+    pub link: String,
+    /// 新闻 tag，一条新闻最多 50 个
+    #[validate(
+        length(max = 50, message = "一条新闻最多 50 个 tag"),
+        custom = "validate_tags"
+    )]
+    pub tags: Vec<String>,
+}
+
+/// 批量导入新闻请求，每行一条 JSON 格式的 CreateNewsRequest
+#[derive(ApiRequest)]
+pub enum BulkNewsRequest {
+    #[oai(content_type = "application/x-ndjson")]
+    Ndjson(PlainText<String>),
+    PlainText(PlainText<String>),
+}
+
+/// 批量导入中单行的处理结果
+#[derive(Enum, Clone, Copy, Debug, PartialEq, Eq)]
+#[oai(rename_all = "lowercase")]
+pub enum BulkRowStatus {
+    /// 导入成功
+    Inserted,
+    /// 正文与已有新闻相同，已跳过
+    Duplicate,
+    /// 格式错误或校验失败，已跳过
+    Invalid,
+}
+
+/// 批量导入中单行的结果
+#[derive(Object)]
+pub struct BulkRowResult {
+    /// 行号，从 1 开始
+    pub line: i32,
+    pub status: BulkRowStatus,
+    /// 导入成功时的新闻 id
+    pub news_id: Option<i32>,
+    /// 失败原因
+    pub message: Option<String>,
+}
+
+/// 批量导入结果
+#[derive(Object)]
+pub struct BulkImportResponse {
+    pub inserted: i32,
+    pub duplicate: i32,
+    pub invalid: i32,
+    /// 每一行的结果，按行号排列
+    pub rows: Vec<BulkRowResult>,
 }
 
 #[derive(Object, sqlx::FromRow, PartialEq, Eq, Hash, Debug)]
@@ -32,6 +104,136 @@ pub struct DetailResponse {
     pub tags: Option<Vec<String>>,
 }
 
+/// 管理后台中的新闻详情，包含所有状态的新闻
+#[derive(Object, sqlx::FromRow)]
+pub struct AdminNews {
+    pub news_id: i32,
+    pub title: String,
+    pub abstracts: String,
+    pub content: String,
+    pub source: String,
+    /// 新闻原链接，早期导入的新闻可能没有
+    pub link: Option<String>,
+    pub status: NewsStatus,
+    pub create_time: chrono::NaiveDateTime,
+    pub like: i32,
+    /// 编辑指定的 tag
+    pub tags: Vec<String>,
+}
+
+/// 自动生成的 tag
+#[derive(Object, sqlx::FromRow)]
+pub struct AutoTag {
+    pub name: String,
+    /// 置信度，0 到 1 之间
+    pub confidence: f64,
+}
+
+/// 管理后台中的新闻详情响应
+#[derive(Object)]
+pub struct AdminNewsResponse {
+    #[oai(flatten)]
+    pub news: AdminNews,
+    /// 自动生成的 tag，按置信度倒序，不包含编辑已指定的 tag
+    pub auto_tags: Vec<AutoTag>,
+}
+
+/// 修改新闻请求，未填写的字段保持不变
+#[derive(Object, Validate)]
+pub struct UpdateNewsRequest {
+    /// 新闻标题
+    #[validate(length(min = 1, message = "标题不能为空"))]
+    pub title: Option<String>,
+    /// 新闻内容
+    #[validate(length(min = 1, message = "内容不能为空"))]
+    pub content: Option<String>,
+    /// 新闻摘要
+    #[validate(length(min = 1, message = "摘要不能为空"))]
+    pub abstracts: Option<String>,
+    /// 新闻来源
+    pub source: Option<String>,
+    /// 新闻原链接
+    pub link: Option<String>,
+    /// 新闻状态
+    pub status: Option<NewsStatus>,
+}
+
+/// 替换新闻 tag 请求
+#[derive(Object, Validate)]
+pub struct UpdateNewsTagsRequest {
+    /// 新的 tag 集合，一次最多 50 个
+    #[validate(
+        length(max = 50, message = "一条新闻最多 50 个 tag"),
+        custom = "validate_tags"
+    )]
+    pub tags: Vec<String>,
+}
+
+/// 新闻列表响应
+#[derive(Object)]
+pub struct NewsListResponse {
+    /// 新闻，按创建时间倒序
+    pub news: Vec<AbstractResponse>,
+    /// 下一页的 cursor，没有下一页时为空
+    pub next_cursor: Option<String>,
+}
+
+/// 疑似重复的一组新闻
+#[derive(Object)]
+pub struct DuplicateCluster {
+    /// 组内任意两条新闻 simhash 汉明距离的最大值，0 表示正文几乎相同
+    pub max_distance: i32,
+    /// 组内新闻，按 id 排序
+    pub news: Vec<AbstractResponse>,
+}
+
+/// 重复新闻报告
+#[derive(Object)]
+pub struct DuplicateReportResponse {
+    /// 疑似重复的组数
+    pub total: i32,
+    /// 按组内新闻数倒序，最多返回 100 组
+    pub clusters: Vec<DuplicateCluster>,
+}
+
+/// 搜索结果
+#[derive(Object)]
+pub struct SearchResult {
+    /// 新闻摘要信息
+    pub news: AbstractResponse,
+    /// 正文中命中关键词的片段，关键词以 <mark></mark> 标记，其余内容已做 HTML 转义
+    pub snippet: String,
+}
+
+/// 新闻搜索响应
+#[derive(Object)]
+pub struct SearchResponse {
+    /// 搜索结果，按相关度倒序
+    pub results: Vec<SearchResult>,
+    /// 下一页的 cursor，没有下一页时为空
+    pub next_cursor: Option<String>,
+}
+
+/// 点赞状态
+#[derive(Object)]
+pub struct LikeResponse {
+    /// 当前用户是否已点赞
+    pub liked: bool,
+    /// 新闻的点赞数
+    pub likes: i32,
+}
+
+/// 导出的点赞记录
+#[derive(Object, sqlx::FromRow)]
+pub struct LikeExport {
+    /// 新闻 id
+    pub news_id: i32,
+    /// 新闻标题
+    pub title: String,
+    /// 点赞时间
+    pub create_time: chrono::NaiveDateTime,
+}
+
 #[derive(Object)]
 pub struct RandomTagResponse {
     pub tags: Vec<String>,
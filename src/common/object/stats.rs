@@ -0,0 +1,16 @@
+use poem_openapi::Object;
+
+/// 系统统计数据
+#[derive(Object, sqlx::FromRow)]
+pub struct StatsResponse {
+    /// 用户数，不包括已注销的用户
+    pub user_count: i64,
+    /// 已发布的新闻数
+    pub published_news_count: i64,
+    /// 点赞数
+    pub like_count: i64,
+    /// 浏览记录数
+    pub view_count: i64,
+    /// 订阅数
+    pub feed_count: i64,
+}
@@ -0,0 +1,66 @@
+use poem_openapi::Object;
+use validator::Validate;
+
+use crate::common::validate::validate_tags;
+
+/// tag 的 id 与名称
+#[derive(Object, sqlx::FromRow, Clone, Debug)]
+pub struct TagBrief {
+    /// tag id
+    pub id: i32,
+    /// tag 名
+    pub name: String,
+}
+
+/// tag 详情
+#[derive(Object)]
+pub struct TagDetail {
+    /// tag id
+    pub id: i32,
+    /// tag 名
+    pub name: String,
+    /// 父 tag
+    pub parent: Option<TagBrief>,
+    /// 子 tag，按名称排序
+    pub children: Vec<TagBrief>,
+    /// 别名，小写，按字母排序
+    pub aliases: Vec<String>,
+}
+
+/// 设置父 tag 请求
+#[derive(Object)]
+pub struct UpdateTagParentRequest {
+    /// 父 tag id，为空时取消父 tag
+    pub parent_id: Option<i32>,
+}
+
+/// 替换 tag 别名请求
+#[derive(Object, Validate)]
+pub struct UpdateTagAliasesRequest {
+    /// 新的别名集合，一次最多 50 个，英文不区分大小写
+    #[validate(
+        length(max = 50, message = "一个 tag 最多 50 个别名"),
+        custom = "validate_tags"
+    )]
+    pub aliases: Vec<String>,
+}
+
+/// 合并 tag 请求
+#[derive(Object)]
+pub struct MergeTagRequest {
+    /// 被合并的 tag id，合并后删除，名称成为目标 tag 的别名
+    pub source_id: i32,
+    /// 目标 tag id
+    pub target_id: i32,
+}
+
+/// 合并 tag 的结果
+#[derive(Object)]
+pub struct MergeTagResponse {
+    /// 合并后的目标 tag
+    pub tag: TagDetail,
+    /// 改为目标 tag 的新闻数
+    pub news: i64,
+    /// 改为目标 tag 的用户兴趣数，已有目标 tag 兴趣的用户权重相加
+    pub interests: i64,
+}
@@ -1,7 +1,11 @@
-use poem_openapi::Object;
+use poem_openapi::{Enum, Object};
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
-use crate::common::data::user::UserData;
+use crate::common::{
+    data::user::UserData,
+    validate::{validate_password, validate_tags, USERNAME_RE},
+};
 
 use super::news;
 
@@ -17,21 +21,130 @@ pub struct LoginRequest {
 /// 用户登录成功返回 token
 #[derive(Object)]
 pub struct LoginSuccess {
-    /// 用户 token
+    /// 用户 access token
     pub token: String,
+    /// access token 有效期（秒）
+    pub expires_in: i64,
+    /// 用于换取新 token 的 refresh token，仅可使用一次
+    pub refresh_token: String,
 }
 
-/// 用户注册请求
+/// 刷新 token 请求
+#[derive(Object)]
+pub struct RefreshRequest {
+    /// 登录或上次刷新时获得的 refresh token
+    pub refresh_token: String,
+}
+
+/// 两步验证 challenge，登录时密码正确但开启了两步验证时返回
+#[derive(Object)]
+pub struct TwoFactorChallenge {
+    /// 提交验证码时携带的 challenge token
+    pub challenge_token: String,
+    /// challenge token 有效期（秒）
+    pub expires_in: i64,
+}
+
+/// 两步验证登录请求
 #[derive(Object)]
+pub struct TwoFactorLoginRequest {
+    /// 登录时返回的 challenge token
+    pub challenge_token: String,
+    /// 认证器 App 中的验证码或恢复码
+    pub code: String,
+}
+
+/// 开启两步验证返回的密钥
+#[derive(Object)]
+pub struct TwoFactorSetupResponse {
+    /// base32 编码的密钥
+    pub secret: String,
+    /// 可生成二维码供认证器 App 扫描
+    pub otpauth_uri: String,
+}
+
+/// 两步验证码请求
+#[derive(Object)]
+pub struct TwoFactorCodeRequest {
+    /// 认证器 App 中的验证码
+    pub code: String,
+}
+
+/// 关闭两步验证请求
+#[derive(Object)]
+pub struct TwoFactorDisableRequest {
+    /// 当前密码
+    pub password: String,
+    /// 认证器 App 中的验证码或恢复码
+    pub code: String,
+}
+
+/// 两步验证恢复码，只在开启时返回一次
+#[derive(Object)]
+pub struct RecoveryCodesResponse {
+    /// 恢复码，每个只能使用一次
+    pub recovery_codes: Vec<String>,
+}
+
+/// 用户登出请求
+#[derive(Object)]
+pub struct LogoutRequest {
+    /// 同时作废的 refresh token
+    pub refresh_token: Option<String>,
+}
+
+/// 用户性别
+#[derive(Enum, sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[oai(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum Sex {
+    Man,
+    Woman,
+    Unknown,
+}
+
+/// 用户注册请求
+#[derive(Object, Validate)]
 pub struct RegisterRequest {
-    /// 用户名
+    /// 用户名，3 到 32 个字符，只允许字母、数字、下划线以及中文
+    #[validate(
+        length(min = 3, max = 32, message = "用户名长度需在 3 到 32 之间"),
+        regex(
+            path = "USERNAME_RE",
+            message = "用户名只允许字母、数字、下划线以及中文"
+        )
+    )]
     pub username: String,
-    /// 密码
+    /// 密码，需满足密码策略
+    #[validate(custom = "validate_password")]
     pub password: String,
     /// 年龄
+    #[validate(range(min = 1, max = 150, message = "年龄需在 1 到 150 之间"))]
     pub age: i32,
-    /// 性别
-    pub sex: Option<String>,
+    /// 性别，默认为 unknown
+    pub sex: Option<Sex>,
+    /// 邮箱，填写后会发送验证邮件
+    #[validate(
+        email(message = "邮箱格式不正确"),
+        length(max = 255, message = "邮箱过长")
+    )]
+    pub email: Option<String>,
+}
+
+/// 用户角色，权限依次递增
+#[derive(
+    Enum, Serialize, Deserialize, sqlx::Type, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[oai(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum Role {
+    /// 普通用户
+    User,
+    /// 编辑，可以发布新闻
+    Editor,
+    /// 管理员
+    Admin,
 }
 
 /// 用户信息响应
@@ -39,30 +152,201 @@ pub struct RegisterRequest {
 pub struct InfoResponse {
     /// 用户名
     pub username: String,
+    /// 用户角色
+    pub role: Role,
     /// 兴趣 tag
     pub interests: Vec<String>,
     /// 年龄
     pub age: i32,
     /// 性别
-    pub sex: String,
+    pub sex: Sex,
+    /// 邮箱
+    pub email: Option<String>,
+    /// 邮箱是否已验证
+    pub email_verified: bool,
     /// 创建时间
     pub create_time: chrono::NaiveDateTime,
 }
 
 /// 用户信息更新请求
-#[derive(Object)]
+#[derive(Object, Validate)]
 pub struct UpdateRequest {
-    /// 兴趣 tag
+    /// 兴趣 tag，一次最多 50 个
+    #[validate(
+        length(max = 50, message = "一次最多更新 50 个兴趣 tag"),
+        custom = "validate_tags"
+    )]
     pub interests: Option<Vec<String>>,
-    /// 密码
+    /// 密码，需满足密码策略
+    #[validate(custom = "validate_password")]
     pub password: Option<String>,
+    /// 邮箱，修改后需要重新验证
+    #[validate(
+        email(message = "邮箱格式不正确"),
+        length(max = 255, message = "邮箱过长")
+    )]
+    pub email: Option<String>,
+}
+
+/// 邮箱验证请求
+#[derive(Object)]
+pub struct VerifyEmailRequest {
+    /// 验证邮件中的 token
+    pub token: String,
+}
+
+/// 申请重置密码请求
+#[derive(Object)]
+pub struct PasswordResetRequest {
+    /// 已验证的邮箱
+    pub email: String,
+}
+
+/// 重置密码请求
+#[derive(Object, Validate)]
+pub struct PasswordResetConfirm {
+    /// 重置密码邮件中的 token
+    pub token: String,
+    /// 新密码，需满足密码策略
+    #[validate(custom = "validate_password")]
+    pub password: String,
+}
+
+/// 修改用户角色请求
+#[derive(Object)]
+pub struct UpdateRoleRequest {
+    /// 用户 id
+    pub user_id: i32,
+    /// 新的角色
+    pub role: Role,
+}
+
+/// 登录会话
+#[derive(Object)]
+pub struct SessionResponse {
+    /// 会话 id
+    pub id: i32,
+    /// 登录时的 User-Agent
+    pub user_agent: String,
+    /// 登录时的 IP
+    pub ip: String,
+    /// 登录时间
+    pub create_time: chrono::NaiveDateTime,
+    /// 最近活动时间
+    pub last_seen: chrono::NaiveDateTime,
+    /// 是否为当前请求所在的会话
+    pub current: bool,
+}
+
+/// 注销会话请求
+#[derive(Object)]
+pub struct RevokeSessionRequest {
+    /// 会话 id
+    pub session_id: i32,
 }
 
 /// 用户历史记录响应
 #[derive(Object)]
 pub struct HistoryResponse {
-    /// 历史记录
+    /// 历史记录，按浏览时间倒序
     pub news: Vec<news::AbstractResponse>,
+    /// 下一页的 cursor，没有下一页时为空
+    pub next_cursor: Option<String>,
+}
+
+/// 用户列表中的用户信息
+#[derive(Object, sqlx::FromRow)]
+pub struct UserSummary {
+    /// 用户 id
+    pub id: i32,
+    /// 用户名
+    pub username: String,
+    /// 用户角色
+    pub role: Role,
+    /// 邮箱
+    pub email: Option<String>,
+    /// 邮箱是否已验证
+    pub email_verified: bool,
+    /// 创建时间
+    pub create_time: chrono::NaiveDateTime,
+}
+
+/// 用户列表响应
+#[derive(Object)]
+pub struct UserListResponse {
+    /// 用户，按 id 倒序
+    pub users: Vec<UserSummary>,
+    /// 下一页的 cursor，没有下一页时为空
+    pub next_cursor: Option<String>,
+}
+
+/// 注销账号请求
+#[derive(Object)]
+pub struct DeleteAccountRequest {
+    /// 当前密码
+    pub password: String,
+}
+
+/// 导出的用户资料
+#[derive(Object)]
+pub struct ProfileExport {
+    /// 用户 id
+    pub id: i32,
+    /// 用户名
+    pub username: String,
+    /// 用户角色
+    pub role: Role,
+    /// 年龄
+    pub age: i32,
+    /// 性别
+    pub sex: Sex,
+    /// 邮箱
+    pub email: Option<String>,
+    /// 邮箱是否已验证
+    pub email_verified: bool,
+    /// 是否开启两步验证
+    pub totp_enabled: bool,
+    /// 创建时间
+    pub create_time: chrono::NaiveDateTime,
+    /// 更新时间
+    pub update_time: chrono::NaiveDateTime,
+}
+
+/// 导出的兴趣 tag
+#[derive(Object, sqlx::FromRow)]
+pub struct InterestExport {
+    /// tag 名称
+    pub tag: String,
+    /// 兴趣权重
+    pub weight: f64,
+    /// 最近浏览时间
+    pub last_view_time: chrono::NaiveDateTime,
+}
+
+/// 导出的历史记录
+#[derive(Object, sqlx::FromRow)]
+pub struct HistoryExport {
+    /// 新闻 id
+    pub news_id: i32,
+    /// 新闻标题
+    pub title: String,
+    /// 最近浏览时间
+    pub last_view_time: chrono::NaiveDateTime,
+}
+
+/// 用户个人数据导出
+#[derive(Object)]
+pub struct ExportResponse {
+    /// 导出时间
+    pub export_time: chrono::NaiveDateTime,
+    /// 用户资料
+    pub profile: ProfileExport,
+    /// 兴趣 tag 以及权重
+    pub interests: Vec<InterestExport>,
+    /// 全部历史记录
+    pub history: Vec<HistoryExport>,
+    /// 全部点赞记录
+    pub likes: Vec<super::news::LikeExport>,
 }
 
 #[derive(Serialize, Deserialize, Object, PartialEq, Eq, Hash)]
@@ -71,6 +355,58 @@ pub struct UserSign {
     pub username: String,
 }
 
+/// access token 中携带的声明
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserClaims {
+    /// 用户 id
+    pub id: i32,
+    /// 用户名
+    pub username: String,
+    /// 用户角色
+    pub role: Role,
+    /// 会话 id
+    pub sid: i32,
+    /// 签发时间
+    pub iat: i64,
+    /// 过期时间
+    pub exp: i64,
+    /// token id
+    pub jti: String,
+}
+
+/// 两步验证 challenge token 中携带的声明
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChallengeClaims {
+    /// 用户 id
+    pub id: i32,
+    /// 固定为 2fa，避免与其他 token 混用
+    pub purpose: String,
+    /// 过期时间
+    pub exp: i64,
+    /// token id，使用后注销
+    pub jti: String,
+}
+
+impl UserClaims {
+    pub fn new(id: i32, username: String, role: Role, sid: i32, ttl: i64) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        UserClaims {
+            id,
+            username,
+            role,
+            sid,
+            iat: now,
+            exp: now + ttl,
+            jti: crate::util::gen_random_token(16),
+        }
+    }
+
+    /// 判断 token 在 now 时刻是否有效
+    pub fn is_valid_at(&self, now: i64) -> bool {
+        self.iat <= now && now < self.exp
+    }
+}
+
 impl UserSign {
     pub fn from(user: UserData) -> Self {
         UserSign {
@@ -79,3 +415,12 @@ impl UserSign {
         }
     }
 }
+
+#[test]
+fn claims_expire_after_ttl() {
+    let claims = UserClaims::new(1, "neko".into(), Role::User, 1, 60);
+    assert!(claims.is_valid_at(claims.iat));
+    assert!(claims.is_valid_at(claims.exp - 1));
+    assert!(!claims.is_valid_at(claims.exp));
+    assert!(!claims.is_valid_at(claims.iat - 1));
+}
@@ -0,0 +1,228 @@
+// 抽取式摘要
+// 按中英文标点切分句子，用 TextRank 计算句子的重要性，在长度限制内按原文顺序选取句子
+
+use std::collections::HashSet;
+
+use super::{fingerprint, html::html_to_text};
+
+/// 参与排序的最大句子数，更靠后的句子不会被选入摘要
+const MAX_SENTENCES: usize = 200;
+
+/// TextRank 的阻尼系数
+const DAMPING: f64 = 0.85;
+
+/// TextRank 的最大迭代次数
+const MAX_ITERATIONS: usize = 50;
+
+/// 两次迭代的分数差小于该值时停止迭代
+const TOLERANCE: f64 = 1e-6;
+
+/// 计算句子相似度时忽略的英文常用词
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "has", "have", "in", "is",
+    "it", "its", "of", "on", "or", "that", "the", "this", "to", "was", "were", "will", "with",
+];
+
+/// 句子结束的标点
+fn is_terminator(c: char) -> bool {
+    matches!(c, '。' | '！' | '？' | '；' | '…' | '!' | '?' | ';')
+}
+
+/// 紧跟在句末标点之后、仍属于该句的字符
+fn is_closing(c: char) -> bool {
+    matches!(c, '"' | '\'' | '”' | '’' | '」' | '』' | '）' | ')' | '】')
+}
+
+/// 按中英文标点与换行切分句子，句末标点保留在句子中
+/// - 英文句号后面需要是空白或结尾，避免切开小数与缩写的域名
+pub fn sentences(text: &str) -> Vec<String> {
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        let newline = c == '\n' || c == '\r';
+        if !newline {
+            current.push(c);
+        }
+        let end = match c {
+            _ if newline => true,
+            '.' => chars.get(i).filter(|next| !next.is_whitespace()).is_none(),
+            c => is_terminator(c),
+        };
+        if !end {
+            continue;
+        }
+        while let Some(&next) = chars.get(i).filter(|_| !newline) {
+            if !(is_terminator(next) || is_closing(next) || next == '.') {
+                break;
+            }
+            current.push(next);
+            i += 1;
+        }
+        let sentence = current.trim();
+        if !sentence.is_empty() {
+            sentences.push(sentence.to_string());
+        }
+        current.clear();
+    }
+    let sentence = current.trim();
+    if !sentence.is_empty() {
+        sentences.push(sentence.to_string());
+    }
+    sentences
+}
+
+/// 用 TextRank 计算每个句子的分数
+/// - 两个句子的相似度为共同特征数除以两句特征数的对数之和，不计英文常用词
+pub fn rank(sentences: &[String]) -> Vec<f64> {
+    let features = sentences
+        .iter()
+        .map(|sentence| {
+            fingerprint::features(sentence)
+                .into_iter()
+                .filter(|feature| !STOP_WORDS.contains(&feature.as_str()))
+                .collect::<HashSet<String>>()
+        })
+        .collect::<Vec<HashSet<String>>>();
+    let n = sentences.len();
+    let mut weights = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let common = features[i].intersection(&features[j]).count();
+            if common == 0 {
+                continue;
+            }
+            let norm =
+                (features[i].len() as f64 + 1.0).ln() + (features[j].len() as f64 + 1.0).ln();
+            weights[i][j] = common as f64 / norm;
+            weights[j][i] = weights[i][j];
+        }
+    }
+    let totals = weights
+        .iter()
+        .map(|row| row.iter().sum::<f64>())
+        .collect::<Vec<f64>>();
+
+    let mut scores = vec![1.0; n];
+    for _ in 0..MAX_ITERATIONS {
+        let next = (0..n)
+            .map(|i| {
+                let rank = (0..n)
+                    .filter(|&j| totals[j] > 0.0)
+                    .map(|j| weights[j][i] / totals[j] * scores[j])
+                    .sum::<f64>();
+                1.0 - DAMPING + DAMPING * rank
+            })
+            .collect::<Vec<f64>>();
+        let delta = next
+            .iter()
+            .zip(&scores)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        scores = next;
+        if delta < TOLERANCE {
+            break;
+        }
+    }
+    scores
+}
+
+/// 拼接句子，英文句子之间加空格
+fn join(sentences: &[&str]) -> String {
+    let mut text = String::new();
+    for sentence in sentences {
+        if text.ends_with(|c: char| c.is_ascii_punctuation() || c.is_ascii_alphanumeric()) {
+            text.push(' ');
+        }
+        text.push_str(sentence);
+    }
+    text
+}
+
+/// 从正文中抽取不超过 max_length 个字符的摘要
+/// - 正文中的 HTML 标签会被去除
+/// - 按分数从高到低选取放得下的句子，再按原文顺序拼接
+/// - 与其他句子都不相似的句子不会用来凑长度
+/// - 分数最高的句子也放不下时截断该句并以省略号结尾
+pub fn summarize(content: &str, max_length: usize) -> String {
+    let mut sentences = sentences(&html_to_text(content));
+    sentences.truncate(MAX_SENTENCES);
+    if sentences.is_empty() || max_length == 0 {
+        return String::new();
+    }
+    let scores = rank(&sentences);
+    let mut order = (0..sentences.len()).collect::<Vec<usize>>();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then(a.cmp(&b)));
+
+    let mut chosen = Vec::new();
+    let mut length = 0;
+    for &i in &order {
+        // 与其他句子没有共同特征的句子只在没有其他句子可选时使用
+        if !chosen.is_empty() && scores[i] <= 1.0 - DAMPING + TOLERANCE {
+            break;
+        }
+        // 预留句子之间可能加入的空格
+        let len = sentences[i].chars().count() + usize::from(!chosen.is_empty());
+        if length + len <= max_length {
+            chosen.push(i);
+            length += len;
+        }
+    }
+    if chosen.is_empty() {
+        let mut text = sentences[order[0]]
+            .chars()
+            .take(max_length - 1)
+            .collect::<String>();
+        text.push('…');
+        return text;
+    }
+    chosen.sort_unstable();
+    join(
+        &chosen
+            .iter()
+            .map(|&i| sentences[i].as_str())
+            .collect::<Vec<&str>>(),
+    )
+}
+
+#[test]
+fn summarize_picks_central_sentences() {
+    assert_eq!(
+        sentences("Rust 1.70 发布了！稀疏索引默认开启。“真快”他说。\nIt is 2.5x faster. Really?!"),
+        vec![
+            "Rust 1.70 发布了！",
+            "稀疏索引默认开启。",
+            "“真快”他说。",
+            "It is 2.5x faster.",
+            "Really?!",
+        ]
+    );
+
+    let content = "<p>新闻推荐系统使用 Rust 编写后端服务。</p>\
+        <p>今天天气晴朗。</p>\
+        <p>推荐系统根据用户的浏览记录计算兴趣，后端服务调用推荐模型。</p>\
+        <p>推荐模型每天根据浏览记录重新训练。</p>";
+    let summary = summarize(content, 60);
+    assert!(summary.chars().count() <= 60);
+    assert!(!summary.contains('<'));
+    assert!(!summary.contains("天气"));
+    assert!(summary.contains("推荐系统根据用户的浏览记录计算兴趣"));
+    // 按原文顺序拼接，不相关的句子不用来凑长度
+    assert_eq!(
+        summarize(content, 1000),
+        "新闻推荐系统使用 Rust 编写后端服务。\
+        推荐系统根据用户的浏览记录计算兴趣，后端服务调用推荐模型。推荐模型每天根据浏览记录重新训练。"
+    );
+    assert_eq!(
+        summarize("First one. Second one.", 100),
+        "First one. Second one."
+    );
+
+    // 放不下任何一句时截断
+    let summary = summarize("推荐系统根据用户的浏览记录计算兴趣", 8);
+    assert_eq!(summary, "推荐系统根据用…");
+    assert_eq!(summarize("<br/>", 100), "");
+}
@@ -0,0 +1,124 @@
+// 请求参数校验
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use poem_openapi::payload::Json;
+use regex::Regex;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::config::CONFIG;
+
+use super::{ApiError, FieldError};
+
+/// 用户名只允许字母、数字、下划线以及中文
+pub static USERNAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9_\p{Han}]+$").unwrap());
+
+/// 校验请求，失败时按字段返回全部错误
+pub fn validate<T: Validate>(request: &T) -> Result<(), ApiError> {
+    request
+        .validate()
+        .map_err(|errors| ApiError::ValidationError(Json(field_errors(errors))))
+}
+
+/// 单个参数校验失败，用于不经过 Validate 的 query 参数
+pub fn invalid_field(field: &str, code: &str, message: &str) -> ApiError {
+    ApiError::ValidationError(Json(vec![FieldError {
+        field: field.to_string(),
+        code: code.to_string(),
+        message: message.to_string(),
+    }]))
+}
+
+/// 将校验错误按字段整理并排序
+pub fn field_errors(errors: ValidationErrors) -> Vec<FieldError> {
+    let mut result = errors
+        .field_errors()
+        .into_iter()
+        .flat_map(|(field, errors)| {
+            errors.iter().map(move |error| FieldError {
+                field: field.to_string(),
+                code: error.code.to_string(),
+                message: error
+                    .message
+                    .as_ref()
+                    .map(|message| message.to_string())
+                    .unwrap_or_else(|| error.code.to_string()),
+            })
+        })
+        .collect::<Vec<FieldError>>();
+    result.sort_by(|a, b| a.field.cmp(&b.field));
+    result
+}
+
+fn error(code: &'static str, message: String) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(Cow::Owned(message));
+    error
+}
+
+/// 密码策略校验，规则见配置中的 [password]
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+    let policy = &CONFIG.password;
+    let len = password.chars().count();
+    if len < policy.min_length || len > policy.max_length {
+        return Err(error(
+            "length",
+            format!(
+                "密码长度需在 {} 到 {} 之间",
+                policy.min_length, policy.max_length
+            ),
+        ));
+    }
+    if policy.require_letter && !password.chars().any(|c| c.is_alphabetic()) {
+        return Err(error("letter", "密码需要包含字母".into()));
+    }
+    if policy.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+        return Err(error("digit", "密码需要包含数字".into()));
+    }
+    if policy.require_symbol && password.chars().all(|c| c.is_alphanumeric()) {
+        return Err(error("symbol", "密码需要包含特殊字符".into()));
+    }
+    Ok(())
+}
+
+/// 兴趣 tag 校验，tag 不能为空且不超过 255 个字符
+pub fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    match tags
+        .iter()
+        .all(|tag| !tag.trim().is_empty() && tag.chars().count() <= 255)
+    {
+        true => Ok(()),
+        false => Err(error("tag", "tag 不能为空且不超过 255 个字符".into())),
+    }
+}
+
+#[test]
+fn validate_register_request() {
+    use super::object::user::RegisterRequest;
+
+    let request = RegisterRequest {
+        username: "neko_猫".into(),
+        password: "nekopara1".into(),
+        age: 18,
+        sex: None,
+        email: Some("neko@example.com".into()),
+    };
+    assert!(validate(&request).is_ok());
+
+    let request = RegisterRequest {
+        username: "a b".into(),
+        password: "short".into(),
+        age: -1,
+        sex: None,
+        email: Some("neko".into()),
+    };
+    let errors = request
+        .validate()
+        .map_err(field_errors)
+        .unwrap_err()
+        .into_iter()
+        .map(|error| error.field)
+        .collect::<Vec<String>>();
+    assert_eq!(errors, vec!["age", "email", "password", "username"]);
+}
@@ -11,7 +11,17 @@ use std::{
     str::FromStr,
 };
 
-use crate::common::object::user::UserSign;
+use crate::{
+    common::{
+        data::{self, api_key::ApiKeyData, revoke::RevokeStore, DbPool},
+        object::{
+            api_key::Scope,
+            user::{Role, UserClaims},
+        },
+        ApiError,
+    },
+    util::calc_token_hash,
+};
 
 pub type ServerKey = Hmac<Sha256>;
 
@@ -23,20 +33,366 @@ pub type ServerKey = Hmac<Sha256>;
     in = "header",
     checker = "api_checker"
 )]
-pub struct AppAuthorization(pub UserSign);
+pub struct AppAuthorization(pub UserClaims);
 
-async fn api_checker(req: &Request, api_key: ApiKey) -> Option<UserSign> {
+async fn api_checker(req: &Request, api_key: ApiKey) -> Option<UserClaims> {
     let server_key = req.data::<ServerKey>().unwrap();
-    VerifyWithKey::<UserSign>::verify_with_key(api_key.key.as_str(), server_key).ok()
+    let revoke_store = req.data::<RevokeStore>().unwrap();
+    let pool = req.data::<DbPool>().unwrap();
+    let claims =
+        VerifyWithKey::<UserClaims>::verify_with_key(api_key.key.as_str(), server_key).ok()?;
+    // 拒绝已过期或签发时间异常的 token
+    if !claims.is_valid_at(chrono::Utc::now().timestamp()) {
+        return None;
+    }
+    // 拒绝已注销的 token，存储不可用时同样拒绝
+    match revoke_store.is_revoked(&claims).await {
+        Ok(false) => {}
+        Ok(true) => return None,
+        Err(e) => {
+            tracing::error!("revoke store error: {}", e);
+            return None;
+        }
+    }
+    // 拒绝所在会话已被注销的 token，同时更新会话的最近活动时间
+    match data::session::touch(pool, claims.id, claims.sid).await {
+        Ok(true) => Some(claims),
+        Ok(false) => None,
+        Err(e) => {
+            tracing::error!("session touch error: {}", e);
+            None
+        }
+    }
+}
+
+/// Admin authorization
+/// - 使用与用户相同的 token，要求角色至少为 editor
+/// - 具体接口需要的角色由 require 进一步校验
+#[derive(SecurityScheme)]
+#[oai(
+    type = "api_key",
+    key_name = "NRS-TOKEN",
+    in = "header",
+    checker = "admin_checker"
+)]
+pub struct AdminAuthorization(pub UserClaims);
+
+async fn admin_checker(req: &Request, api_key: ApiKey) -> Option<UserClaims> {
+    api_checker(req, api_key)
+        .await
+        .filter(|claims| claims.role >= Role::Editor)
+}
+
+impl AdminAuthorization {
+    /// 校验当前用户角色不低于 role
+    pub fn require(&self, role: Role) -> Result<&UserClaims, ApiError> {
+        match self.0.role >= role {
+            true => Ok(&self.0),
+            false => Err(ApiError::AdminAuthFailed),
+        }
+    }
+}
+
+/// 服务调用方
+pub enum Principal {
+    /// 使用用户 token 访问，权限范围由角色决定
+    User(UserClaims),
+    /// 使用 API key 访问
+    ApiKey(ApiKeyData),
+}
+
+impl Principal {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        match self {
+            Principal::User(claims) => claims.role.scopes().contains(&scope),
+            Principal::ApiKey(key) => key.scopes.contains(&scope),
+        }
+    }
+}
+
+/// Service authorization
+/// - 同时接受 API key 与用户 token，均放在 NRS-TOKEN 请求头中
+/// - 具体接口需要的权限范围由 require 进一步校验
+#[derive(SecurityScheme)]
+#[oai(
+    type = "api_key",
+    key_name = "NRS-TOKEN",
+    in = "header",
+    checker = "service_checker"
+)]
+pub struct ServiceAuthorization(pub Principal);
+
+async fn service_checker(req: &Request, api_key: ApiKey) -> Option<Principal> {
+    if !api_key.key.starts_with(data::api_key::KEY_PREFIX) {
+        return api_checker(req, api_key).await.map(Principal::User);
+    }
+    let pool = req.data::<DbPool>().unwrap();
+    match data::api_key::authenticate(pool, &calc_token_hash(&api_key.key)).await {
+        Ok(key) => key.map(Principal::ApiKey),
+        Err(e) => {
+            tracing::error!("api key authenticate error: {}", e);
+            None
+        }
+    }
+}
+
+impl ServiceAuthorization {
+    /// 校验调用方拥有 scope 权限
+    pub fn require(&self, scope: Scope) -> Result<&Principal, ApiError> {
+        match self.0.has_scope(scope) {
+            true => Ok(&self.0),
+            false => Err(ApiError::AdminAuthFailed),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Server {
     pub api_port: u16,
     pub log_file: PathBuf,
-    pub api_key: String,
+    /// 仅用于校验旧格式的密码 hash
     pub salt: String,
     pub server_key: String,
+    /// 是否信任反向代理传递的 X-Real-IP / X-Forwarded-For
+    #[serde(default)]
+    pub trust_proxy: bool,
+}
+
+/// token 相关配置，单位均为秒
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Token {
+    /// access token 有效期
+    pub access_token_ttl: i64,
+    /// refresh token 有效期
+    pub refresh_token_ttl: i64,
+    /// 邮箱验证链接有效期
+    pub email_verify_ttl: i64,
+    /// 重置密码链接有效期
+    pub password_reset_ttl: i64,
+}
+
+impl Default for Token {
+    fn default() -> Self {
+        Self {
+            access_token_ttl: 15 * 60,
+            refresh_token_ttl: 7 * 24 * 60 * 60,
+            email_verify_ttl: 24 * 60 * 60,
+            password_reset_ttl: 30 * 60,
+        }
+    }
+}
+
+/// 密码相关配置
+/// - argon2 参数修改后，旧密码会在用户下次登录时重新计算
+/// - 密码策略只作用于注册与修改密码
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Password {
+    /// 内存开销（KiB）
+    pub m_cost: u32,
+    /// 迭代次数
+    pub t_cost: u32,
+    /// 并行度
+    pub p_cost: u32,
+    /// 同时进行的 hash 计算上限，超出时请求返回 503
+    pub max_concurrency: usize,
+    /// 密码最短长度
+    pub min_length: usize,
+    /// 密码最长长度
+    pub max_length: usize,
+    /// 是否必须包含字母
+    pub require_letter: bool,
+    /// 是否必须包含数字
+    pub require_digit: bool,
+    /// 是否必须包含特殊字符
+    pub require_symbol: bool,
+}
+
+impl Default for Password {
+    fn default() -> Self {
+        Self {
+            m_cost: argon2::Params::DEFAULT_M_COST,
+            t_cost: argon2::Params::DEFAULT_T_COST,
+            p_cost: argon2::Params::DEFAULT_P_COST,
+            max_concurrency: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4),
+            min_length: 8,
+            max_length: 128,
+            require_letter: true,
+            require_digit: true,
+            require_symbol: false,
+        }
+    }
+}
+
+/// 登录失败限流配置
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Login {
+    /// 同一用户名连续失败多少次后开始锁定
+    pub max_user_failures: i32,
+    /// 同一 IP 连续失败多少次后开始锁定
+    pub max_ip_failures: i32,
+    /// 首次锁定时长（秒），之后每次失败翻倍
+    pub base_lockout: i64,
+    /// 最长锁定时长（秒）
+    pub max_lockout: i64,
+    /// 距上次失败超过该时长（秒）后重新计数
+    pub reset_after: i64,
+}
+
+impl Default for Login {
+    fn default() -> Self {
+        Self {
+            max_user_failures: 5,
+            max_ip_failures: 20,
+            base_lockout: 30,
+            max_lockout: 60 * 60,
+            reset_after: 60 * 60,
+        }
+    }
+}
+
+/// redis 配置，未配置 url 时相关功能回退到 postgres
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Redis {
+    pub url: Option<String>,
+}
+
+/// 邮件发送方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MailSender {
+    /// 只输出到日志
+    Log,
+    /// 追加写入文件
+    File,
+    /// 通过 SMTP 发送
+    Smtp,
+}
+
+/// SMTP 连接方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    Starttls,
+    Tls,
+    None,
+}
+
+/// 邮件配置
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Mail {
+    pub sender: MailSender,
+    /// sender 为 file 时写入的文件
+    pub file: PathBuf,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_security: SmtpSecurity,
+    pub smtp_username: String,
+    pub smtp_password: String,
+    /// 发件人，如 `NRS <noreply@example.com>`
+    pub from: String,
+    /// 邮件中链接的前缀，token 会拼接在其后
+    pub link_base: String,
+}
+
+impl Default for Mail {
+    fn default() -> Self {
+        Self {
+            sender: MailSender::Log,
+            file: PathBuf::from("mail.log"),
+            smtp_host: "localhost".into(),
+            smtp_port: 587,
+            smtp_security: SmtpSecurity::Starttls,
+            smtp_username: String::new(),
+            smtp_password: String::new(),
+            from: "NRS <noreply@localhost>".into(),
+            link_base: "http://localhost:3000".into(),
+        }
+    }
+}
+
+/// RSS/Atom 订阅抓取配置
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Feed {
+    /// 订阅地址，为空时不启动抓取任务
+    pub urls: Vec<String>,
+    /// 两轮抓取之间的间隔（秒）
+    pub interval: u64,
+    /// 单个订阅的请求超时（秒）
+    pub timeout: u64,
+    /// 响应的最大字节数
+    pub max_body_size: usize,
+    /// 是否允许订阅地址（包括重定向后的地址）指向回环、内网等地址
+    pub allow_private_hosts: bool,
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            interval: 10 * 60,
+            timeout: 30,
+            max_body_size: 5 * 1024 * 1024,
+            allow_private_hosts: false,
+        }
+    }
+}
+
+/// 自动打 tag 配置
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AutoTag {
+    /// 新增新闻时是否自动打 tag
+    pub enabled: bool,
+    /// 每条新闻最多自动生成的 tag 数
+    pub max_tags: usize,
+    /// 置信度低于该值的 tag 不会被保存
+    pub min_confidence: f64,
+}
+
+impl Default for AutoTag {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_tags: 5,
+            min_confidence: 0.5,
+        }
+    }
+}
+
+/// 摘要生成配置
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Summary {
+    /// 自动生成的摘要最多包含的字符数
+    pub max_length: usize,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Self { max_length: 100 }
+    }
+}
+
+/// 数据库迁移配置
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Migrate {
+    /// 启动时自动执行未执行的迁移，关闭时存在未执行的迁移会拒绝启动
+    pub run_on_start: bool,
+}
+
+impl Default for Migrate {
+    fn default() -> Self {
+        Self { run_on_start: true }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -58,7 +414,25 @@ pub struct Common {
 pub struct Config {
     pub common: Common,
     pub server: Server,
+    #[serde(default)]
+    pub token: Token,
+    #[serde(default)]
+    pub password: Password,
+    #[serde(default)]
+    pub login: Login,
     pub database: Database,
+    #[serde(default)]
+    pub redis: Redis,
+    #[serde(default)]
+    pub mail: Mail,
+    #[serde(default)]
+    pub feed: Feed,
+    #[serde(default)]
+    pub auto_tag: AutoTag,
+    #[serde(default)]
+    pub summary: Summary,
+    #[serde(default)]
+    pub migrate: Migrate,
 }
 
 impl Default for Config {
@@ -72,10 +446,13 @@ impl Default for Config {
             server: Server {
                 api_port: 3000,
                 log_file: PathBuf::from_str("server.log").unwrap(),
-                api_key: String::from("SNn0TR#*N0f#JDMWsdmiwan3dj2d2k3d"),
                 salt: String::from("Nekopara114514"),
                 server_key: String::from("0237jfH#f3h289f3j0"),
+                trust_proxy: false,
             },
+            token: Token::default(),
+            password: Password::default(),
+            login: Login::default(),
             database: Database {
                 user_name: "news_recommender".into(),
                 password: "nekopara".into(),
@@ -83,6 +460,12 @@ impl Default for Config {
                 port: "5432".into(),
                 db: "news_recommend".into(),
             },
+            redis: Redis::default(),
+            mail: Mail::default(),
+            feed: Feed::default(),
+            auto_tag: AutoTag::default(),
+            summary: Summary::default(),
+            migrate: Migrate::default(),
         }
     }
 }
@@ -0,0 +1,91 @@
+use poem_openapi::payload::Json;
+use serde_json::json;
+
+use crate::{
+    common::{
+        audit::{self, Actor, AuditEntry},
+        cursor::PageQuery,
+        data::{self, api_key::KEY_PREFIX, DbPool},
+        object::{
+            api_key::{
+                ApiKeyListResponse, CreateApiKeyRequest, CreateApiKeyResponse, RevokeApiKeyRequest,
+            },
+            audit::AuditAction,
+        },
+        validate::validate,
+        ApiError, ApiResult, ErrorMessage, NoData,
+    },
+    util::{calc_token_hash, gen_random_token},
+};
+
+/// 列表中展示的 key 前缀长度
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+/// 创建 API key，key 原文只在这里返回一次
+pub async fn create(
+    pool: &DbPool,
+    created_by: i32,
+    request: CreateApiKeyRequest,
+    ip: &str,
+) -> ApiResult<CreateApiKeyResponse> {
+    validate(&request)?;
+
+    let key = format!("{}{}", KEY_PREFIX, gen_random_token(32));
+    let id = data::api_key::insert(
+        pool,
+        &request.name,
+        &key[..DISPLAY_PREFIX_LEN],
+        calc_token_hash(&key),
+        &request.scopes,
+        created_by,
+        request.expires_in,
+    )
+    .await
+    .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::ApiKeyCreate, Actor::User(created_by), ip)
+            .target(format!("api_key:{}", id))
+            .payload(json!({ "name": request.name, "scopes": request.scopes })),
+    )
+    .await;
+    Ok(Json(CreateApiKeyResponse { id, key }))
+}
+
+/// 分页获取 API key，按 id 倒序
+pub async fn list(
+    pool: &DbPool,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> ApiResult<ApiKeyListResponse> {
+    let page = PageQuery::parse(cursor, limit)?;
+    let keys = data::api_key::list(pool, page.after, page.fetch_limit())
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    let (keys, next_cursor) = page.finish(keys, |key| key.id);
+    Ok(Json(ApiKeyListResponse { keys, next_cursor }))
+}
+
+/// 注销 API key，立即生效
+pub async fn revoke(
+    pool: &DbPool,
+    request: RevokeApiKeyRequest,
+    actor: Actor,
+    ip: &str,
+) -> ApiResult<NoData> {
+    match data::api_key::revoke(pool, request.id).await {
+        Ok(true) => {
+            audit::record(
+                pool,
+                AuditEntry::new(AuditAction::ApiKeyRevoke, actor, ip)
+                    .target(format!("api_key:{}", request.id)),
+            )
+            .await;
+            Ok(Json(NoData {}))
+        }
+        Ok(false) => Err(ApiError::ApiKeyNotExists),
+        Err(e) => Err(ApiError::DBError(Json(ErrorMessage::new(e)))),
+    }
+}
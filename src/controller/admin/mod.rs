@@ -1,22 +1,71 @@
 use poem_openapi::payload::Json;
+use serde_json::json;
 
 use crate::common::{
-    data::{self, DbPool},
-    object, ApiError, ApiResult, ErrorMessage, NoData,
+    audit::{self, Actor, AuditEntry},
+    cursor::PageQuery,
+    data::{self, audit::AuditFilter, DbPool},
+    object::{self, audit::AuditAction},
+    validate::validate,
+    ApiError, ApiResult, ErrorMessage, NoData,
 };
 
+pub mod api_key;
+pub mod news;
+pub mod tag;
+
 /// Admin 获取用户信息
-pub async fn get_user_by_id(pool: &DbPool, user_id: i32) -> ApiResult<object::user::InfoResponse> {
+pub async fn get_user_by_id(
+    pool: &DbPool,
+    user_id: i32,
+    actor: Actor,
+    ip: &str,
+) -> ApiResult<object::user::InfoResponse> {
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::UserLookup, actor, ip).target(format!("user:{}", user_id)),
+    )
+    .await;
     // 直接调用 user 模块的相同方法
     super::user::get_info(pool, user_id).await
 }
 
+/// 修改用户角色
+/// - 新角色在用户下次登录或刷新 token 后生效
+pub async fn update_role(
+    pool: &DbPool,
+    request: object::user::UpdateRoleRequest,
+    actor: Actor,
+    ip: &str,
+) -> ApiResult<NoData> {
+    data::user::update_role_by_id(pool, request.user_id, request.role)
+        .await
+        .map_err(|e| ApiError::UserUpdateFailed(Json(ErrorMessage::new(e))))?;
+
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::RoleChange, actor, ip)
+            .target(format!("user:{}", request.user_id))
+            .payload(json!({ "role": request.role })),
+    )
+    .await;
+    Ok(Json(NoData {}))
+}
+
 /// 新建新闻到数据库中
 pub async fn create_news(
     pool: &DbPool,
     news: object::news::CreateNewsRequest,
+    actor: Actor,
+    ip: &str,
 ) -> ApiResult<NoData> {
+    validate(&news)?;
+
     let mut tx = pool.begin().await.unwrap();
+    let title = news.title.clone();
+    let mut tagger = data::auto_tag::Tagger::load(&mut tx)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
 
     match data::news::insert_new_news(
         &mut tx,
@@ -26,13 +75,95 @@ pub async fn create_news(
         news.source,
         news.tags,
         news.link,
+        tagger.as_mut(),
     )
     .await
     {
-        Ok(_) => {
+        Ok(None) => Err(ApiError::NewsAlreadyExists),
+        Ok(Some(news_id)) => {
             tx.commit().await.unwrap();
+            audit::record(
+                pool,
+                AuditEntry::new(AuditAction::NewsCreate, actor, ip)
+                    .target(format!("news:{}", news_id))
+                    .payload(json!({ "title": title })),
+            )
+            .await;
             Ok(Json(NoData {}))
         }
         Err(e) => Err(ApiError::DBError(Json(ErrorMessage::new(e)))),
     }
 }
+
+/// 分页获取用户列表
+pub async fn list_users(
+    pool: &DbPool,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> ApiResult<object::user::UserListResponse> {
+    let page = PageQuery::parse(cursor, limit)?;
+    let users = data::user::list(pool, page.after, page.fetch_limit())
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    let (users, next_cursor) = page.finish(users, |user| user.id);
+    Ok(Json(object::user::UserListResponse { users, next_cursor }))
+}
+
+/// 分页获取新闻列表，status 为空时返回所有状态的新闻
+pub async fn list_news(
+    pool: &DbPool,
+    status: Option<object::news::NewsStatus>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> ApiResult<object::news::NewsListResponse> {
+    let page = PageQuery::parse(cursor, limit)?;
+    let news = data::news::list(pool, status, page.after, page.fetch_limit())
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    let (news, next_cursor) = page.finish(news, |news| (news.create_time, news.news_id));
+    Ok(Json(object::news::NewsListResponse { news, next_cursor }))
+}
+
+/// 分页获取订阅的抓取状态
+pub async fn list_feeds(
+    pool: &DbPool,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> ApiResult<object::feed::FeedListResponse> {
+    let page = PageQuery::parse(cursor, limit)?;
+    let feeds = data::feed::list(pool, page.after, page.fetch_limit())
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    let (feeds, next_cursor) = page.finish(feeds, |feed| feed.id);
+    Ok(Json(object::feed::FeedListResponse { feeds, next_cursor }))
+}
+
+/// 获取系统统计数据
+pub async fn get_stats(pool: &DbPool) -> ApiResult<object::stats::StatsResponse> {
+    let stats = data::stats::summary(pool)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+    Ok(Json(stats))
+}
+
+/// 分页查询审计日志，按 id 倒序
+pub async fn get_audit_log(
+    pool: &DbPool,
+    filter: AuditFilter,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> ApiResult<object::audit::AuditLogResponse> {
+    let page = PageQuery::parse(cursor, limit)?;
+    let entries = data::audit::query(pool, &filter, page.after, page.fetch_limit())
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    let (entries, next_cursor) = page.finish(entries, |entry| entry.id);
+    Ok(Json(object::audit::AuditLogResponse {
+        entries,
+        next_cursor,
+    }))
+}
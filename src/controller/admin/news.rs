@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use poem_openapi::payload::Json;
+use serde_json::json;
+
+use crate::{
+    common::{
+        audit::{self, Actor, AuditEntry},
+        data::{self, DbPool},
+        fingerprint,
+        object::{
+            audit::AuditAction,
+            news::{
+                AbstractResponse, AdminNewsResponse, BulkImportResponse, DuplicateCluster,
+                DuplicateReportResponse, NewsStatus, UpdateNewsRequest, UpdateNewsTagsRequest,
+            },
+        },
+        validate::{invalid_field, validate},
+        ApiError, ApiResult, ErrorMessage, NoData,
+    },
+    importer,
+};
+
+/// 单次批量导入的最大行数，更大的文件请使用 import 子命令
+const MAX_BULK_ROWS: usize = 1000;
+
+/// 重复新闻报告最多返回的组数
+const MAX_DUPLICATE_CLUSTERS: usize = 100;
+
+fn db_error(e: anyhow::Error) -> ApiError {
+    ApiError::DBError(Json(ErrorMessage::new(e)))
+}
+
+/// 获取新闻详情，包含未发布以及已删除的新闻
+pub async fn get(pool: &DbPool, news_id: i32) -> ApiResult<AdminNewsResponse> {
+    let news = data::news::find_by_id_admin(pool, news_id)
+        .await
+        .map_err(db_error)?
+        .ok_or(ApiError::NewsNotExists)?;
+    let auto_tags = data::auto_tag::list_by_news_id(pool, news_id)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(AdminNewsResponse { news, auto_tags }))
+}
+
+/// 修改新闻内容或状态，返回修改后的新闻
+/// - 状态改为 unpublished 即下架，改回 published 即重新发布
+pub async fn update(
+    pool: &DbPool,
+    news_id: i32,
+    request: UpdateNewsRequest,
+    actor: Actor,
+    ip: &str,
+) -> ApiResult<AdminNewsResponse> {
+    validate(&request)?;
+
+    if let Some(content) = &request.content {
+        let duplicate = data::news::find_id_by_content(pool, content)
+            .await
+            .map_err(db_error)?;
+        if duplicate.filter(|id| *id != news_id).is_some() {
+            return Err(ApiError::NewsAlreadyExists);
+        }
+    }
+
+    match data::news::update(pool, news_id, &request).await {
+        Ok(true) => {}
+        Ok(false) => return Err(ApiError::NewsNotExists),
+        // 并发修改为相同正文时，上面的检查可能都通过，由唯一索引拒绝
+        Err(e) if data::is_unique_violation(&e, "idx_news_content_hash") => {
+            return Err(ApiError::NewsAlreadyExists)
+        }
+        Err(e) => return Err(db_error(e)),
+    }
+
+    // 只记录修改了哪些字段，正文可能很长
+    let fields = [
+        ("title", request.title.is_some()),
+        ("content", request.content.is_some()),
+        ("abstracts", request.abstracts.is_some()),
+        ("source", request.source.is_some()),
+        ("link", request.link.is_some()),
+        ("status", request.status.is_some()),
+    ]
+    .into_iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(field, _)| field)
+    .collect::<Vec<&str>>();
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::NewsUpdate, actor, ip)
+            .target(format!("news:{}", news_id))
+            .payload(json!({ "fields": fields, "status": request.status })),
+    )
+    .await;
+
+    get(pool, news_id).await
+}
+
+/// 删除新闻
+/// - 只将状态标记为 deleted，保留历史记录与点赞数据，可以通过修改状态恢复
+pub async fn delete(pool: &DbPool, news_id: i32, actor: Actor, ip: &str) -> ApiResult<NoData> {
+    if !data::news::set_status(pool, news_id, NewsStatus::Deleted)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(ApiError::NewsNotExists);
+    }
+
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::NewsDelete, actor, ip).target(format!("news:{}", news_id)),
+    )
+    .await;
+    Ok(Json(NoData {}))
+}
+
+/// 在同一个事务中替换新闻的 tag 集合，返回修改后的新闻
+pub async fn update_tags(
+    pool: &DbPool,
+    news_id: i32,
+    request: UpdateNewsTagsRequest,
+    actor: Actor,
+    ip: &str,
+) -> ApiResult<AdminNewsResponse> {
+    validate(&request)?;
+
+    let mut tags = request
+        .tags
+        .iter()
+        .map(|tag| tag.trim().to_string())
+        .collect::<Vec<String>>();
+    tags.sort();
+    tags.dedup();
+
+    let mut tx = pool.begin().await.map_err(|e| db_error(e.into()))?;
+    if !data::news::replace_tags(&mut tx, news_id, tags.clone())
+        .await
+        .map_err(db_error)?
+    {
+        return Err(ApiError::NewsNotExists);
+    }
+    tx.commit().await.map_err(|e| db_error(e.into()))?;
+
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::NewsTagUpdate, actor, ip)
+            .target(format!("news:{}", news_id))
+            .payload(json!({ "tags": tags })),
+    )
+    .await;
+
+    get(pool, news_id).await
+}
+
+/// 批量导入新闻，请求体每行一条 JSON 格式的新闻
+/// - 逐行返回导入结果，与已有新闻重复或校验失败的行不影响其他行
+pub async fn bulk(
+    pool: &DbPool,
+    body: String,
+    actor: Actor,
+    ip: &str,
+) -> ApiResult<BulkImportResponse> {
+    let rows = body
+        .lines()
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(i, text)| importer::parse_json(i as i32 + 1, text))
+        .collect::<Vec<importer::Row>>();
+    if rows.is_empty() || rows.len() > MAX_BULK_ROWS {
+        return Err(invalid_field(
+            "body",
+            "length",
+            "每次需要导入 1 到 1000 条新闻",
+        ));
+    }
+
+    let rows = importer::ingest(pool, rows).await.map_err(db_error)?;
+    let response = importer::summarize(rows);
+
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::NewsBulkImport, actor, ip).payload(json!({
+            "inserted": response.inserted,
+            "duplicate": response.duplicate,
+            "invalid": response.invalid,
+        })),
+    )
+    .await;
+    Ok(Json(response))
+}
+
+/// 重复新闻报告，按正文 simhash 把相似的新闻分组，不包含已删除的新闻
+/// - max_distance 为组内相邻新闻的最大汉明距离，默认为 3
+pub async fn duplicates(
+    pool: &DbPool,
+    max_distance: Option<i32>,
+) -> ApiResult<DuplicateReportResponse> {
+    let max_distance = max_distance.unwrap_or(fingerprint::MAX_DISTANCE as i32);
+    if !(0..=fingerprint::MAX_DISTANCE as i32).contains(&max_distance) {
+        return Err(invalid_field(
+            "max_distance",
+            "range",
+            &format!("max_distance 需在 0 到 {} 之间", fingerprint::MAX_DISTANCE),
+        ));
+    }
+
+    let items = data::news::list_simhash(pool).await.map_err(db_error)?;
+    let mut clusters = fingerprint::clusters(&items, max_distance as u32);
+    let total = clusters.len() as i32;
+    clusters.truncate(MAX_DUPLICATE_CLUSTERS);
+
+    let simhashes = items.into_iter().collect::<HashMap<i32, u64>>();
+    let news_ids = clusters.iter().flatten().copied().collect::<Vec<i32>>();
+    let mut news = data::news::find_by_ids_abstract(pool, &news_ids)
+        .await
+        .map_err(db_error)?
+        .into_iter()
+        .map(|news| (news.news_id, news))
+        .collect::<HashMap<i32, AbstractResponse>>();
+
+    let clusters = clusters
+        .into_iter()
+        .map(|news_ids| {
+            let max_distance = news_ids
+                .iter()
+                .enumerate()
+                .flat_map(|(n, a)| news_ids[n + 1..].iter().map(move |b| (a, b)))
+                .map(|(a, b)| fingerprint::distance(simhashes[a], simhashes[b]))
+                .max()
+                .unwrap_or_default();
+            DuplicateCluster {
+                max_distance: max_distance as i32,
+                news: news_ids.iter().filter_map(|id| news.remove(id)).collect(),
+            }
+        })
+        .collect();
+    Ok(Json(DuplicateReportResponse { total, clusters }))
+}
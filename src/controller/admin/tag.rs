@@ -0,0 +1,184 @@
+use poem_openapi::payload::Json;
+use serde_json::json;
+
+use crate::common::{
+    audit::{self, Actor, AuditEntry},
+    data::{self, DbPool},
+    object::{
+        audit::AuditAction,
+        tag::{
+            MergeTagRequest, MergeTagResponse, TagDetail, UpdateTagAliasesRequest,
+            UpdateTagParentRequest,
+        },
+    },
+    validate::{invalid_field, validate},
+    ApiError, ApiResult, ErrorMessage,
+};
+
+fn db_error(e: anyhow::Error) -> ApiError {
+    ApiError::DBError(Json(ErrorMessage::new(e)))
+}
+
+async fn find_detail(pool: &DbPool, tag_id: i32) -> Result<TagDetail, ApiError> {
+    data::tag::find_detail(pool, tag_id)
+        .await
+        .map_err(db_error)?
+        .ok_or(ApiError::TagNotExists)
+}
+
+/// 获取 tag 详情
+pub async fn get(pool: &DbPool, tag_id: i32) -> ApiResult<TagDetail> {
+    find_detail(pool, tag_id).await.map(Json)
+}
+
+/// 设置父 tag，返回修改后的 tag
+/// - 父 tag 不能是自己或自己的子孙
+pub async fn update_parent(
+    pool: &DbPool,
+    tag_id: i32,
+    request: UpdateTagParentRequest,
+    actor: Actor,
+    ip: &str,
+) -> ApiResult<TagDetail> {
+    let mut tx = pool.begin().await.map_err(|e| db_error(e.into()))?;
+    let ids = [Some(tag_id), request.parent_id]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<i32>>();
+    let tags = data::tag::lock(&mut tx, &ids).await.map_err(db_error)?;
+    if !tags.iter().any(|tag| tag.id == tag_id) {
+        return Err(ApiError::TagNotExists);
+    }
+    if let Some(parent_id) = request.parent_id {
+        if !tags.iter().any(|tag| tag.id == parent_id) {
+            return Err(invalid_field("parent_id", "not_found", "父 tag 不存在"));
+        }
+        if data::tag::is_ancestor(&mut tx, tag_id, parent_id)
+            .await
+            .map_err(db_error)?
+        {
+            return Err(invalid_field(
+                "parent_id",
+                "cycle",
+                "父 tag 不能是自己或自己的子 tag",
+            ));
+        }
+    }
+    data::tag::set_parent(&mut tx, tag_id, request.parent_id)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(|e| db_error(e.into()))?;
+
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::TagUpdate, actor, ip)
+            .target(format!("tag:{}", tag_id))
+            .payload(json!({ "parent_id": request.parent_id })),
+    )
+    .await;
+
+    get(pool, tag_id).await
+}
+
+/// 替换 tag 的别名集合，返回修改后的 tag
+/// - 别名不能是其他 tag 的名称或别名，需要合并时使用 merge
+pub async fn update_aliases(
+    pool: &DbPool,
+    tag_id: i32,
+    request: UpdateTagAliasesRequest,
+    actor: Actor,
+    ip: &str,
+) -> ApiResult<TagDetail> {
+    validate(&request)?;
+
+    let mut aliases = request
+        .aliases
+        .iter()
+        .map(|alias| alias.trim().to_string())
+        .collect::<Vec<String>>();
+    aliases.sort();
+    aliases.dedup();
+
+    let mut tx = pool.begin().await.map_err(|e| db_error(e.into()))?;
+    if data::tag::lock(&mut tx, &[tag_id])
+        .await
+        .map_err(db_error)?
+        .is_empty()
+    {
+        return Err(ApiError::TagNotExists);
+    }
+    let conflicts = data::tag::find_alias_conflicts(&mut tx, tag_id, &aliases)
+        .await
+        .map_err(db_error)?;
+    if !conflicts.is_empty() {
+        return Err(invalid_field(
+            "aliases",
+            "conflict",
+            &format!("别名已被其他 tag 使用：{}", conflicts.join("、")),
+        ));
+    }
+    data::tag::replace_aliases(&mut tx, tag_id, &aliases)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(|e| db_error(e.into()))?;
+
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::TagUpdate, actor, ip)
+            .target(format!("tag:{}", tag_id))
+            .payload(json!({ "aliases": aliases })),
+    )
+    .await;
+
+    get(pool, tag_id).await
+}
+
+/// 把 source 合并到 target，新闻 tag 与用户兴趣在一个事务中改写
+pub async fn merge(
+    pool: &DbPool,
+    request: MergeTagRequest,
+    actor: Actor,
+    ip: &str,
+) -> ApiResult<MergeTagResponse> {
+    if request.source_id == request.target_id {
+        return Err(invalid_field(
+            "target_id",
+            "same_tag",
+            "不能把 tag 合并到自己",
+        ));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| db_error(e.into()))?;
+    let tags = data::tag::lock(&mut tx, &[request.source_id, request.target_id])
+        .await
+        .map_err(db_error)?;
+    let find = |id: i32| {
+        tags.iter()
+            .find(|tag| tag.id == id)
+            .ok_or(ApiError::TagNotExists)
+    };
+    let (source, target) = (find(request.source_id)?, find(request.target_id)?);
+    let (news, interests) = data::tag::merge(&mut tx, source, target)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(|e| db_error(e.into()))?;
+
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::TagMerge, actor, ip)
+            .target(format!("tag:{}", target.id))
+            .payload(json!({
+                "source_id": source.id,
+                "source": source.name,
+                "news": news,
+                "interests": interests,
+            })),
+    )
+    .await;
+
+    Ok(Json(MergeTagResponse {
+        tag: find_detail(pool, target.id).await?,
+        news: news as i64,
+        interests: interests as i64,
+    }))
+}
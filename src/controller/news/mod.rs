@@ -6,45 +6,95 @@ use rand::seq::IteratorRandom;
 use crate::{
     common::{
         data::{self, DbPool},
-        object::news::{AbstractResponse, DetailResponse, RandomTagResponse},
-        ApiResult, ErrorMessage, NoData,
+        object::news::{AbstractResponse, DetailResponse, LikeResponse, RandomTagResponse},
+        ApiError, ApiResult, ErrorMessage,
     },
     config::CONFIG,
     rpc::{self, recommend::UserCfRequest},
 };
 
+pub mod search;
+
+/// 点赞后新闻 tag 的兴趣权重，高于浏览新闻时的权重
+const LIKE_WEIGHT: f64 = 5.5;
+
+/// 浏览新闻后新闻 tag 的兴趣权重
+const VIEW_WEIGHT: f64 = 4.8;
+
+fn db_error(e: anyhow::Error) -> ApiError {
+    ApiError::DBError(Json(ErrorMessage::new(e)))
+}
+
 /// 用户点赞新闻
-pub async fn like(pool: &DbPool, _user_id: i32, news_id: i32) -> ApiResult<NoData> {
-    match data::news::increase_like(pool, news_id).await {
-        Ok(_) => Ok(Json(NoData {})),
-        Err(e) => Err(crate::common::ApiError::Error(Json(ErrorMessage::new(e)))),
+/// - 重复点赞不会增加点赞数
+/// - 首次点赞时提高用户对新闻 tag 的兴趣权重
+pub async fn like(pool: &DbPool, user_id: i32, news_id: i32) -> ApiResult<LikeResponse> {
+    set_like(pool, user_id, news_id, true).await
+}
+
+/// 用户取消点赞，没有点赞过时不做任何修改
+pub async fn unlike(pool: &DbPool, user_id: i32, news_id: i32) -> ApiResult<LikeResponse> {
+    set_like(pool, user_id, news_id, false).await
+}
+
+async fn set_like(
+    pool: &DbPool,
+    user_id: i32,
+    news_id: i32,
+    liked: bool,
+) -> ApiResult<LikeResponse> {
+    data::news::find_likes_by_id(pool, news_id)
+        .await
+        .map_err(db_error)?
+        .ok_or(ApiError::NewsNotExists)?;
+
+    let mut tx = pool.begin().await.map_err(|e| db_error(e.into()))?;
+    if liked {
+        let inserted = data::like::insert(&mut tx, user_id, news_id)
+            .await
+            .map_err(db_error)?;
+        if inserted {
+            data::user::raise_interests_by_news_id(&mut tx, user_id, news_id, LIKE_WEIGHT)
+                .await
+                .map_err(db_error)?;
+        }
+    } else {
+        data::like::delete(&mut tx, user_id, news_id)
+            .await
+            .map_err(db_error)?;
     }
+    tx.commit().await.map_err(|e| db_error(e.into()))?;
+
+    let likes = data::news::find_likes_by_id(pool, news_id)
+        .await
+        .map_err(db_error)?
+        .unwrap_or_default();
+    Ok(Json(LikeResponse { liked, likes }))
 }
 
-/// 用户获取新闻详情
+/// 用户获取新闻详情，未发布的新闻返回 404
 pub async fn get(pool: &DbPool, user_id: i32, news_id: i32) -> ApiResult<DetailResponse> {
+    // 首先获取新闻详情
+    let news = data::news::find_by_id(pool, news_id)
+        .await
+        .map_err(|e| crate::common::ApiError::Error(Json(ErrorMessage::new(e))))?
+        .ok_or(ApiError::NewsNotExists)?;
+
     // 事务处理
     let mut tx = pool.begin().await.unwrap();
 
-    // 首先会历史记录中添加一条记录
+    // 然后在历史记录中添加一条记录
     if let Err(e) = data::user::update_history(&mut tx, user_id, news_id).await {
         tracing::error!("{}", e);
     }
 
-    // 动态更改 tag 权重
-    let interests = data::tag::find_all_tag_name_by_news_id(pool, news_id)
+    // 动态更改 tag 权重，不会降低点赞时设置的更高权重
+    data::user::raise_interests_by_news_id(&mut tx, user_id, news_id, VIEW_WEIGHT)
         .await
-        .map_err(|e| crate::common::ApiError::Error(Json(ErrorMessage::new(e))))?;
-    data::user::update_interests_by_id(&mut tx, user_id, interests, 4.8, true).await?;
+        .map_err(db_error)?;
 
-    // 然后去获取新闻详情
-    match data::news::find_by_id(pool, news_id).await {
-        Ok(news) => {
-            tx.commit().await.unwrap();
-            Ok(Json(news))
-        }
-        Err(e) => Err(crate::common::ApiError::Error(Json(ErrorMessage::new(e)))),
-    }
+    tx.commit().await.unwrap();
+    Ok(Json(news))
 }
 
 pub async fn get_random_tags(pool: &DbPool, limit: i32) -> ApiResult<RandomTagResponse> {
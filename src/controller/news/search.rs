@@ -0,0 +1,180 @@
+use poem_openapi::payload::Json;
+
+use crate::common::{
+    cursor::PageQuery,
+    data::{self, news::SearchFilter, DbPool},
+    object::news::{SearchResponse, SearchResult},
+    validate::invalid_field,
+    ApiError, ApiResult, ErrorMessage,
+};
+
+/// 搜索关键词的最大字符数
+const MAX_QUERY_LENGTH: usize = 100;
+
+/// 片段的最大字符数
+const SNIPPET_LENGTH: usize = 120;
+
+/// 片段中第一个关键词之前保留的字符数
+const SNIPPET_CONTEXT: usize = 20;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharKind {
+    Separator,
+    Word,
+    Cjk,
+}
+
+/// 字符分类，与迁移 0018_full_text_search 中 nrs_search_terms 的规则保持一致
+fn char_kind(c: char) -> CharKind {
+    match c as u32 {
+        _ if c.is_ascii_alphanumeric() => CharKind::Word,
+        0x3040..=0x30FF | 0x3400..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF => CharKind::Cjk,
+        _ => CharKind::Separator,
+    }
+}
+
+/// 把搜索关键词切分为英文单词以及连续的中日韩文字，较长的排在前面
+fn terms(query: &str) -> Vec<Vec<char>> {
+    let mut terms = Vec::new();
+    let mut term = Vec::new();
+    let mut last = CharKind::Separator;
+    for c in query.chars().map(|c| c.to_ascii_lowercase()) {
+        let kind = char_kind(c);
+        if kind != last && !term.is_empty() {
+            terms.push(std::mem::take(&mut term));
+        }
+        if kind != CharKind::Separator {
+            term.push(c);
+        }
+        last = kind;
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+    terms.sort_by_key(|term| std::cmp::Reverse(term.len()));
+    terms
+}
+
+fn push_escaped(result: &mut String, c: char) {
+    match c {
+        '&' => result.push_str("&amp;"),
+        '<' => result.push_str("&lt;"),
+        '>' => result.push_str("&gt;"),
+        '"' => result.push_str("&quot;"),
+        _ => result.push(c),
+    }
+}
+
+/// 截取正文中第一个关键词附近的片段，并用 <mark></mark> 标记其中所有关键词
+/// - 英文单词只匹配完整的单词
+fn snippet(content: &str, terms: &[Vec<char>]) -> String {
+    let chars = content.chars().collect::<Vec<char>>();
+    let lower = chars
+        .iter()
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<Vec<char>>();
+    let is_word = |i: usize| matches!(lower.get(i), Some(c) if c.is_ascii_alphanumeric());
+    let match_at = |i: usize| {
+        terms
+            .iter()
+            .find(|term| {
+                lower[i..].starts_with(term)
+                    && (char_kind(term[0]) == CharKind::Cjk
+                        || !((i > 0 && is_word(i - 1)) || is_word(i + term.len())))
+            })
+            .map(|term| term.len())
+    };
+
+    let first = (0..chars.len())
+        .find(|&i| match_at(i).is_some())
+        .unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_CONTEXT);
+    let end = chars.len().min(start + SNIPPET_LENGTH);
+
+    let mut result = String::new();
+    if start > 0 {
+        result.push('…');
+    }
+    let mut i = start;
+    while i < end {
+        match match_at(i) {
+            Some(len) => {
+                let stop = end.min(i + len);
+                result.push_str("<mark>");
+                chars[i..stop]
+                    .iter()
+                    .for_each(|&c| push_escaped(&mut result, c));
+                result.push_str("</mark>");
+                i = stop;
+            }
+            None => {
+                push_escaped(&mut result, chars[i]);
+                i += 1;
+            }
+        }
+    }
+    if end < chars.len() {
+        result.push('…');
+    }
+    result
+}
+
+/// 全文搜索新闻，按相关度倒序分页
+pub async fn search(
+    pool: &DbPool,
+    filter: SearchFilter,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> ApiResult<SearchResponse> {
+    let terms = terms(&filter.query);
+    if terms.is_empty() || filter.query.chars().count() > MAX_QUERY_LENGTH {
+        return Err(invalid_field(
+            "q",
+            "invalid",
+            "搜索关键词需要包含文字且不超过 100 个字符",
+        ));
+    }
+    let page = PageQuery::parse(cursor, limit)?;
+
+    let rows = data::news::search(pool, &filter, page.after, page.fetch_limit())
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    let (rows, next_cursor) = page.finish(rows, |row| (row.rank, row.news.news_id));
+    let results = rows
+        .into_iter()
+        .map(|row| SearchResult {
+            snippet: snippet(&row.content, &terms),
+            news: row.news,
+        })
+        .collect();
+    Ok(Json(SearchResponse {
+        results,
+        next_cursor,
+    }))
+}
+
+#[test]
+fn snippet_marks_terms() {
+    let query_terms = terms("推荐系统 Rust");
+    assert_eq!(
+        query_terms,
+        vec![
+            "推荐系统".chars().collect::<Vec<char>>(),
+            "rust".chars().collect()
+        ]
+    );
+
+    let content = "基于 RUST 的新闻推荐系统，trusty <b> 不会被标记";
+    assert_eq!(
+        snippet(content, &query_terms),
+        "基于 <mark>RUST</mark> 的新闻<mark>推荐系统</mark>，trusty &lt;b&gt; 不会被标记"
+    );
+
+    let content = format!("{}推荐系统{}", "前".repeat(50), "后".repeat(200));
+    let result = snippet(&content, &query_terms);
+    assert!(result.starts_with(&format!("…{}<mark>推荐系统</mark>", "前".repeat(20))));
+    assert!(result.ends_with('…'));
+
+    assert!(terms("。。 ,").is_empty());
+}
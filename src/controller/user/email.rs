@@ -0,0 +1,174 @@
+use poem_openapi::payload::Json;
+
+use crate::{
+    common::{
+        audit::{self, Actor, AuditEntry},
+        data::{self, email_token::Purpose, revoke::RevokeStore, DbPool},
+        object::{
+            audit::AuditAction,
+            user::{PasswordResetConfirm, PasswordResetRequest, VerifyEmailRequest},
+        },
+        validate::validate,
+        ApiError, ApiResult, ErrorMessage, NoData,
+    },
+    config::CONFIG,
+    mailer::SharedMailer,
+    util::{calc_token_hash, gen_random_token, hash_password_async},
+};
+
+fn db_error(e: anyhow::Error) -> ApiError {
+    ApiError::DBError(Json(ErrorMessage::new(e)))
+}
+
+/// 邮箱统一转为小写保存
+pub fn normalize(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// 在后台发送邮件，避免 SMTP 的耗时影响接口响应
+fn deliver(mailer: &SharedMailer, to: String, subject: &'static str, body: String) {
+    let mailer = mailer.clone();
+    tokio::spawn(async move {
+        if let Err(e) = mailer.send(&to, subject, &body).await {
+            tracing::error!("send mail to {} error: {}", to, e);
+        }
+    });
+}
+
+/// 生成一次性 token 保存摘要，返回 token 原文
+async fn new_token(
+    pool: &DbPool,
+    user_id: i32,
+    purpose: Purpose,
+    ttl: i64,
+) -> Result<String, ApiError> {
+    let token = gen_random_token(32);
+    data::email_token::insert(pool, user_id, purpose, calc_token_hash(&token), ttl)
+        .await
+        .map_err(db_error)?;
+    Ok(token)
+}
+
+/// 向用户邮箱发送验证邮件
+pub async fn send_verification(
+    pool: &DbPool,
+    mailer: &SharedMailer,
+    user_id: i32,
+    email: String,
+) -> ApiResult<NoData> {
+    let ttl = CONFIG.token.email_verify_ttl;
+    let token = new_token(pool, user_id, Purpose::VerifyEmail, ttl).await?;
+    let body = format!(
+        "请打开以下链接验证邮箱，链接 {} 小时内有效：\n{}/verify_email?token={}",
+        ttl / 3600,
+        CONFIG.mail.link_base,
+        token
+    );
+    deliver(mailer, email, "NRS 邮箱验证", body);
+    Ok(Json(NoData {}))
+}
+
+/// 重新发送验证邮件，此前发送的链接全部失效
+pub async fn resend_verification(
+    pool: &DbPool,
+    mailer: &SharedMailer,
+    user_id: i32,
+) -> ApiResult<NoData> {
+    let user = data::user::find_by_id(pool, user_id)
+        .await
+        .map_err(|_| ApiError::UserNotExists)?;
+    match (user.email, user.email_verified) {
+        (Some(email), false) => send_verification(pool, mailer, user_id, email).await,
+        _ => Err(ApiError::Error(Json(ErrorMessage::new(
+            "未设置邮箱或邮箱已验证",
+        )))),
+    }
+}
+
+/// 使用验证邮件中的 token 验证邮箱
+pub async fn verify_email(pool: &DbPool, request: VerifyEmailRequest) -> ApiResult<NoData> {
+    let token_hash = calc_token_hash(&request.token);
+    let user_id = data::email_token::consume(pool, Purpose::VerifyEmail, &token_hash)
+        .await
+        .map_err(db_error)?
+        .ok_or(ApiError::EmailTokenInvalid)?;
+    data::user::set_email_verified(pool, user_id)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(NoData {}))
+}
+
+/// 申请重置密码
+/// - 只向已验证的邮箱发送邮件
+/// - 无论邮箱是否存在都返回成功，避免枚举邮箱
+pub async fn request_password_reset(
+    pool: &DbPool,
+    mailer: &SharedMailer,
+    request: PasswordResetRequest,
+) -> ApiResult<NoData> {
+    let email = normalize(&request.email);
+    let user = match data::user::find_by_verified_email(pool, &email).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Ok(Json(NoData {})),
+        Err(e) => return Err(db_error(e)),
+    };
+
+    let ttl = CONFIG.token.password_reset_ttl;
+    let token = new_token(pool, user.id, Purpose::PasswordReset, ttl).await?;
+    let body = format!(
+        "{}，你正在重置密码，请在 {} 分钟内打开以下链接：\n{}/password_reset?token={}\n如果不是你本人的操作，请忽略这封邮件。",
+        user.username,
+        ttl / 60,
+        CONFIG.mail.link_base,
+        token
+    );
+    deliver(mailer, email, "NRS 重置密码", body);
+    Ok(Json(NoData {}))
+}
+
+/// 使用重置密码邮件中的 token 设置新密码，并注销该用户所有已签发的 token
+pub async fn confirm_password_reset(
+    pool: &DbPool,
+    revoke_store: &RevokeStore,
+    request: PasswordResetConfirm,
+    ip: &str,
+) -> ApiResult<NoData> {
+    validate(&request)?;
+
+    // 先计算 hash，hash 线程池繁忙时不会浪费 token
+    let password_hash = hash_password_async(request.password).await?;
+
+    let token_hash = calc_token_hash(&request.token);
+    let user_id = data::email_token::consume(pool, Purpose::PasswordReset, &token_hash)
+        .await
+        .map_err(db_error)?
+        .ok_or(ApiError::EmailTokenInvalid)?;
+
+    let mut tx = pool.begin().await.map_err(|e| db_error(e.into()))?;
+    data::user::update_password_by_id(&mut tx, user_id, password_hash)
+        .await
+        .map_err(|e| ApiError::UserUpdateFailed(Json(ErrorMessage::new(e))))?;
+    tx.commit().await.map_err(|e| db_error(e.into()))?;
+
+    data::email_token::invalidate_all(pool, user_id, Purpose::PasswordReset)
+        .await
+        .map_err(db_error)?;
+    data::token::revoke_all_by_user_id(pool, user_id)
+        .await
+        .map_err(db_error)?;
+    data::session::revoke_all_by_user_id(pool, user_id)
+        .await
+        .map_err(db_error)?;
+    revoke_store
+        .revoke_all_before(user_id, chrono::Utc::now().timestamp())
+        .await
+        .map_err(|e| ApiError::Error(Json(ErrorMessage::new(e))))?;
+
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::PasswordReset, Actor::Anonymous, ip)
+            .target(format!("user:{}", user_id)),
+    )
+    .await;
+    Ok(Json(NoData {}))
+}
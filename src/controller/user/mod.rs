@@ -3,27 +3,50 @@ use std::collections::HashSet;
 use jwt::SignWithKey;
 use poem_openapi::payload::Json;
 use rand::seq::IteratorRandom;
+use serde_json::json;
 use tracing::debug;
 
 use crate::{
     common::{
-        data::{self, DbPool},
+        audit::{self, Actor, AuditEntry},
+        cursor::PageQuery,
+        data::{self, revoke::RevokeStore, DbPool, TransPool},
         object::{
             self,
-            user::{LoginRequest, LoginSuccess, RegisterRequest, UserSign},
+            audit::AuditAction,
+            user::{
+                LoginRequest, LoginSuccess, LogoutRequest, RefreshRequest, RegisterRequest, Role,
+                Sex, UserClaims, UserSign,
+            },
         },
+        validate::validate,
         ApiError, ApiResult, ErrorMessage, NoData,
     },
     config::{ServerKey, CONFIG},
+    mailer::SharedMailer,
     rpc::{
         self,
         recommend::{news_recommend_client::NewsRecommendClient, ItemCfRequest},
     },
-    util::calc_password_hash,
+    util::{
+        calc_token_hash, gen_random_token, hash_password_async, verify_dummy_password,
+        verify_password_async, ClientInfo, PasswordCheck,
+    },
 };
 
+pub mod email;
+pub mod session;
+mod throttle;
+pub mod two_factor;
+
 /// 用户注册操作
-pub async fn register(pool: &DbPool, user: RegisterRequest) -> ApiResult<NoData> {
+/// - 填写邮箱时发送验证邮件
+pub async fn register(
+    pool: &DbPool,
+    mailer: &SharedMailer,
+    user: RegisterRequest,
+) -> ApiResult<NoData> {
+    validate(&user)?;
     let username = user.username;
     debug!("{} start to register", username);
 
@@ -38,56 +61,319 @@ pub async fn register(pool: &DbPool, user: RegisterRequest) -> ApiResult<NoData>
         _ => {}
     }
 
+    // 邮箱不能与其他用户重复
+    let email = user.email.as_deref().map(email::normalize);
+    if let Some(email) = &email {
+        match data::user::is_exist_by_email(pool, email).await {
+            Ok(true) => return Err(ApiError::EmailAlreadyExists),
+            Err(e) => return Err(ApiError::DBError(Json(ErrorMessage::new(e)))),
+            _ => {}
+        }
+    }
+
     // 然后再插入新用户
-    // 使用 salt 计算 hash 后的 password
-    let password_hash = calc_password_hash(&user.password, &username);
-    // debug!("password hash: {}", password);
+    // 使用随机 salt 计算 hash 后的 password
+    let password_hash = hash_password_async(user.password).await?;
 
-    // 判断用户性别，man、woman、unknown
-    let sex = match user.sex {
-        Some(s) if s == "man" => "man",
-        Some(s) if s == "woman" => "woman",
-        _ => "unknown",
-    }
-    .to_string();
+    // 未填写性别时为 unknown
+    let sex = user.sex.unwrap_or(Sex::Unknown);
 
     // 判断用户年龄
     let age = user.age;
 
     // 执行插入操作
     debug!("{} is finishing register", username);
-    match data::user::insert_new_user(pool, username, password_hash, sex, age).await {
-        Ok(_) => Ok(Json(NoData {})),
-        Err(e) => Err(ApiError::DBError(Json(ErrorMessage::new(e)))),
+    let user_id =
+        match data::user::insert_new_user(pool, username, password_hash, sex, age, email.clone())
+            .await
+        {
+            Ok(user_id) => user_id,
+            Err(e) => return Err(ApiError::DBError(Json(ErrorMessage::new(e)))),
+        };
+
+    // 验证邮件发送失败时用户可以稍后重新发送
+    if let Some(email) = email {
+        if email::send_verification(pool, mailer, user_id, email)
+            .await
+            .is_err()
+        {
+            tracing::error!("create verification token of user {} failed", user_id);
+        }
     }
+    Ok(Json(NoData {}))
 }
 
 /// 用户登录操作
+/// - 用户名不存在与密码错误返回相同的错误，避免枚举用户名
+/// - 按用户名与 IP 统计连续失败次数，超过阈值后指数退避锁定
+/// - 开启两步验证时返回 challenge，由 two_factor::login 完成登录
+/// - 登录成功后新建会话
 pub async fn login(
     pool: &DbPool,
     server_key: &ServerKey,
     user: LoginRequest,
+    client: &ClientInfo,
 ) -> ApiResult<LoginSuccess> {
-    // 使用 salt 计算 hash 后的 password
-    let password_hash = calc_password_hash(&user.password, &user.username);
+    let LoginRequest { username, password } = user;
+    let ip = client.ip.as_str();
+    if let Err(e) = throttle::check(pool, &username, ip).await {
+        audit_login_failure(pool, None, &username, ip, "locked").await;
+        return Err(e);
+    }
 
-    let user = match data::user::find_by_name(pool, user.username).await {
+    let user = match data::user::find_by_name(pool, username.clone()).await {
         Ok(user) => user,
         Err(_) => {
-            return Err(ApiError::UserNotExists);
+            if let Err(e) = verify_dummy_password(password).await {
+                throttle::release(pool, &username, ip).await;
+                return Err(e);
+            }
+            audit_login_failure(pool, None, &username, ip, "unknown_user").await;
+            return Err(ApiError::UserPasswordError);
         }
     };
 
-    // 密码错误
-    if user.password != password_hash {
-        return Err(ApiError::UserPasswordError);
+    let checked =
+        match verify_password_async(password.clone(), user.password, user.username.clone()).await {
+            Ok(checked) => checked,
+            Err(e) => {
+                throttle::release(pool, &username, ip).await;
+                return Err(e);
+            }
+        };
+    match checked {
+        // 密码错误
+        PasswordCheck::Invalid => {
+            audit_login_failure(pool, Some(user.id), &username, ip, "wrong_password").await;
+            return Err(ApiError::UserPasswordError);
+        }
+        // 旧格式的 hash 在登录成功后重新计算
+        PasswordCheck::NeedsRehash => {
+            if let Err(e) = rehash_password(pool, user.id, password).await {
+                tracing::error!("rehash password error: {}", e);
+            }
+        }
+        PasswordCheck::Valid => {}
+    }
+    throttle::on_success(pool, &username, ip).await;
+
+    // 开启两步验证的用户需要再提交验证码
+    if user.totp_enabled {
+        return Err(two_factor::challenge(server_key, user.id));
     }
 
     // 密码正确返回 token
-    match UserSign::from(user).sign_with_key(server_key) {
-        Ok(token) => Ok(Json(LoginSuccess { token })),
-        Err(e) => Err(ApiError::SignError(Json(ErrorMessage::new(e.to_string())))),
+    issue_tokens(pool, server_key, user.id, user.username, user.role, client).await
+}
+
+/// 记录一次登录失败，user_id 为 None 表示用户不存在或尚未查询用户
+async fn audit_login_failure(
+    pool: &DbPool,
+    user_id: Option<i32>,
+    username: &str,
+    ip: &str,
+    reason: &str,
+) {
+    let entry = AuditEntry::new(AuditAction::LoginFailure, Actor::Anonymous, ip)
+        .payload(json!({ "username": username, "reason": reason }));
+    let entry = match user_id {
+        Some(user_id) => entry.target(format!("user:{}", user_id)),
+        None => entry,
+    };
+    audit::record(pool, entry).await;
+}
+
+/// 使用新的格式与参数重新计算用户密码 hash
+/// - hash 线程池繁忙时跳过，等待下次登录
+async fn rehash_password(pool: &DbPool, user_id: i32, password: String) -> anyhow::Result<()> {
+    let password_hash = match hash_password_async(password).await {
+        Ok(password_hash) => password_hash,
+        Err(_) => return Err(anyhow::anyhow!("hash password failed")),
+    };
+    let mut tx = pool.begin().await?;
+    data::user::update_password_by_id(&mut tx, user_id, password_hash).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// 使用 refresh token 换取新的 token
+/// - refresh token 每次使用后都会轮换，旧 token 立即作废
+/// - 若已作废的 refresh token 被再次使用，说明 token 可能已泄露，作废该用户所有 refresh token
+/// - 新的 token 沿用原来的会话
+pub async fn refresh(
+    pool: &DbPool,
+    server_key: &ServerKey,
+    request: RefreshRequest,
+    client: &ClientInfo,
+) -> ApiResult<LoginSuccess> {
+    let token_hash = calc_token_hash(&request.refresh_token);
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    let (user_id, session_id) = match data::token::consume_refresh_token(&mut tx, &token_hash).await
+    {
+        Ok(Some(result)) => result,
+        Ok(None) => {
+            drop(tx);
+            if let Ok(Some(user_id)) = data::token::find_revoked_owner(pool, &token_hash).await {
+                tracing::warn!(
+                    "refresh token reused, revoke all tokens of user {}",
+                    user_id
+                );
+                if let Err(e) = data::token::revoke_all_by_user_id(pool, user_id).await {
+                    tracing::error!("{}", e);
+                }
+            }
+            return Err(ApiError::RefreshTokenInvalid);
+        }
+        Err(e) => return Err(ApiError::DBError(Json(ErrorMessage::new(e)))),
+    };
+
+    let user = data::user::find_by_id(pool, user_id)
+        .await
+        .map_err(|_| ApiError::UserNotExists)?;
+
+    // 会话功能上线前签发的 refresh token 在刷新时补建会话
+    let session_id = match session_id {
+        Some(session_id) => session_id,
+        None => data::session::create(&mut tx, user.id, &client.user_agent, &client.ip)
+            .await
+            .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?,
+    };
+
+    let response = issue_tokens_in(
+        &mut tx,
+        server_key,
+        user.id,
+        user.username,
+        user.role,
+        session_id,
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    if let Err(e) = data::session::touch(pool, user_id, session_id).await {
+        tracing::error!("{}", e);
+    }
+    Ok(response)
+}
+
+/// 用户登出，注销当前 token 以及所在的会话
+pub async fn logout(
+    pool: &DbPool,
+    revoke_store: &RevokeStore,
+    claims: &UserClaims,
+    request: LogoutRequest,
+) -> ApiResult<NoData> {
+    revoke_store
+        .revoke(claims)
+        .await
+        .map_err(|e| ApiError::Error(Json(ErrorMessage::new(e))))?;
+
+    if let Some(refresh_token) = request.refresh_token {
+        data::token::revoke_refresh_token(pool, claims.id, &calc_token_hash(&refresh_token))
+            .await
+            .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
     }
+
+    data::session::revoke(pool, claims.id, claims.sid)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+    Ok(Json(NoData {}))
+}
+
+/// 用户在所有设备上登出，注销此前签发的全部 token
+pub async fn logout_all(
+    pool: &DbPool,
+    revoke_store: &RevokeStore,
+    claims: &UserClaims,
+) -> ApiResult<NoData> {
+    let now = chrono::Utc::now().timestamp();
+    revoke_store
+        .revoke_all_before(claims.id, now)
+        .await
+        .map_err(|e| ApiError::Error(Json(ErrorMessage::new(e))))?;
+    // 与 now 同一秒签发的当前 token 需要单独注销
+    revoke_store
+        .revoke(claims)
+        .await
+        .map_err(|e| ApiError::Error(Json(ErrorMessage::new(e))))?;
+
+    data::token::revoke_all_by_user_id(pool, claims.id)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+    data::session::revoke_all_by_user_id(pool, claims.id)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+    Ok(Json(NoData {}))
+}
+
+/// 为用户新建会话，并签发 access token 以及 refresh token，同时记录登录成功
+async fn issue_tokens(
+    pool: &DbPool,
+    server_key: &ServerKey,
+    user_id: i32,
+    username: String,
+    role: Role,
+    client: &ClientInfo,
+) -> ApiResult<LoginSuccess> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+    let session_id = data::session::create(&mut tx, user_id, &client.user_agent, &client.ip)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+    let response =
+        issue_tokens_in(&mut tx, server_key, user_id, username, role, session_id).await?;
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    audit::record(
+        pool,
+        AuditEntry::new(AuditAction::LoginSuccess, Actor::User(user_id), &client.ip)
+            .target(format!("user:{}", user_id))
+            .payload(json!({ "session_id": session_id, "user_agent": client.user_agent })),
+    )
+    .await;
+    Ok(response)
+}
+
+async fn issue_tokens_in(
+    tx: &mut TransPool<'_>,
+    server_key: &ServerKey,
+    user_id: i32,
+    username: String,
+    role: Role,
+    session_id: i32,
+) -> ApiResult<LoginSuccess> {
+    let expires_in = CONFIG.token.access_token_ttl;
+    let token = UserClaims::new(user_id, username, role, session_id, expires_in)
+        .sign_with_key(server_key)
+        .map_err(|e| ApiError::SignError(Json(ErrorMessage::new(e.to_string()))))?;
+
+    let refresh_token = gen_random_token(32);
+    data::token::insert_refresh_token(
+        tx,
+        user_id,
+        session_id,
+        calc_token_hash(&refresh_token),
+        CONFIG.token.refresh_token_ttl,
+    )
+    .await
+    .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    Ok(Json(LoginSuccess {
+        token,
+        expires_in,
+        refresh_token,
+    }))
 }
 
 /// 获取用户信息
@@ -113,59 +399,197 @@ pub async fn get_info(pool: &DbPool, user_id: i32) -> ApiResult<object::user::In
     // 组合返回
     Ok(Json(object::user::InfoResponse {
         username: user.username,
+        role: user.role,
         interests,
         age: user.age,
         sex: user.sex,
+        email: user.email,
+        email_verified: user.email_verified,
         create_time: user.create_time,
     }))
 }
 
-/// 获取用户历史记录
-pub async fn get_history(pool: &DbPool, user_id: i32) -> ApiResult<object::user::HistoryResponse> {
+/// 分页获取用户历史记录，按浏览时间倒序
+pub async fn get_history(
+    pool: &DbPool,
+    user_id: i32,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> ApiResult<object::user::HistoryResponse> {
+    let page = PageQuery::parse(cursor, limit)?;
+
     // 通过 user_id 获取用户历史记录
-    let history = match data::user::get_history_by_user_id(pool, user_id).await {
-        Ok(history) => history,
-        Err(e) => {
-            return Err(ApiError::DBError(Json(ErrorMessage::new(e))));
-        }
-    };
+    let history =
+        match data::user::get_history_by_user_id(pool, user_id, page.after, page.fetch_limit())
+            .await
+        {
+            Ok(history) => history,
+            Err(e) => {
+                return Err(ApiError::DBError(Json(ErrorMessage::new(e))));
+            }
+        };
 
-    Ok(Json(object::user::HistoryResponse { news: history }))
+    let (history, next_cursor) = page.finish(history, |row| (row.last_view_time, row.history_id));
+    Ok(Json(object::user::HistoryResponse {
+        news: history.into_iter().map(|row| row.news).collect(),
+        next_cursor,
+    }))
 }
 
 /// 更新用户信息
 /// 1. 更新密码
 /// 2. 更新兴趣 tag （注：这里的 tag 更新是表示对这个 tag 感兴趣，将 weight 增加到 5）
+/// 3. 更新邮箱，新邮箱需要重新验证
 pub async fn update(
     pool: &DbPool,
+    mailer: &SharedMailer,
     user_id: i32,
     user_update: object::user::UpdateRequest,
+    ip: &str,
 ) -> ApiResult<NoData> {
-    // 通过 user_id 获取用户信息
+    validate(&user_update)?;
+
+    // 确认用户存在
     let user = match data::user::find_by_id(pool, user_id).await {
         Ok(user) => user,
-        Err(e) => {
-            return Err(ApiError::DBError(Json(ErrorMessage::new(e))));
+        Err(e) => return Err(ApiError::DBError(Json(ErrorMessage::new(e)))),
+    };
+
+    // 邮箱未变化时不做处理
+    let email = user_update
+        .email
+        .as_deref()
+        .map(email::normalize)
+        .filter(|email| user.email.as_ref() != Some(email));
+    if let Some(email) = &email {
+        match data::user::is_exist_by_email(pool, email).await {
+            Ok(true) => return Err(ApiError::EmailAlreadyExists),
+            Err(e) => return Err(ApiError::DBError(Json(ErrorMessage::new(e)))),
+            _ => {}
         }
+    }
+
+    // 先计算新密码的 hash，避免在事务中等待
+    let password_hash = match user_update.password {
+        Some(password) => Some(hash_password_async(password).await?),
+        None => None,
     };
 
     // 事务开始
     let mut tx = pool.begin().await.unwrap();
 
     // 更新兴趣 tag（即表示对这个 tag 感兴趣）
+    let mut audit_entries = Vec::new();
     if let Some(interests) = user_update.interests {
+        let payload = json!({ "interests": interests });
         data::user::update_interests_by_id(&mut tx, user_id, interests, 5.0, true).await?;
+        audit_entries.push(
+            AuditEntry::new(AuditAction::InterestUpdate, Actor::User(user_id), ip)
+                .target(format!("user:{}", user_id))
+                .payload(payload),
+        );
     }
 
     // 更新密码
-    if let Some(password) = user_update.password {
-        let password_hash = calc_password_hash(&password, &user.username);
+    if let Some(password_hash) = password_hash {
         data::user::update_password_by_id(&mut tx, user_id, password_hash)
             .await
             .map_err(|e| ApiError::UserUpdateFailed(Json(ErrorMessage::new(e.to_string()))))?;
+        audit_entries.push(
+            AuditEntry::new(AuditAction::PasswordChange, Actor::User(user_id), ip)
+                .target(format!("user:{}", user_id)),
+        );
+    }
+
+    // 更新邮箱
+    if let Some(email) = &email {
+        data::user::update_email_by_id(&mut tx, user_id, email)
+            .await
+            .map_err(|e| ApiError::UserUpdateFailed(Json(ErrorMessage::new(e.to_string()))))?;
     }
 
     tx.commit().await.unwrap();
+
+    for entry in audit_entries {
+        audit::record(pool, entry).await;
+    }
+
+    if let Some(email) = email {
+        email::send_verification(pool, mailer, user_id, email).await?;
+    }
+    Ok(Json(NoData {}))
+}
+
+/// 导出用户的个人数据
+pub async fn export(pool: &DbPool, user_id: i32) -> ApiResult<object::user::ExportResponse> {
+    let user = data::user::find_by_id(pool, user_id)
+        .await
+        .map_err(|_| ApiError::UserNotExists)?;
+    let interests = data::user::get_interest_weights_by_user_id(pool, user_id)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+    let history = data::user::get_all_history_by_user_id(pool, user_id)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+    let likes = data::like::list_by_user_id(pool, user_id)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    Ok(Json(object::user::ExportResponse {
+        export_time: chrono::Utc::now().naive_utc(),
+        profile: object::user::ProfileExport {
+            id: user.id,
+            username: user.username,
+            role: user.role,
+            age: user.age,
+            sex: user.sex,
+            email: user.email,
+            email_verified: user.email_verified,
+            totp_enabled: user.totp_enabled,
+            create_time: user.create_time,
+            update_time: user.update_time,
+        },
+        interests,
+        history,
+        likes,
+    }))
+}
+
+/// 注销账号
+/// - 需要再次确认密码
+/// - 在同一个事务中清除用户的全部个人数据以及审计日志中的 IP 与用户名，会话删除后已签发的 token 随即失效
+pub async fn delete(
+    pool: &DbPool,
+    user_id: i32,
+    request: object::user::DeleteAccountRequest,
+) -> ApiResult<NoData> {
+    let user = data::user::find_by_id(pool, user_id)
+        .await
+        .map_err(|_| ApiError::UserNotExists)?;
+    if let PasswordCheck::Invalid =
+        verify_password_async(request.password, user.password, user.username).await?
+    {
+        return Err(ApiError::UserPasswordError);
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+    data::user::anonymize_by_id(&mut tx, user_id)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    audit::record(
+        pool,
+        // 注销记录同样不保存 IP
+        AuditEntry::new(AuditAction::AccountDelete, Actor::User(user_id), "")
+            .target(format!("user:{}", user_id)),
+    )
+    .await;
     Ok(Json(NoData {}))
 }
 
@@ -185,7 +609,6 @@ pub async fn connect(pool: &DbPool, user_id: i32, limit: i32) -> ApiResult<Vec<U
         .await
         .map_err(|e| crate::common::ApiError::Error(Json(ErrorMessage::new(e))))?;
 
-
     let response = rpc::get_recommend_users(
         &mut rpc_client,
         ItemCfRequest {
@@ -196,20 +619,19 @@ pub async fn connect(pool: &DbPool, user_id: i32, limit: i32) -> ApiResult<Vec<U
     .await
     .map_err(|e| crate::common::ApiError::RPCError(Json(ErrorMessage::new(e))))?;
 
-    let mut users = Vec::new();
-
-    for item in response.response {
-        for user_id in item.user_id {
-            let user = data::user::find_by_id(pool, user_id)
-                .await
-                .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
-            users.push(UserSign::from(user));
-        }
-    }
+    let user_ids = response
+        .response
+        .into_iter()
+        .flat_map(|item| item.user_id)
+        .collect::<Vec<i32>>();
+    let users = data::user::find_by_ids(pool, &user_ids)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
 
     Ok(Json(
         users
-            .drain(..)
+            .into_iter()
+            .map(UserSign::from)
             .collect::<HashSet<UserSign>>()
             .into_iter()
             .choose_multiple(&mut rand::thread_rng(), limit as usize),
@@ -0,0 +1,45 @@
+use poem_openapi::payload::Json;
+
+use crate::{
+    common::{
+        data::{self, DbPool},
+        object::user::{RevokeSessionRequest, SessionResponse, UserClaims},
+        ApiError, ApiResult, ErrorMessage, NoData,
+    },
+    config::CONFIG,
+};
+
+/// 获取用户当前有效的登录会话
+/// - 超过 refresh token 有效期未活动的会话已无法继续使用，不再返回
+pub async fn list(pool: &DbPool, claims: &UserClaims) -> ApiResult<Vec<SessionResponse>> {
+    let sessions = data::session::list_by_user_id(pool, claims.id, CONFIG.token.refresh_token_ttl)
+        .await
+        .map_err(|e| ApiError::DBError(Json(ErrorMessage::new(e))))?;
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionResponse {
+                current: session.id == claims.sid,
+                id: session.id,
+                user_agent: session.user_agent,
+                ip: session.ip,
+                create_time: session.create_time,
+                last_seen: session.last_seen,
+            })
+            .collect(),
+    ))
+}
+
+/// 注销某个会话，该会话的 token 立即失效
+pub async fn revoke(
+    pool: &DbPool,
+    user_id: i32,
+    request: RevokeSessionRequest,
+) -> ApiResult<NoData> {
+    match data::session::revoke(pool, user_id, request.session_id).await {
+        Ok(true) => Ok(Json(NoData {})),
+        Ok(false) => Err(ApiError::SessionNotExists),
+        Err(e) => Err(ApiError::DBError(Json(ErrorMessage::new(e)))),
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{
+    common::{
+        data::{self, login_attempt::Lockout, DbPool},
+        ApiError,
+    },
+    config::CONFIG,
+};
+
+fn user_key(username: &str) -> String {
+    format!("user:{}", username)
+}
+
+fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+/// IP 与用户名各自的锁定规则，先检查 IP，避免被锁定的 IP 继续为用户名计数
+fn lockouts(username: &str, ip: &str) -> [(String, Lockout); 2] {
+    let login = &CONFIG.login;
+    let lockout = |threshold| Lockout {
+        threshold,
+        base: login.base_lockout,
+        max: login.max_lockout,
+        reset_after: login.reset_after,
+    };
+    [
+        (ip_key(ip), lockout(login.max_ip_failures)),
+        (user_key(username), lockout(login.max_user_failures)),
+    ]
+}
+
+async fn release_all(pool: &DbPool, lockouts: &[(String, Lockout)]) {
+    for (key, lockout) in lockouts {
+        if let Err(e) = data::login_attempt::release(pool, key, lockout.threshold).await {
+            tracing::error!("{}", e);
+        }
+    }
+}
+
+/// 登录前为 IP 与用户名各记录一次尝试，处于锁定期时拒绝
+/// - 尝试先按失败计数，检查与计数在同一条语句中完成，超过阈值后指数退避锁定
+/// - 登录成功后由 on_success 撤销计数
+pub async fn check(pool: &DbPool, username: &str, ip: &str) -> Result<(), ApiError> {
+    let lockouts = lockouts(username, ip);
+    for (i, (key, lockout)) in lockouts.iter().enumerate() {
+        match data::login_attempt::attempt(pool, key, lockout).await {
+            Ok(Some(secs)) => {
+                // 被拒绝的尝试不计数，撤销前面已经计入的 key
+                release_all(pool, &lockouts[..i]).await;
+                return Err(ApiError::TooManyAttempts(secs.max(1) as u64));
+            }
+            Ok(None) => {}
+            // 限流记录不可用时不影响正常登录
+            Err(e) => tracing::error!("{}", e),
+        }
+    }
+    Ok(())
+}
+
+/// 登录成功后清除该用户名的失败记录，并撤销 IP 的本次计数
+pub async fn on_success(pool: &DbPool, username: &str, ip: &str) {
+    let [(ip_key, ip_lockout), (user_key, _)] = lockouts(username, ip);
+    if let Err(e) = data::login_attempt::clear(pool, &user_key).await {
+        tracing::error!("{}", e);
+    }
+    if let Err(e) = data::login_attempt::release(pool, &ip_key, ip_lockout.threshold).await {
+        tracing::error!("{}", e);
+    }
+}
+
+/// 撤销 IP 与用户名的本次计数
+/// - 用于没有完成密码校验的请求，如 hash 并发已满返回 503 时，不应计为失败
+pub async fn release(pool: &DbPool, username: &str, ip: &str) {
+    release_all(pool, &lockouts(username, ip)).await;
+}
@@ -0,0 +1,256 @@
+use jwt::{SignWithKey, VerifyWithKey};
+use poem_openapi::payload::Json;
+use serde_json::json;
+
+use crate::{
+    common::{
+        audit::{self, Actor, AuditEntry},
+        data::{self, revoke::RevokeStore, user::UserData, DbPool},
+        object::audit::AuditAction,
+        object::user::{
+            ChallengeClaims, LoginSuccess, RecoveryCodesResponse, TwoFactorChallenge,
+            TwoFactorCodeRequest, TwoFactorDisableRequest, TwoFactorLoginRequest,
+            TwoFactorSetupResponse,
+        },
+        ApiError, ApiResult, ErrorMessage, NoData,
+    },
+    config::ServerKey,
+    totp,
+    util::{calc_token_hash, gen_random_token, verify_password_async, ClientInfo, PasswordCheck},
+};
+
+use super::{issue_tokens, throttle};
+
+/// challenge token 有效期（秒）
+const CHALLENGE_TTL: i64 = 5 * 60;
+/// 恢复码数量
+const RECOVERY_CODE_COUNT: usize = 10;
+const CHALLENGE_PURPOSE: &str = "2fa";
+
+fn db_error(e: anyhow::Error) -> ApiError {
+    ApiError::DBError(Json(ErrorMessage::new(e)))
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// 恢复码统一为小写且去掉分隔符后再计算摘要
+fn recovery_code_hash(code: &str) -> String {
+    let code = code
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| *c != '-')
+        .collect::<String>();
+    calc_token_hash(&code)
+}
+
+/// 登录时密码正确后签发 challenge，返回 863 要求提交验证码
+pub fn challenge(server_key: &ServerKey, user_id: i32) -> ApiError {
+    let claims = ChallengeClaims {
+        id: user_id,
+        purpose: CHALLENGE_PURPOSE.into(),
+        exp: now() + CHALLENGE_TTL,
+        jti: gen_random_token(16),
+    };
+    match claims.sign_with_key(server_key) {
+        Ok(challenge_token) => ApiError::TwoFactorRequired(Json(TwoFactorChallenge {
+            challenge_token,
+            expires_in: CHALLENGE_TTL,
+        })),
+        Err(e) => ApiError::SignError(Json(ErrorMessage::new(e))),
+    }
+}
+
+/// 校验验证码，支持 TOTP 验证码以及一次性恢复码
+async fn check_code(pool: &DbPool, user: &UserData, code: &str) -> anyhow::Result<bool> {
+    let secret = match &user.totp_secret {
+        Some(secret) => secret,
+        None => return Ok(false),
+    };
+    match totp::verify(secret, code, now() as u64) {
+        Some(step) => data::two_factor::consume_step(pool, user.id, step as i64).await,
+        None => {
+            data::two_factor::consume_recovery_code(pool, user.id, &recovery_code_hash(code)).await
+        }
+    }
+}
+
+/// 生成新的 TOTP 密钥，需要调用 verify 确认后才会启用
+pub async fn setup(pool: &DbPool, user_id: i32) -> ApiResult<TwoFactorSetupResponse> {
+    let user = data::user::find_by_id(pool, user_id)
+        .await
+        .map_err(|_| ApiError::UserNotExists)?;
+    if user.totp_enabled {
+        return Err(ApiError::TwoFactorStateError(Json(ErrorMessage::new(
+            "两步验证已启用",
+        ))));
+    }
+
+    let secret = totp::generate_secret();
+    data::two_factor::set_pending_secret(pool, user_id, &secret)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(TwoFactorSetupResponse {
+        otpauth_uri: totp::otpauth_uri("NRS", &user.username, &secret),
+        secret,
+    }))
+}
+
+/// 使用认证器 App 中的验证码确认并启用两步验证，返回恢复码
+pub async fn verify(
+    pool: &DbPool,
+    user_id: i32,
+    request: TwoFactorCodeRequest,
+) -> ApiResult<RecoveryCodesResponse> {
+    let user = data::user::find_by_id(pool, user_id)
+        .await
+        .map_err(|_| ApiError::UserNotExists)?;
+    let secret = match (&user.totp_secret, user.totp_enabled) {
+        (Some(secret), false) => secret,
+        _ => {
+            return Err(ApiError::TwoFactorStateError(Json(ErrorMessage::new(
+                "请先调用 setup 生成密钥",
+            ))))
+        }
+    };
+
+    let step =
+        totp::verify(secret, &request.code, now() as u64).ok_or(ApiError::TwoFactorCodeError)?;
+    if !data::two_factor::consume_step(pool, user_id, step as i64)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(ApiError::TwoFactorCodeError);
+    }
+
+    let recovery_codes = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code = gen_random_token(5);
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect::<Vec<String>>();
+
+    let mut tx = pool.begin().await.map_err(|e| db_error(e.into()))?;
+    data::two_factor::enable(&mut tx, user_id)
+        .await
+        .map_err(db_error)?;
+    data::two_factor::replace_recovery_codes(
+        &mut tx,
+        user_id,
+        recovery_codes
+            .iter()
+            .map(|code| recovery_code_hash(code))
+            .collect(),
+    )
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(|e| db_error(e.into()))?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// 关闭两步验证，需要提供当前密码以及验证码或恢复码
+/// - 避免只凭被盗用的会话与泄露的恢复码关闭两步验证
+/// - 与登录共用失败次数限制，密码与验证码错误返回相同的错误，避免被用来分别猜测
+pub async fn disable(
+    pool: &DbPool,
+    user_id: i32,
+    request: TwoFactorDisableRequest,
+    ip: &str,
+) -> ApiResult<NoData> {
+    let user = data::user::find_by_id(pool, user_id)
+        .await
+        .map_err(|_| ApiError::UserNotExists)?;
+    if !user.totp_enabled {
+        return Err(ApiError::TwoFactorStateError(Json(ErrorMessage::new(
+            "两步验证未启用",
+        ))));
+    }
+    throttle::check(pool, &user.username, ip).await?;
+
+    let checked = match verify_password_async(
+        request.password,
+        user.password.clone(),
+        user.username.clone(),
+    )
+    .await
+    {
+        Ok(checked) => checked,
+        Err(e) => {
+            throttle::release(pool, &user.username, ip).await;
+            return Err(e);
+        }
+    };
+    // 密码错误时不再校验验证码，避免消耗恢复码
+    if let PasswordCheck::Invalid = checked {
+        return Err(ApiError::UserPasswordError);
+    }
+    if !check_code(pool, &user, &request.code)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(ApiError::UserPasswordError);
+    }
+    throttle::on_success(pool, &user.username, ip).await;
+
+    let mut tx = pool.begin().await.map_err(|e| db_error(e.into()))?;
+    data::two_factor::disable(&mut tx, user_id)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(|e| db_error(e.into()))?;
+    Ok(Json(NoData {}))
+}
+
+/// 两步验证登录的第二步，提交 challenge token 与验证码换取 token
+pub async fn login(
+    pool: &DbPool,
+    server_key: &ServerKey,
+    revoke_store: &RevokeStore,
+    request: TwoFactorLoginRequest,
+    client: &ClientInfo,
+) -> ApiResult<LoginSuccess> {
+    let ip = client.ip.as_str();
+    let claims = VerifyWithKey::<ChallengeClaims>::verify_with_key(
+        request.challenge_token.as_str(),
+        server_key,
+    )
+    .ok()
+    .filter(|claims| claims.purpose == CHALLENGE_PURPOSE && claims.exp > now())
+    .ok_or(ApiError::UserAuthFailed)?;
+
+    // challenge token 只能成功使用一次
+    match revoke_store.is_jti_revoked(&claims.jti).await {
+        Ok(false) => {}
+        Ok(true) => return Err(ApiError::UserAuthFailed),
+        Err(e) => return Err(ApiError::Error(Json(ErrorMessage::new(e)))),
+    }
+
+    let user = data::user::find_by_id(pool, claims.id)
+        .await
+        .map_err(|_| ApiError::UserAuthFailed)?;
+    throttle::check(pool, &user.username, ip).await?;
+
+    if !check_code(pool, &user, &request.code)
+        .await
+        .map_err(db_error)?
+    {
+        audit::record(
+            pool,
+            AuditEntry::new(AuditAction::LoginFailure, Actor::Anonymous, ip)
+                .target(format!("user:{}", user.id))
+                .payload(json!({ "username": user.username, "reason": "wrong_code" })),
+        )
+        .await;
+        return Err(ApiError::TwoFactorCodeError);
+    }
+    throttle::on_success(pool, &user.username, ip).await;
+
+    revoke_store
+        .revoke_jti(&claims.jti, claims.exp)
+        .await
+        .map_err(|e| ApiError::Error(Json(ErrorMessage::new(e))))?;
+    issue_tokens(pool, server_key, user.id, user.username, user.role, client).await
+}
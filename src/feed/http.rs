@@ -0,0 +1,367 @@
+// 抓取订阅使用的 HTTP 客户端
+// 每次请求单独建立连接，支持 http 与 https，跟随有限次数的重定向
+// 默认拒绝访问回环、内网等地址，重定向后的地址同样检查
+
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::Arc,
+    time::Duration,
+};
+
+use hyper::{
+    body::HttpBody,
+    client::conn,
+    header::{ACCEPT, HOST, LOCATION, USER_AGENT},
+    Body, Request, Response, Uri,
+};
+use once_cell::sync::Lazy;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName},
+    TlsConnector,
+};
+
+/// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 5;
+
+static TLS_CONNECTOR: Lazy<TlsConnector> = Lazy::new(|| {
+    let mut roots = RootCertStore::empty();
+    roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|anchor| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            anchor.subject,
+            anchor.spki,
+            anchor.name_constraints,
+        )
+    }));
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+});
+
+async fn send_over<T>(io: T, request: Request<Body>) -> anyhow::Result<Response<Body>>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, connection) = conn::handshake(io).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::debug!("feed connection error: {}", e);
+        }
+    });
+    Ok(sender.send_request(request).await?)
+}
+
+/// 是否为回环、内网、链路本地等不应由订阅访问的地址
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_multicast()
+                // 0.0.0.0/8 本网络
+                || a == 0
+                // 100.64.0.0/10 运营商级 NAT
+                || (a == 100 && b & 0xc0 == 64)
+                // 192.0.0.0/24 IETF 协议分配
+                || (a == 192 && b == 0 && c == 0)
+                // 198.18.0.0/15 网络基准测试
+                || (a == 198 && b & 0xfe == 18)
+                // 240.0.0.0/4 保留地址，包括广播地址
+                || a >= 240
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            let v4 = |high: u16, low: u16| Ipv4Addr::from(u32::from(high) << 16 | u32::from(low));
+            // 内嵌 IPv4 的地址按其中的 IPv4 地址判断：64:ff9b::/96 NAT64、2002::/16 6to4、
+            // ::ffff:a.b.c.d 映射地址与 ::a.b.c.d 兼容地址（包括 :: 与 ::1）
+            let embedded = match segments {
+                [0x64, 0xff9b, 0, 0, 0, 0, high, low] => Some(v4(high, low)),
+                [0x2002, high, low, ..] => Some(v4(high, low)),
+                _ => ip.to_ipv4(),
+            };
+            match embedded {
+                Some(ip) => is_internal(IpAddr::V4(ip)),
+                None => {
+                    ip.is_multicast()
+                        // fc00::/7 唯一本地地址与 fe80::/10 链路本地地址
+                        || segments[0] & 0xfe00 == 0xfc00
+                        || segments[0] & 0xffc0 == 0xfe80
+                }
+            }
+        }
+    }
+}
+
+async fn send(uri: &Uri, allow_private_hosts: bool) -> anyhow::Result<Response<Body>> {
+    let https = match uri.scheme_str() {
+        Some("https") => true,
+        Some("http") => false,
+        _ => anyhow::bail!("不支持的地址: {}", uri),
+    };
+    let host = uri
+        .host()
+        .ok_or_else(|| anyhow::anyhow!("地址缺少主机名: {}", uri))?;
+    let authority = uri.authority().map(|a| a.as_str()).unwrap_or(host);
+    let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+
+    let request = Request::get(uri.path_and_query().map(|p| p.as_str()).unwrap_or("/"))
+        .header(HOST, authority)
+        .header(
+            USER_AGENT,
+            concat!("nrs-server/", env!("CARGO_PKG_VERSION")),
+        )
+        .header(
+            ACCEPT,
+            "application/rss+xml, application/atom+xml, application/xml;q=0.9, */*;q=0.8",
+        )
+        .body(Body::empty())?;
+
+    // 先解析域名再检查地址，连接时只使用检查过的地址
+    let host = host.trim_matches(|c| c == '[' || c == ']');
+    let addrs = tokio::net::lookup_host((host, port))
+        .await?
+        .filter(|addr| allow_private_hosts || !is_internal(addr.ip()))
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        anyhow::bail!("不允许访问内网地址: {}", uri);
+    }
+
+    let stream = TcpStream::connect(&addrs[..]).await?;
+    match https {
+        true => {
+            let server_name = ServerName::try_from(host)?;
+            let stream = TLS_CONNECTOR.connect(server_name, stream).await?;
+            send_over(stream, request).await
+        }
+        false => send_over(stream, request).await,
+    }
+}
+
+/// 去掉路径中的 `.` 与 `..`，path 以 `/` 开头
+fn remove_dot_segments(path: &str) -> String {
+    let segments = path.split('/').skip(1).collect::<Vec<_>>();
+    let mut output = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        match *segment {
+            "." => {}
+            ".." => {
+                output.pop();
+            }
+            segment => output.push(segment),
+        }
+        // 以 `.` 或 `..` 结尾时保留末尾的 `/`
+        if i == segments.len() - 1 && (*segment == "." || *segment == "..") {
+            output.push("");
+        }
+    }
+    format!("/{}", output.join("/"))
+}
+
+/// 重定向的目标地址，相对地址按当前地址补全
+fn redirect_target(uri: &Uri, location: &str) -> anyhow::Result<Uri> {
+    let location = location.split('#').next().unwrap_or_default();
+    let scheme = uri.scheme_str().unwrap_or("http");
+    // 带协议的绝对地址
+    if location
+        .split(['/', '?'])
+        .next()
+        .filter(|s| s.contains(':'))
+        .is_some()
+    {
+        return Ok(location.parse()?);
+    }
+    // 省略协议的地址沿用当前协议
+    if let Some(rest) = location.strip_prefix("//") {
+        return Ok(format!("{}://{}", scheme, rest).parse()?);
+    }
+
+    let (path, query) = match location.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (location, None),
+    };
+    let (path, query) = match path {
+        "" => (uri.path().to_string(), query.or_else(|| uri.query())),
+        path if path.starts_with('/') => (remove_dot_segments(path), query),
+        path => {
+            // 相对路径以当前地址所在的目录为基准
+            let base = uri.path();
+            let dir = &base[..base.rfind('/').map_or(0, |i| i + 1)];
+            (remove_dot_segments(&format!("{}{}", dir, path)), query)
+        }
+    };
+    let path_and_query = match query {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    };
+    Ok(Uri::builder()
+        .scheme(scheme)
+        .authority(uri.authority().map(|a| a.as_str()).unwrap_or_default())
+        .path_and_query(path_and_query)
+        .build()?)
+}
+
+/// GET 请求 url，返回响应体文本
+/// - 非 2xx 响应、超时或响应超过 max_body_size 字节时返回错误
+/// - allow_private_hosts 为 false 时，地址或重定向地址解析到内网时返回错误
+pub async fn get(
+    url: &str,
+    timeout: Duration,
+    max_body_size: usize,
+    allow_private_hosts: bool,
+) -> anyhow::Result<String> {
+    tokio::time::timeout(timeout, async {
+        let mut uri: Uri = url.parse()?;
+        for _ in 0..=MAX_REDIRECTS {
+            let mut response = send(&uri, allow_private_hosts).await?;
+            let status = response.status();
+            if status.is_redirection() {
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .ok_or_else(|| anyhow::anyhow!("HTTP {} 缺少 Location", status))?;
+                uri = redirect_target(&uri, location)?;
+                continue;
+            }
+            if !status.is_success() {
+                anyhow::bail!("HTTP {}", status);
+            }
+
+            let mut body = Vec::new();
+            while let Some(chunk) = response.body_mut().data().await {
+                body.extend_from_slice(&chunk?);
+                if body.len() > max_body_size {
+                    anyhow::bail!("响应超过 {} 字节", max_body_size);
+                }
+            }
+            return Ok(String::from_utf8_lossy(&body).into_owned());
+        }
+        anyhow::bail!("重定向次数过多")
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("请求超时"))?
+}
+
+#[tokio::test]
+async fn get_follows_redirect() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = stream.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+            let response = match request.split_whitespace().nth(1) {
+                Some("/old") => {
+                    "HTTP/1.1 301 Moved\r\nLocation: /feed\r\nContent-Length: 0\r\n\r\n".to_string()
+                }
+                Some("/a/old") => {
+                    "HTTP/1.1 302 Found\r\nLocation: ../feed\r\nContent-Length: 0\r\n\r\n"
+                        .to_string()
+                }
+                Some("/feed") => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                    "<rss/>".len(),
+                    "<rss/>"
+                ),
+                _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+            };
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    let timeout = Duration::from_secs(5);
+    for path in ["/old", "/a/old"] {
+        let body = get(&format!("http://{}{}", addr, path), timeout, 1024, true)
+            .await
+            .unwrap();
+        assert_eq!(body, "<rss/>");
+    }
+
+    let error = get(&format!("http://{}/missing", addr), timeout, 1024, true)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("404"));
+    assert!(get(&format!("http://{}/feed", addr), timeout, 3, true)
+        .await
+        .is_err());
+
+    // 默认不允许访问回环地址
+    let error = get(&format!("http://{}/feed", addr), timeout, 1024, false)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("内网"));
+}
+
+#[test]
+fn resolve_redirect_target() {
+    let uri: Uri = "https://example.com/a/b/feed.xml?x=1".parse().unwrap();
+    let target = |location| redirect_target(&uri, location).unwrap().to_string();
+    assert_eq!(target("/rss"), "https://example.com/rss");
+    assert_eq!(target("rss.xml"), "https://example.com/a/b/rss.xml");
+    assert_eq!(
+        target("../rss.xml?y=2"),
+        "https://example.com/a/rss.xml?y=2"
+    );
+    assert_eq!(target("./"), "https://example.com/a/b/");
+    assert_eq!(target("?y=2"), "https://example.com/a/b/feed.xml?y=2");
+    assert_eq!(
+        target("//cdn.example.com/rss"),
+        "https://cdn.example.com/rss"
+    );
+    assert_eq!(target("http://other.com/rss#top"), "http://other.com/rss");
+    assert_eq!(target("../../../rss"), "https://example.com/rss");
+}
+
+#[test]
+fn internal_addresses() {
+    for ip in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "0.1.2.3",
+        "192.0.0.8",
+        "198.18.0.1",
+        "198.19.255.1",
+        "224.0.0.1",
+        "240.0.0.1",
+        "255.255.255.255",
+        "::",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "ff02::1",
+        "::ffff:127.0.0.1",
+        "::127.0.0.1",
+        "::10.0.0.1",
+        "64:ff9b::7f00:1",
+        "64:ff9b::a9fe:a9fe",
+        "2002:7f00:1::",
+        "2002:c0a8:101::1",
+    ] {
+        assert!(is_internal(ip.parse().unwrap()), "{}", ip);
+    }
+    for ip in [
+        "93.184.216.34",
+        "198.20.0.1",
+        "2606:2800:220:1::1",
+        "64:ff9b::5db8:d822",
+        "2002:5db8:d822::1",
+    ] {
+        assert!(!is_internal(ip.parse().unwrap()), "{}", ip);
+    }
+}
@@ -0,0 +1,185 @@
+// RSS/Atom 订阅抓取
+// 按配置定时抓取订阅，把新的条目写入 news 表，写入逻辑与批量导入相同
+
+mod http;
+mod parser;
+
+use std::{collections::HashSet, time::Duration};
+
+use tracing::{info, warn};
+
+use crate::{
+    common::{
+        data::{self, DbPool},
+        html::html_to_text,
+        object::news::{BulkRowStatus, CreateNewsRequest},
+    },
+    config::CONFIG,
+    importer::{self, Row},
+};
+
+use self::parser::FeedItem;
+
+/// 一条新闻最多保留的 tag 数，与 CreateNewsRequest 的校验一致
+const MAX_TAGS: usize = 50;
+
+/// tag 的最大字符数，与 CreateNewsRequest 的校验一致
+const MAX_TAG_LENGTH: usize = 255;
+
+/// 把订阅条目转换为新闻，分类转换为 tag
+/// - 正文优先使用 content，没有时使用摘要
+/// - 摘要与正文相同时不单独保存摘要
+fn to_news(item: &FeedItem, source: &str) -> CreateNewsRequest {
+    let summary = html_to_text(&item.summary);
+    let content = match html_to_text(&item.content) {
+        content if content.is_empty() => summary.clone(),
+        content => content,
+    };
+    let abstracts = match summary.is_empty() || summary == content {
+        true => None,
+        false => Some(summary),
+    };
+
+    let mut seen = HashSet::new();
+    let tags = item
+        .categories
+        .iter()
+        .map(|category| category.trim().to_string())
+        .filter(|tag| !tag.is_empty() && tag.chars().count() <= MAX_TAG_LENGTH)
+        .filter(|tag| seen.insert(tag.to_lowercase()))
+        .take(MAX_TAGS)
+        .collect();
+
+    CreateNewsRequest {
+        title: item.title.clone(),
+        content,
+        source: source.to_string(),
+        abstracts,
+        link: item.link.clone(),
+        tags,
+    }
+}
+
+/// 抓取一个订阅，返回订阅标题与新增的新闻数
+async fn poll(pool: &DbPool, feed_id: i32, url: &str) -> anyhow::Result<(String, i32)> {
+    let body = http::get(
+        url,
+        Duration::from_secs(CONFIG.feed.timeout),
+        CONFIG.feed.max_body_size,
+        CONFIG.feed.allow_private_hosts,
+    )
+    .await?;
+    let feed = parser::parse(&body)?;
+    // 没有标题时使用域名作为新闻来源
+    let source = match feed.title.is_empty() {
+        true => url
+            .parse::<hyper::Uri>()
+            .ok()
+            .and_then(|uri| uri.host().map(String::from))
+            .unwrap_or_else(|| url.to_string()),
+        false => feed.title.clone(),
+    };
+
+    // 按 guid 与 link 去重，包括同一次抓取中重复的条目
+    let (mut guids, mut links) = (HashSet::new(), HashSet::new());
+    let mut items = Vec::new();
+    for item in feed.items {
+        if item.link.is_empty()
+            || !guids.insert(item.guid.clone())
+            || !links.insert(item.link.clone())
+            || data::feed::is_known(pool, feed_id, &item.guid, &item.link).await?
+        {
+            continue;
+        }
+        items.push(item);
+    }
+
+    let rows = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| Row {
+            line: i as i32 + 1,
+            news: Ok(to_news(item, &source)),
+        })
+        .collect();
+    let results = importer::ingest(pool, rows).await?;
+
+    let mut inserted = 0;
+    for (item, result) in items.iter().zip(results) {
+        match result.status {
+            BulkRowStatus::Inserted => inserted += 1,
+            BulkRowStatus::Duplicate => (),
+            BulkRowStatus::Invalid => warn!(
+                "feed {} skip {}: {}",
+                url,
+                item.link,
+                result.message.unwrap_or_default()
+            ),
+        }
+        data::feed::insert_item(pool, feed_id, &item.guid, &item.link, result.news_id).await?;
+    }
+    Ok((feed.title, inserted))
+}
+
+/// 依次抓取所有配置的订阅，并记录每个订阅的抓取状态
+pub async fn poll_all(pool: &DbPool) {
+    for url in &CONFIG.feed.urls {
+        let feed_id = match data::feed::upsert(pool, url).await {
+            Ok(feed_id) => feed_id,
+            Err(e) => {
+                tracing::error!("feed {} error: {}", url, e);
+                continue;
+            }
+        };
+        let recorded = match poll(pool, feed_id, url).await {
+            Ok((title, inserted)) => {
+                info!("feed {}: {} news inserted", url, inserted);
+                data::feed::record_success(pool, feed_id, &title, inserted).await
+            }
+            Err(e) => {
+                warn!("feed {} error: {}", url, e);
+                data::feed::record_failure(pool, feed_id, &e.to_string()).await
+            }
+        };
+        if let Err(e) = recorded {
+            tracing::error!("feed {} record status error: {}", url, e);
+        }
+    }
+}
+
+/// 启动订阅抓取任务，未配置订阅时不启动
+pub async fn start(pool: DbPool) -> anyhow::Result<()> {
+    if CONFIG.feed.urls.is_empty() {
+        return Ok(());
+    }
+
+    tokio::spawn(async move {
+        loop {
+            info!("feed task start");
+            poll_all(&pool).await;
+            info!("feed task finish");
+            tokio::time::sleep(Duration::from_secs(CONFIG.feed.interval)).await;
+        }
+    });
+
+    Ok(())
+}
+
+#[test]
+fn feed_item_to_news() {
+    let feed = parser::parse(include_str!("fixtures/rss.xml")).unwrap();
+
+    let news = to_news(&feed.items[0], &feed.title);
+    assert_eq!(news.title, "Rust 1.70 发布");
+    assert_eq!(news.content, "Rust 1.70 正式发布。\n稀疏索引 & 更快的构建");
+    assert_eq!(news.abstracts.as_deref(), Some("Rust 1.70 正式发布"));
+    assert_eq!(news.source, "示例新闻");
+    assert_eq!(news.link, "https://news.example.com/1?from=rss");
+    assert_eq!(news.tags, vec!["科技", "Rust"]);
+
+    // 只有摘要时作为正文
+    let news = to_news(&feed.items[1], &feed.title);
+    assert_eq!(news.content, "只有摘要");
+    assert_eq!(news.abstracts, None);
+    assert!(news.tags.is_empty());
+}
@@ -0,0 +1,206 @@
+// RSS 2.0 / Atom 解析
+// 只取生成新闻需要的字段，不校验完整的规范
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use crate::common::html::collapse_whitespace;
+#[cfg(test)]
+use crate::common::html::html_to_text;
+
+/// 订阅中的一条新闻
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FeedItem {
+    /// RSS 的 guid 或 Atom 的 id，没有时使用 link
+    pub guid: String,
+    pub link: String,
+    pub title: String,
+    /// RSS 的 description 或 Atom 的 summary，可能为 HTML
+    pub summary: String,
+    /// RSS 的 content:encoded 或 Atom 的 content，可能为 HTML
+    pub content: String,
+    /// 分类，转换为新闻 tag
+    pub categories: Vec<String>,
+}
+
+/// 解析后的订阅
+#[derive(Debug, Default)]
+pub struct Feed {
+    pub title: String,
+    pub items: Vec<FeedItem>,
+}
+
+fn name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.name().as_ref()).into_owned()
+}
+
+fn attribute(element: &BytesStart, key: &str) -> Option<String> {
+    element
+        .try_get_attribute(key)
+        .ok()
+        .flatten()
+        .and_then(|attr| attr.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// Atom 的 link 与 category 使用属性保存内容
+fn read_attributes(item: &mut FeedItem, path: &[String], element: &BytesStart) {
+    if !matches!(path.last().map(String::as_str), Some("item" | "entry")) {
+        return;
+    }
+    match name(element).as_str() {
+        "link" => {
+            let rel = attribute(element, "rel");
+            if matches!(rel.as_deref(), None | Some("alternate")) {
+                if let Some(href) = attribute(element, "href") {
+                    item.link = href;
+                }
+            }
+        }
+        "category" => {
+            if let Some(term) = attribute(element, "term") {
+                item.categories.push(term);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn append_text(feed: &mut Feed, item: &mut Option<FeedItem>, path: &[String], text: &str) {
+    let (element, parent) = match path {
+        [.., parent, element] => (element.as_str(), parent.as_str()),
+        _ => return,
+    };
+    match item {
+        Some(item) if parent == "item" || parent == "entry" => {
+            let field = match element {
+                "title" => &mut item.title,
+                "link" => &mut item.link,
+                "description" | "summary" => &mut item.summary,
+                "content:encoded" | "content" => &mut item.content,
+                "guid" | "id" => &mut item.guid,
+                "category" => {
+                    item.categories.push(text.to_string());
+                    return;
+                }
+                _ => return,
+            };
+            field.push_str(text);
+        }
+        None if element == "title" && (parent == "channel" || parent == "feed") => {
+            feed.title.push_str(text)
+        }
+        _ => (),
+    }
+}
+
+/// 解析 RSS 2.0 或 Atom 文档
+pub fn parse(xml: &str) -> anyhow::Result<Feed> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut feed = Feed::default();
+    let mut item: Option<FeedItem> = None;
+    let mut path: Vec<String> = Vec::new();
+    let mut root = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                let name = name(&element);
+                if path.is_empty() {
+                    if root || !matches!(name.as_str(), "rss" | "feed" | "rdf:RDF") {
+                        anyhow::bail!("不是 RSS 或 Atom 文档: <{}>", name);
+                    }
+                    root = true;
+                }
+                match (name.as_str(), &mut item) {
+                    ("item" | "entry", None) => item = Some(FeedItem::default()),
+                    (_, Some(item)) => read_attributes(item, &path, &element),
+                    _ => (),
+                }
+                path.push(name);
+            }
+            Event::Empty(element) => {
+                if let Some(item) = &mut item {
+                    read_attributes(item, &path, &element);
+                }
+            }
+            Event::Text(text) => {
+                let text = text
+                    .unescape()
+                    .map(|text| text.into_owned())
+                    .unwrap_or_else(|_| String::from_utf8_lossy(&text).into_owned());
+                append_text(&mut feed, &mut item, &path, &text);
+            }
+            Event::CData(text) => {
+                let text = String::from_utf8_lossy(&text).into_owned();
+                append_text(&mut feed, &mut item, &path, &text);
+            }
+            Event::End(_) => {
+                if let Some(name) = path.pop() {
+                    if name == "item" || name == "entry" {
+                        feed.items.extend(item.take());
+                    }
+                }
+            }
+            Event::Eof if root && path.is_empty() => break,
+            Event::Eof => anyhow::bail!("文档不完整"),
+            _ => (),
+        }
+    }
+
+    for item in &mut feed.items {
+        item.title = collapse_whitespace(&item.title);
+        item.link = item.link.trim().to_string();
+        item.guid = match item.guid.trim() {
+            "" => item.link.clone(),
+            guid => guid.to_string(),
+        };
+    }
+    feed.title = collapse_whitespace(&feed.title);
+    Ok(feed)
+}
+
+#[test]
+fn parse_rss_fixture() {
+    let feed = parse(include_str!("fixtures/rss.xml")).unwrap();
+    assert_eq!(feed.title, "示例新闻");
+    assert_eq!(feed.items.len(), 2);
+
+    let item = &feed.items[0];
+    assert_eq!(item.guid, "https://news.example.com/1");
+    assert_eq!(item.link, "https://news.example.com/1?from=rss");
+    assert_eq!(item.title, "Rust 1.70 发布");
+    assert_eq!(item.categories, vec!["科技", "Rust"]);
+    assert_eq!(
+        html_to_text(&item.content),
+        "Rust 1.70 正式发布。\n稀疏索引 & 更快的构建"
+    );
+    assert_eq!(html_to_text(&item.summary), "Rust 1.70 正式发布");
+
+    // 没有 guid 时使用 link
+    let item = &feed.items[1];
+    assert_eq!(item.guid, "https://news.example.com/2");
+    assert!(item.content.is_empty());
+    assert!(item.categories.is_empty());
+}
+
+#[test]
+fn parse_atom_fixture() {
+    let feed = parse(include_str!("fixtures/atom.xml")).unwrap();
+    assert_eq!(feed.title, "Example Atom");
+    assert_eq!(feed.items.len(), 1);
+
+    let item = &feed.items[0];
+    assert_eq!(item.guid, "urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a");
+    assert_eq!(item.link, "https://atom.example.com/2023/06/rust");
+    assert_eq!(item.title, "Atom <entry> 示例");
+    assert_eq!(item.categories, vec!["rust", "后端"]);
+    assert_eq!(html_to_text(&item.content), "第一段\n第二段");
+
+    assert!(parse("<html><body></body></html>").is_err());
+    assert!(parse("<rss><channel>").is_err());
+    assert!(parse("").is_err());
+}
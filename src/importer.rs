@@ -0,0 +1,351 @@
+// 新闻批量导入
+// /admin/news/bulk 接口与 import 子命令共用同一套解析与写入逻辑
+
+use std::path::Path;
+
+use sqlx::Acquire;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::{info, warn};
+use validator::Validate;
+
+use crate::common::{
+    data::{self, DbPool},
+    object::news::{BulkImportResponse, BulkRowResult, BulkRowStatus, CreateNewsRequest},
+    validate::field_errors,
+};
+
+/// CSV 中 tags 列的分隔符
+const CSV_TAG_SEPARATOR: char = '|';
+
+/// 导入文件格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// 每行一条 JSON 格式的 CreateNewsRequest
+    Jsonl,
+    /// 首行为表头，列名与 CreateNewsRequest 的字段相同，tags 列使用 | 分隔
+    Csv,
+}
+
+impl Format {
+    /// 按扩展名判断文件格式，默认为 JSONL
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ => Format::Jsonl,
+        }
+    }
+}
+
+/// 待导入的一行
+pub struct Row {
+    /// 行号，从 1 开始
+    pub line: i32,
+    /// 解析结果，失败时为错误原因
+    pub news: Result<CreateNewsRequest, String>,
+}
+
+/// 解析一行 JSON
+pub fn parse_json(line: i32, text: &str) -> Row {
+    Row {
+        line,
+        news: serde_json::from_str(text).map_err(|e| format!("JSON 格式错误: {}", e)),
+    }
+}
+
+/// CSV 解析，字段规则参照 RFC 4180
+/// - 字段可以用双引号包裹，引号内的逗号与换行属于字段内容，两个连续的双引号表示一个双引号
+#[derive(Default)]
+pub struct CsvReader {
+    header: Option<Vec<String>>,
+    record: String,
+    record_line: i32,
+    quotes: usize,
+}
+
+impl CsvReader {
+    /// 读入一行，凑成一条完整记录时返回该记录
+    /// - 表头行以及空行返回 None
+    pub fn push_line(&mut self, line: i32, text: &str) -> Option<Row> {
+        if self.record.is_empty() {
+            self.record_line = line;
+        } else {
+            self.record.push('\n');
+        }
+        self.record.push_str(text);
+        self.quotes += text.matches('"').count();
+        // 引号未闭合，字段中包含换行
+        if self.quotes % 2 == 1 {
+            return None;
+        }
+
+        let record = std::mem::take(&mut self.record);
+        self.quotes = 0;
+        if record.trim().is_empty() {
+            return None;
+        }
+        let fields = split_record(&record);
+        match &self.header {
+            None => {
+                self.header = Some(fields.into_iter().map(|f| f.trim().to_string()).collect());
+                None
+            }
+            Some(header) => Some(Row {
+                line: self.record_line,
+                news: to_news(header, fields),
+            }),
+        }
+    }
+
+    /// 文件结束时仍未闭合的记录
+    pub fn finish(self) -> Option<Row> {
+        match self.record.is_empty() {
+            true => None,
+            false => Some(Row {
+                line: self.record_line,
+                news: Err("CSV 格式错误: 引号未闭合".to_string()),
+            }),
+        }
+    }
+}
+
+fn split_record(record: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = record.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn to_news(header: &[String], fields: Vec<String>) -> Result<CreateNewsRequest, String> {
+    if fields.len() != header.len() {
+        return Err(format!(
+            "CSV 格式错误: 表头有 {} 列，该行有 {} 列",
+            header.len(),
+            fields.len()
+        ));
+    }
+    let mut news = CreateNewsRequest {
+        title: String::new(),
+        content: String::new(),
+        source: String::new(),
+        abstracts: None,
+        link: String::new(),
+        tags: Vec::new(),
+    };
+    for (name, value) in header.iter().zip(fields) {
+        match name.as_str() {
+            "title" => news.title = value,
+            "content" => news.content = value,
+            "source" => news.source = value,
+            "abstracts" if !value.is_empty() => news.abstracts = Some(value),
+            "link" => news.link = value,
+            "tags" => {
+                news.tags = value
+                    .split(CSV_TAG_SEPARATOR)
+                    .map(|tag| tag.trim().to_string())
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            }
+            _ => (),
+        }
+    }
+    Ok(news)
+}
+
+fn result(
+    line: i32,
+    status: BulkRowStatus,
+    news_id: Option<i32>,
+    message: Option<String>,
+) -> BulkRowResult {
+    BulkRowResult {
+        line,
+        status,
+        news_id,
+        message,
+    }
+}
+
+/// 在同一个事务中导入一批新闻
+/// - 每一行使用单独的 savepoint，单行写入失败不影响同一批的其他行
+pub async fn ingest(pool: &DbPool, rows: Vec<Row>) -> anyhow::Result<Vec<BulkRowResult>> {
+    let mut results = Vec::with_capacity(rows.len());
+    let mut tx = pool.begin().await?;
+    // 词典与语料规模每批只加载一次
+    let mut tagger = data::auto_tag::Tagger::load(&mut tx).await?;
+    for row in rows {
+        let news = match row.news {
+            Ok(news) => news,
+            Err(message) => {
+                results.push(result(
+                    row.line,
+                    BulkRowStatus::Invalid,
+                    None,
+                    Some(message),
+                ));
+                continue;
+            }
+        };
+        if let Err(errors) = news.validate() {
+            let message = field_errors(errors)
+                .into_iter()
+                .map(|error| format!("{}: {}", error.field, error.message))
+                .collect::<Vec<String>>()
+                .join("; ");
+            results.push(result(
+                row.line,
+                BulkRowStatus::Invalid,
+                None,
+                Some(message),
+            ));
+            continue;
+        }
+
+        let mut savepoint = tx.begin().await?;
+        match data::news::insert_new_news(
+            &mut savepoint,
+            news.title,
+            news.content,
+            news.abstracts,
+            news.source,
+            news.tags,
+            news.link,
+            tagger.as_mut(),
+        )
+        .await
+        {
+            Ok(Some(news_id)) => {
+                savepoint.commit().await?;
+                results.push(result(
+                    row.line,
+                    BulkRowStatus::Inserted,
+                    Some(news_id),
+                    None,
+                ));
+            }
+            Ok(None) => {
+                savepoint.commit().await?;
+                results.push(result(row.line, BulkRowStatus::Duplicate, None, None));
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                results.push(result(
+                    row.line,
+                    BulkRowStatus::Invalid,
+                    None,
+                    Some(e.to_string()),
+                ));
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok(results)
+}
+
+/// 统计导入结果
+pub fn summarize(rows: Vec<BulkRowResult>) -> BulkImportResponse {
+    let count = |status| rows.iter().filter(|row| row.status == status).count() as i32;
+    BulkImportResponse {
+        inserted: count(BulkRowStatus::Inserted),
+        duplicate: count(BulkRowStatus::Duplicate),
+        invalid: count(BulkRowStatus::Invalid),
+        rows,
+    }
+}
+
+/// import 子命令，逐行读取本地文件，每 batch_size 行在一个事务中写入
+pub async fn run(path: &Path, format: Format, batch_size: usize) -> anyhow::Result<()> {
+    let pool = data::connect(1).await?;
+    let file = tokio::fs::File::open(path).await?;
+    let mut lines = BufReader::new(file).lines();
+
+    let mut csv = CsvReader::default();
+    let mut batch = Vec::with_capacity(batch_size);
+    let (mut inserted, mut duplicate, mut invalid) = (0, 0, 0);
+    let mut line = 0;
+    loop {
+        let text = lines.next_line().await?;
+        let row = match (&text, format) {
+            (Some(text), Format::Jsonl) => {
+                line += 1;
+                match text.trim().is_empty() {
+                    true => None,
+                    false => Some(parse_json(line, text)),
+                }
+            }
+            (Some(text), Format::Csv) => {
+                line += 1;
+                csv.push_line(line, text)
+            }
+            (None, _) => std::mem::take(&mut csv).finish(),
+        };
+        batch.extend(row);
+
+        if batch.len() >= batch_size || (text.is_none() && !batch.is_empty()) {
+            for row in ingest(&pool, std::mem::take(&mut batch)).await? {
+                match row.status {
+                    BulkRowStatus::Inserted => inserted += 1,
+                    BulkRowStatus::Duplicate => duplicate += 1,
+                    BulkRowStatus::Invalid => {
+                        invalid += 1;
+                        warn!("line {}: {}", row.line, row.message.unwrap_or_default());
+                    }
+                }
+            }
+            info!(
+                "imported {} lines: {} inserted, {} duplicate, {} invalid",
+                line, inserted, duplicate, invalid
+            );
+        }
+        if text.is_none() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn csv_reader_handles_quoted_fields() {
+    let mut reader = CsvReader::default();
+    let lines = [
+        "title,content,source,link,tags",
+        "标题,\"第一行, 含逗号",
+        "第二行 \"\"引号\"\"\",来源,http://a,科技| rust ",
+        "",
+        "缺列,内容",
+        "未闭合,\"内容",
+    ];
+    let rows = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, text)| reader.push_line(i as i32 + 1, text))
+        .collect::<Vec<Row>>();
+    assert_eq!(rows.len(), 2);
+
+    assert_eq!(rows[0].line, 2);
+    let news = rows[0].news.as_ref().unwrap();
+    assert_eq!(news.title, "标题");
+    assert_eq!(news.content, "第一行, 含逗号\n第二行 \"引号\"");
+    assert_eq!(news.source, "来源");
+    assert_eq!(news.abstracts, None);
+    assert_eq!(news.tags, vec!["科技", "rust"]);
+
+    assert_eq!(rows[1].line, 5);
+    assert!(rows[1].news.is_err());
+
+    let row = reader.finish().unwrap();
+    assert_eq!(row.line, 6);
+    assert!(row.news.is_err());
+}
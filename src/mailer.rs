@@ -0,0 +1,131 @@
+use std::{path::PathBuf, sync::Arc};
+
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{MailSender, SmtpSecurity, CONFIG};
+
+/// 邮件发送接口
+#[async_trait::async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()>;
+}
+
+pub type SharedMailer = Arc<dyn Mailer>;
+
+/// 根据配置创建 Mailer
+pub fn from_config() -> anyhow::Result<SharedMailer> {
+    let mail = &CONFIG.mail;
+    let mailer: SharedMailer = match mail.sender {
+        MailSender::Log => Arc::new(LogMailer),
+        MailSender::File => Arc::new(FileMailer::new(mail.file.clone())),
+        MailSender::Smtp => Arc::new(SmtpMailer::new()?),
+    };
+    Ok(mailer)
+}
+
+/// 通过 SMTP 发送邮件
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new() -> anyhow::Result<Self> {
+        let mail = &CONFIG.mail;
+        let builder = match mail.smtp_security {
+            SmtpSecurity::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&mail.smtp_host)?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&mail.smtp_host)?,
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&mail.smtp_host)
+            }
+        };
+        let builder = builder.port(mail.smtp_port);
+        let builder = match mail.smtp_username.is_empty() {
+            true => builder,
+            false => builder.credentials(Credentials::new(
+                mail.smtp_username.clone(),
+                mail.smtp_password.clone(),
+            )),
+        };
+        Ok(Self {
+            transport: builder.build(),
+            from: mail.from.parse()?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse()?)
+            .subject(subject)
+            .body(body.to_string())?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// 将邮件追加写入文件，用于开发与测试
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        let content = format!("To: {}\nSubject: {}\n\n{}\n\n", to, subject, body);
+        file.write_all(content.as_bytes()).await?;
+        // tokio 的文件写入在后台线程完成，需要 flush 确保返回前已写入
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// 只在日志中输出邮件内容
+pub struct LogMailer;
+
+#[async_trait::async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        tracing::info!("mail to {}: {}\n{}", to, subject, body);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn file_mailer_appends_mails() {
+    let path = std::env::temp_dir().join(format!("nrs-mail-{}.txt", std::process::id()));
+    let mailer = FileMailer::new(path.clone());
+    mailer
+        .send("a@example.com", "first", "hello")
+        .await
+        .unwrap();
+    mailer
+        .send("b@example.com", "second", "world")
+        .await
+        .unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(content.contains("To: a@example.com\nSubject: first\n\nhello"));
+    assert!(content.contains("To: b@example.com\nSubject: second\n\nworld"));
+}
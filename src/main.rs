@@ -24,19 +24,126 @@ mod util;
 /// RPC 模块
 mod rpc;
 
+/// TOTP 两步验证模块
+mod totp;
+
+/// 邮件发送模块
+mod mailer;
+
 /// Server 启动主要模块
 mod server;
 
+/// 新闻批量导入模块
+mod importer;
+
+/// RSS/Atom 订阅抓取模块
+mod feed;
+
 #[cfg(test)]
 mod test;
 
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::{arg, value_parser, ArgMatches, Command};
 use tracing::info;
 use tracing_appender::{non_blocking, rolling};
 use tracing_subscriber::{
     fmt, prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt,
 };
 
-use crate::config::CONFIG;
+use crate::{
+    common::data::{self, migrate},
+    config::CONFIG,
+    importer::Format,
+};
+
+fn cli() -> Command {
+    Command::new("server")
+        .about("新闻推荐系统后端，不带子命令时启动 HTTP 服务")
+        .subcommand(
+            Command::new("import")
+                .about("从 JSONL 或 CSV 文件批量导入新闻")
+                .arg(arg!(<FILE> "待导入的文件").value_parser(value_parser!(PathBuf)))
+                .arg(
+                    arg!(--format <FORMAT> "文件格式，默认按扩展名判断")
+                        .value_parser(["jsonl", "csv"]),
+                )
+                .arg(
+                    arg!(--"batch-size" <SIZE> "每个事务写入的行数")
+                        .value_parser(value_parser!(usize))
+                        .default_value("500"),
+                ),
+        )
+        .subcommand(
+            Command::new("summarize")
+                .about("重新生成自动生成的新闻摘要")
+                .arg(arg!(--all "重新生成所有新闻的摘要，包括编辑填写的摘要"))
+                .arg(
+                    arg!(--"batch-size" <SIZE> "每条语句更新的新闻数")
+                        .value_parser(value_parser!(i64).range(1..))
+                        .default_value("500"),
+                ),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("管理数据库迁移")
+                .subcommand_required(true)
+                .subcommand(Command::new("run").about("执行所有未执行的迁移"))
+                .subcommand(
+                    Command::new("revert")
+                        .about("回滚迁移，默认回滚最新的一个")
+                        .arg(
+                            arg!(--target <VERSION> "回滚到的版本，0 表示全部回滚")
+                                .value_parser(value_parser!(i64).range(0..)),
+                        ),
+                )
+                .subcommand(Command::new("status").about("查看迁移的执行状态"))
+                .subcommand(
+                    Command::new("baseline")
+                        .about(
+                            "将不超过指定版本的迁移标记为已执行，用于由旧版 init.sql 创建的数据库",
+                        )
+                        .arg(
+                            arg!(<VERSION> "已有的版本，fixN 对应版本 N + 1")
+                                .value_parser(value_parser!(i64)),
+                        ),
+                ),
+        )
+}
+
+async fn migrate(matches: &ArgMatches) -> anyhow::Result<()> {
+    let pool = data::connect(1).await?;
+    match matches.subcommand() {
+        Some(("revert", matches)) => {
+            let target = match matches.get_one::<i64>("target") {
+                Some(&target) => target,
+                None => (migrate::current_version(&pool).await? - 1).max(0),
+            };
+            migrate::revert(&pool, target).await?;
+            info!("migrate revert finish: database at version {}", target);
+        }
+        Some(("status", _)) => {
+            for m in migrate::status(&pool).await? {
+                let state = if m.applied { "applied" } else { "pending" };
+                println!("{:>4} {:<8} {}", m.version, state, m.description);
+            }
+        }
+        Some(("baseline", matches)) => {
+            let version = *matches.get_one::<i64>("VERSION").unwrap();
+            let count = migrate::baseline(&pool, version).await?;
+            info!(
+                "migrate baseline finish: {} migrations marked as applied",
+                count
+            );
+        }
+        _ => {
+            let count = migrate::run(&pool).await?;
+            info!("migrate run finish: {} migrations applied", count);
+        }
+    }
+    Ok(())
+}
 
 #[tokio::main]
 async fn main() {
@@ -46,7 +153,7 @@ async fn main() {
     let formatting_layer = fmt::layer();
     // 2. server file layer
     let file_appender = rolling::daily("logs", CONFIG.server.log_file.clone());
-    let (non_blocking_appender, _guard) = non_blocking(file_appender);
+    let (non_blocking_appender, guard) = non_blocking(file_appender);
     let server_file_layer = fmt::layer()
         .with_ansi(false)
         .with_line_number(true)
@@ -62,8 +169,40 @@ async fn main() {
         .init();
     info!("Tracing logger initialized");
 
-    // Server 启动
-    if let Err(e) = server::run().await {
-        tracing::error!("server error: {}", e);
+    let result = match cli().get_matches().subcommand() {
+        Some(("import", matches)) => {
+            let path = matches.get_one::<PathBuf>("FILE").unwrap();
+            let format = match matches.get_one::<String>("format").map(String::as_str) {
+                Some("csv") => Format::Csv,
+                Some(_) => Format::Jsonl,
+                None => Format::from_path(path),
+            };
+            let batch_size = *matches.get_one::<usize>("batch-size").unwrap();
+            importer::run(path, format, batch_size.max(1))
+                .await
+                .context("import error")
+        }
+        Some(("summarize", matches)) => {
+            let all = matches.get_flag("all");
+            let batch_size = *matches.get_one::<i64>("batch-size").unwrap();
+            let result = match data::connect(1).await {
+                Ok(pool) => data::news::regenerate_abstracts(&pool, all, batch_size).await,
+                Err(e) => Err(e),
+            };
+            result
+                .map(|count| info!("summarize finish: {} abstracts regenerated", count))
+                .context("summarize error")
+        }
+        Some(("migrate", matches)) => migrate(matches).await.context("migrate error"),
+        // Server 启动
+        _ => server::run().await.context("server error"),
+    };
+
+    // 失败时以非零状态码退出，便于脚本与部署工具判断
+    if let Err(e) = result {
+        tracing::error!("{:#}", e);
+        // exit 不会执行析构，先释放 guard 把日志写入文件
+        drop(guard);
+        std::process::exit(1);
     }
 }
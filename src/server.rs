@@ -3,13 +3,14 @@ use hmac::Hmac;
 use poem::{listener::TcpListener, EndpointExt, Route, Server};
 use poem_openapi::OpenApiService;
 use sha2::Sha256;
-use sqlx::postgres::PgPoolOptions;
 use tracing::info;
 
 use crate::{
     api::{AdminApi, CommonApi, NewsApi, UserApi},
     backend,
+    common::data::{self, revoke::RevokeStore},
     config::CONFIG,
+    feed, mailer,
 };
 
 pub async fn run() -> anyhow::Result<()> {
@@ -18,27 +19,44 @@ pub async fn run() -> anyhow::Result<()> {
     info!("Starting to connect to database");
 
     // 初始化数据库连接池
-    let database_url = format!(
-        "postgres://{}:{}@{}:{}/{}",
-        CONFIG.database.user_name,
-        CONFIG.database.password,
-        CONFIG.database.host,
-        CONFIG.database.port,
-        CONFIG.database.db
-    );
-    let pool = PgPoolOptions::new()
-        .min_connections(5)
-        .max_connections(15)
-        .connect(&database_url)
-        .await?;
+    let pool = data::connect(15).await?;
+    // 检查并执行数据库迁移
+    if CONFIG.migrate.run_on_start {
+        let count = data::migrate::run(&pool).await?;
+        info!(
+            "database migrated to version {} ({} applied)",
+            data::migrate::latest_version(),
+            count
+        );
+    } else {
+        let pending = data::migrate::check(&pool).await?;
+        if pending > 0 {
+            anyhow::bail!("数据库还有 {} 个迁移未执行，请先执行 migrate run", pending);
+        }
+    }
 
     info!("Starting to set backend tasks...");
     // 启动后台任务
     backend::start(pool.clone()).await?;
+    // 启动订阅抓取任务
+    feed::start(pool.clone()).await?;
+    // 为旧数据补充正文指纹
+    let backfill_pool = pool.clone();
+    tokio::spawn(async move {
+        match data::news::backfill_fingerprints(&backfill_pool).await {
+            Ok(0) => (),
+            Ok(count) => info!("backfilled fingerprints of {} news", count),
+            Err(e) => tracing::error!("backfill fingerprints error: {}", e),
+        }
+    });
 
     info!("Starting to initialize server");
     // 初始化 server key
     let server_key = Hmac::<Sha256>::new_from_slice(CONFIG.server.server_key.as_bytes())?;
+    // 初始化 token 注销存储
+    let revoke_store = RevokeStore::new(pool.clone()).await;
+    // 初始化邮件发送
+    let mailer = mailer::from_config()?;
 
     // 初始化 OpenApi 服务
     let api_url = format!("http://localhost:{}/api", CONFIG.server.api_port);
@@ -67,7 +85,9 @@ pub async fn run() -> anyhow::Result<()> {
     let router = router
         .with(poem::middleware::Tracing)
         .data(pool)
-        .data(server_key);
+        .data(server_key)
+        .data(revoke_store)
+        .data(mailer);
 
     // 启动服务器
     let server_url = format!("0.0.0.0:{}", CONFIG.server.api_port);
@@ -1,7 +1,136 @@
-use crate::config::CONFIG;
+use argon2::{
+    password_hash::{Output, SaltString},
+    Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier,
+};
+use std::{net::IpAddr, sync::Arc};
 
-// TOO SLOW!!!
-pub fn calc_password_hash(password: &str, dyn_salt: &str) -> String {
+use once_cell::sync::Lazy;
+use poem::Request;
+use poem_openapi::payload::Json;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use tokio::sync::Semaphore;
+
+use crate::{
+    common::{ApiError, ErrorMessage},
+    config::CONFIG,
+};
+
+/// 限制同时进行的密码 hash 计算数量，避免 argon2 占满阻塞线程池
+static HASH_PERMITS: Lazy<Arc<Semaphore>> =
+    Lazy::new(|| Arc::new(Semaphore::new(CONFIG.password.max_concurrency.max(1))));
+
+/// 密码校验结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum PasswordCheck {
+    /// 密码错误
+    Invalid,
+    /// 密码正确
+    Valid,
+    /// 密码正确，但 hash 为旧格式或参数已过时，需要重新计算
+    NeedsRehash,
+}
+
+fn argon2_instance() -> anyhow::Result<Argon2<'static>> {
+    let params = Params::new(
+        CONFIG.password.m_cost,
+        CONFIG.password.t_cost,
+        CONFIG.password.p_cost,
+        None,
+    )
+    .map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(Argon2::new(
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        params,
+    ))
+}
+
+/// 在阻塞线程池中执行 hash 计算，并发数达到上限时直接返回 503
+async fn run_hash_task<T, F>(task: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let permit = HASH_PERMITS
+        .clone()
+        .try_acquire_owned()
+        .map_err(|_| ApiError::ServiceBusy(1))?;
+    tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        task()
+    })
+    .await
+    .map_err(|e| ApiError::Error(Json(ErrorMessage::new(e))))
+}
+
+/// calc_password_hash 的异步版本
+pub async fn hash_password_async(password: String) -> Result<String, ApiError> {
+    run_hash_task(move || calc_password_hash(&password))
+        .await?
+        .map_err(|e| ApiError::Error(Json(ErrorMessage::new(e))))
+}
+
+/// verify_password 的异步版本
+pub async fn verify_password_async(
+    password: String,
+    hash: String,
+    username: String,
+) -> Result<PasswordCheck, ApiError> {
+    run_hash_task(move || verify_password(&password, &hash, &username)).await
+}
+
+/// 计算 PHC 格式的密码 hash，每次使用随机 salt
+/// - 计算开销较大，在 async 上下文中应使用 hash_password_async
+pub fn calc_password_hash(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::generate(&mut rand::rngs::OsRng);
+    let hash = argon2_instance()?
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(hash.to_string())
+}
+
+/// 校验密码
+/// - PHC 格式的 hash 使用 PasswordVerifier 校验
+/// - 兼容旧版本以用户名与全局 salt 计算的 hex hash
+pub fn verify_password(password: &str, hash: &str, username: &str) -> PasswordCheck {
+    let parsed_hash = match PasswordHash::new(hash) {
+        Ok(parsed_hash) => parsed_hash,
+        Err(_) => {
+            return match (
+                calc_legacy_password_hash(password, username),
+                hex_output(hash),
+            ) {
+                (Some(output), Some(expected)) if output == expected => PasswordCheck::NeedsRehash,
+                _ => PasswordCheck::Invalid,
+            }
+        }
+    };
+
+    if Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .is_err()
+    {
+        return PasswordCheck::Invalid;
+    }
+
+    // hash 参数与当前配置不一致时需要重新计算
+    let outdated = match Params::try_from(&parsed_hash) {
+        Ok(params) => {
+            params.m_cost() != CONFIG.password.m_cost
+                || params.t_cost() != CONFIG.password.t_cost
+                || params.p_cost() != CONFIG.password.p_cost
+        }
+        Err(_) => true,
+    };
+    match outdated || parsed_hash.algorithm != argon2::Algorithm::Argon2id.ident() {
+        true => PasswordCheck::NeedsRehash,
+        false => PasswordCheck::Valid,
+    }
+}
+
+/// 旧版本的密码 hash，salt 由用户名与全局 salt 拼接而成
+fn calc_legacy_password_hash(password: &str, dyn_salt: &str) -> Option<Output> {
     let argon2 = argon2::Argon2::default();
     let mut output_hash = [0u8; 32];
     argon2
@@ -10,6 +139,97 @@ pub fn calc_password_hash(password: &str, dyn_salt: &str) -> String {
             &[dyn_salt.as_bytes(), CONFIG.server.salt.as_bytes()].concat(),
             &mut output_hash,
         )
-        .unwrap();
-    hex::encode(output_hash)
+        .ok()?;
+    Output::new(&output_hash).ok()
+}
+
+/// 将旧版本 hex hash 转为 Output，比较时为常数时间
+fn hex_output(hash: &str) -> Option<Output> {
+    Output::new(&hex::decode(hash).ok()?).ok()
+}
+
+/// 用于用户不存在时的校验，使两种登录失败的耗时一致
+static DUMMY_HASH: Lazy<String> =
+    Lazy::new(|| calc_password_hash("nrs-dummy-password").unwrap_or_default());
+
+/// 用户不存在时执行一次同等开销的校验
+pub async fn verify_dummy_password(password: String) -> Result<(), ApiError> {
+    run_hash_task(move || {
+        verify_password(&password, &DUMMY_HASH, "");
+    })
+    .await
+}
+
+/// 获取客户端 IP
+/// - 仅在配置 trust_proxy 时使用反向代理传递的请求头
+pub fn client_ip(req: &Request) -> String {
+    if CONFIG.server.trust_proxy {
+        let forwarded = req
+            .header("X-Real-IP")
+            .or_else(|| {
+                req.header("X-Forwarded-For")
+                    .and_then(|value| value.split(',').next())
+            })
+            .and_then(|value| value.trim().parse::<IpAddr>().ok());
+        if let Some(ip) = forwarded {
+            return ip.to_string();
+        }
+    }
+    match req.remote_addr().as_socket_addr() {
+        Some(addr) => addr.ip().to_string(),
+        None => req.remote_addr().to_string(),
+    }
+}
+
+/// 请求来源信息，用于登录限流与会话记录
+pub struct ClientInfo {
+    pub ip: String,
+    pub user_agent: String,
+}
+
+impl ClientInfo {
+    pub fn from_request(req: &Request) -> Self {
+        let user_agent = req.header("User-Agent").unwrap_or_default();
+        ClientInfo {
+            ip: client_ip(req),
+            // 与 user_session.user_agent 长度保持一致
+            user_agent: user_agent.chars().take(511).collect(),
+        }
+    }
+}
+
+/// 生成 len 字节的随机 token，以 hex 编码返回
+pub fn gen_random_token(len: usize) -> String {
+    let mut buf = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut buf);
+    hex::encode(buf)
+}
+
+/// 计算 token 的 sha256 摘要，数据库中只保存摘要
+pub fn calc_token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[test]
+fn verify_phc_and_legacy_password() {
+    let hash = calc_password_hash("nekopara").unwrap();
+    assert!(hash.starts_with("$argon2id$"));
+    assert_eq!(
+        verify_password("nekopara", &hash, "neko"),
+        PasswordCheck::Valid
+    );
+    assert_eq!(
+        verify_password("wrong", &hash, "neko"),
+        PasswordCheck::Invalid
+    );
+
+    let legacy = hex::encode(calc_legacy_password_hash("nekopara", "neko").unwrap());
+    assert_eq!(
+        verify_password("nekopara", &legacy, "neko"),
+        PasswordCheck::NeedsRehash
+    );
+    assert_eq!(
+        verify_password("nekopara", &legacy, "other"),
+        PasswordCheck::Invalid
+    );
 }